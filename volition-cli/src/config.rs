@@ -0,0 +1,411 @@
+// volition-cli/src/config.rs
+
+//! Loads the configuration from its layers, lowest precedence first:
+//!
+//! 1. the user's global config, `~/.config/volition/config.toml` on Linux,
+//! 2. the project's `Volition.toml`, found in the current directory or a parent,
+//! 3. `VOLITION_*` environment variables.
+//!
+//! Tables are merged key by key, so a project can override a single setting of
+//! a provider defined globally; any other value, including arrays, replaces the
+//! one from a lower layer. Without a project, the global config is used alone and
+//! conversations are stored in the user data directory. Relative file paths in
+//! a config file, such as a `script` or `ca_bundle`, are relative to that file.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+use tracing::{debug, info, warn};
+use volition_core::config::AgentConfig;
+
+pub const CONFIG_FILENAME: &str = "Volition.toml";
+const ENV_PREFIX: &str = "VOLITION_";
+/// Separates the levels of a key path in environment variable names.
+const ENV_PATH_SEPARATOR: &str = "__";
+/// Top-level keys that environment variables can set. Other `VOLITION_*`
+/// variables, such as API keys, are left alone.
+const ENV_KEYS: &[&str] = &[
+    "system_prompt",
+    "default_provider",
+    "providers",
+    "mcp_servers",
+    "strategies",
+    "git_server",
+];
+/// Keys below a provider's `model_config` that hold file paths. Relative paths
+/// are resolved against the directory of the config file that sets them.
+const PATH_KEYS: &[&[&str]] = &[
+    &["script"],
+    &["cassette"],
+    &["record_to"],
+    &["http", "ca_bundle"],
+];
+
+/// A config file that was merged.
+#[derive(Debug)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// An environment variable that overrode a setting.
+#[derive(Debug)]
+pub struct EnvOverride {
+    pub name: String,
+    /// The dotted key path it set, e.g. `providers.local.model_config.model_name`.
+    pub key: String,
+}
+
+/// Where a setting was defined.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigSource<'a> {
+    /// A line of a config file, counted from 1.
+    File(&'a Path, usize),
+    Env(&'a str),
+}
+
+impl fmt::Display for ConfigSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::File(path, line) => write!(f, "{}:{}", path.display(), line),
+            ConfigSource::Env(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// The merged configuration and where it came from.
+#[derive(Debug)]
+pub struct ConfigLayers {
+    /// The directory holding `Volition.toml`, if run inside a project.
+    pub project_root: Option<PathBuf>,
+    /// The config files that were merged, lowest precedence first.
+    pub files: Vec<ConfigFile>,
+    /// The environment variables that overrode a setting, in the order applied.
+    pub env_overrides: Vec<EnvOverride>,
+    /// The merged configuration.
+    pub merged: Value,
+}
+
+impl ConfigLayers {
+    /// Where conversations are stored: the project root, or the user data
+    /// directory outside a project.
+    pub fn state_root(&self) -> Result<PathBuf> {
+        match &self.project_root {
+            Some(root) => Ok(root.clone()),
+            None => dirs::data_dir()
+                .map(|dir| dir.join("volition"))
+                .ok_or_else(|| anyhow!("Could not determine the user data directory")),
+        }
+    }
+
+    /// The layer that defines `key`, a dotted path such as
+    /// `providers.local.model_config.routes[0].provider`. A key that is not set
+    /// anywhere is attributed to its nearest ancestor that is.
+    pub fn source_of(&self, key: &str) -> Option<ConfigSource<'_>> {
+        let path = split_dotted(key);
+        (1..=path.len()).rev().find_map(|len| {
+            let prefix = &path[..len];
+            if let Some(env) = self
+                .env_overrides
+                .iter()
+                .rev()
+                .find(|env| split_dotted(&env.key).starts_with(prefix))
+            {
+                return Some(ConfigSource::Env(&env.name));
+            }
+            self.files.iter().rev().find_map(|file| {
+                find_key_line(&file.content, prefix)
+                    .map(|line| ConfigSource::File(&file.path, line))
+            })
+        })
+    }
+}
+
+/// Splits a TOML dotted key into its parts, unquoting quoted parts. Array
+/// indices in key paths stay with their key, as in `routes[0]`.
+fn split_dotted(key: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in key.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '.') => parts.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current.trim().to_string());
+    parts
+}
+
+/// The byte offset of the first `=` outside quotes, if any.
+fn find_assignment(line: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '=') => return Some(i),
+            (None, '#') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The line (counted from 1) of the first table header or key in `content`
+/// whose path starts with `path`. Array-of-tables entries are addressed as
+/// `key[index]`. This reads the file line by line rather than parsing it, which
+/// is enough to point at a setting.
+fn find_key_line(content: &str, path: &[String]) -> Option<usize> {
+    let mut table: Vec<String> = Vec::new();
+    let mut array_counts: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut in_multiline_string = false;
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let delimiters = line.matches("\"\"\"").count() + line.matches("'''").count();
+        if in_multiline_string {
+            in_multiline_string = delimiters % 2 == 0;
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let full = if let Some(header) = line.strip_prefix("[[") {
+            let header = split_dotted(header.split("]]").next().unwrap_or_default());
+            let count = array_counts.entry(header.clone()).or_default();
+            table = header;
+            if let Some(last) = table.last_mut() {
+                *last = format!("{}[{}]", last, count);
+            }
+            *count += 1;
+            table.clone()
+        } else if let Some(header) = line.strip_prefix('[') {
+            table = split_dotted(header.split(']').next().unwrap_or_default());
+            table.clone()
+        } else if let Some(eq) = find_assignment(line) {
+            in_multiline_string = delimiters % 2 == 1;
+            let mut full = table.clone();
+            full.extend(split_dotted(&line[..eq]));
+            full
+        } else {
+            continue;
+        };
+        if full.starts_with(path) {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// The user's global config file, whether or not it exists.
+pub fn global_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("volition").join("config.toml"))
+}
+
+/// The nearest directory, starting at the current one, that holds `Volition.toml`.
+pub fn find_project_root() -> Result<Option<PathBuf>> {
+    let current_dir = env::current_dir().context("Failed to get current directory")?;
+    Ok(current_dir
+        .ancestors()
+        .find(|dir| dir.join(CONFIG_FILENAME).is_file())
+        .map(Path::to_path_buf))
+}
+
+/// Merges `overlay` into `base`: tables key by key, other values replaced.
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(table)) => {
+                merge_tables(base_table, table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Makes the relative file paths of the providers in `table`, read from a
+/// config file in `dir`, absolute, so they do not depend on the directory
+/// `volition` runs in.
+fn resolve_relative_paths(table: &mut Table, dir: &Path) {
+    let Some(Value::Table(providers)) = table.get_mut("providers") else {
+        return;
+    };
+    for (id, provider) in providers.iter_mut() {
+        let Some(model_config) = provider.get_mut("model_config") else {
+            continue;
+        };
+        for key_path in PATH_KEYS {
+            let value = key_path
+                .iter()
+                .try_fold(&mut *model_config, |value, key| value.get_mut(*key));
+            if let Some(Value::String(path)) = value
+                && Path::new(path.as_str()).is_relative()
+            {
+                let resolved = dir.join(path.as_str()).display().to_string();
+                debug!(provider = %id, key = %key_path.join("."), %resolved, "Resolved relative path");
+                *path = resolved;
+            }
+        }
+    }
+}
+
+/// Reads an environment value as a TOML value (a number, boolean, array or
+/// quoted string), or else as a plain string.
+fn parse_env_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Applies `VOLITION_<PATH>` variables to `table`, where `__` separates the
+/// keys of the path, e.g. `VOLITION_PROVIDERS__LOCAL__MODEL_CONFIG__MODEL_NAME`.
+/// Returns the variables that were applied.
+fn apply_env_overrides(
+    table: &mut Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Vec<EnvOverride>> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort();
+
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let path: Vec<String> = name[ENV_PREFIX.len()..]
+            .to_lowercase()
+            .split(ENV_PATH_SEPARATOR)
+            .map(String::from)
+            .collect();
+        if !ENV_KEYS.contains(&path[0].as_str()) || path.iter().any(String::is_empty) {
+            continue;
+        }
+        let (last, parents) = path.split_last().expect("split yields at least one key");
+        let mut current = &mut *table;
+        for key in parents {
+            let entry = current
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            current = entry.as_table_mut().ok_or_else(|| {
+                anyhow!("{} sets a key below '{}', which is not a table", name, key)
+            })?;
+        }
+        let key = path.join(".");
+        debug!(var = %name, %key, "Applying environment override");
+        current.insert(last.clone(), parse_env_value(&raw));
+        applied.push(EnvOverride { name, key });
+    }
+    Ok(applied)
+}
+
+/// Finds, reads and merges the configuration layers.
+pub fn load_config_layers() -> Result<ConfigLayers> {
+    let project_root = find_project_root()?;
+    let global_path = global_config_path().filter(|path| path.is_file());
+    let project_path = project_root.as_ref().map(|root| root.join(CONFIG_FILENAME));
+
+    let paths: Vec<PathBuf> = global_path.into_iter().chain(project_path).collect();
+    if paths.is_empty() {
+        return Err(anyhow!(
+            "Could not find '{}' in the current directory or any parent directory, nor a global config at {}.",
+            CONFIG_FILENAME,
+            global_config_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "~/.config/volition/config.toml".to_string())
+        ));
+    }
+
+    let mut merged = Table::new();
+    let mut files = Vec::new();
+    for path in paths {
+        info!("Found configuration file at: {:?}", path);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut table: Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            resolve_relative_paths(&mut table, dir);
+        }
+        merge_tables(&mut merged, table);
+        files.push(ConfigFile { path, content });
+    }
+    let env_overrides = apply_env_overrides(&mut merged, env::vars())?;
+    Ok(ConfigLayers {
+        project_root,
+        files,
+        env_overrides,
+        merged: Value::Table(merged),
+    })
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct GitServerCliConfig {
+    allowed_commands: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct CliTomlConfig {
+    #[serde(default)]
+    git_server: GitServerCliConfig,
+}
+
+/// Passes `git_server.allowed_commands` to the `git` MCP server as arguments.
+fn apply_git_server_allowed_commands(config: &mut AgentConfig, merged: &Value) {
+    let allowed_commands = match merged.clone().try_into::<CliTomlConfig>() {
+        Ok(cli_config) => cli_config.git_server.allowed_commands,
+        Err(e) => {
+            warn!(error = %e, "Failed to read git_server config. Using default.");
+            None
+        }
+    };
+    let Some(allowed_commands) = allowed_commands else {
+        info!("No git_server.allowed_commands found in config. Server will use its default.");
+        return;
+    };
+    let Some(git_server_conf) = config.mcp_servers.get_mut("git") else {
+        if !allowed_commands.is_empty() {
+            warn!(
+                "git_server.allowed_commands found in TOML, but no MCP server with ID 'git' defined in config."
+            );
+        }
+        return;
+    };
+    if allowed_commands.is_empty() {
+        info!("Empty git allowed_commands list found in config. Server will use its default.");
+        return;
+    }
+    info!(commands = ?allowed_commands, "Found git allowed_commands in config. Passing to server.");
+    if !git_server_conf
+        .args
+        .contains(&"--allowed-commands".to_string())
+    {
+        git_server_conf.args.push("--allowed-commands".to_string());
+        git_server_conf.args.push(allowed_commands.join(","));
+        debug!(server_id = "git", args = ?git_server_conf.args, "Updated git server args");
+    }
+}
+
+/// Loads and validates the merged configuration, returning it with the
+/// directory conversations are stored in.
+pub fn load_cli_config() -> Result<(AgentConfig, PathBuf)> {
+    let layers = load_config_layers()?;
+    info!(
+        files = ?layers.files.iter().map(|file| &file.path).collect::<Vec<_>>(),
+        env_overrides = ?layers.env_overrides.iter().map(|env| &env.name).collect::<Vec<_>>(),
+        "Merged configuration layers"
+    );
+    let mut config = AgentConfig::from_toml_value(layers.merged.clone())
+        .context("Failed to parse or validate configuration content")?;
+    apply_git_server_allowed_commands(&mut config, &layers.merged);
+    Ok((config, layers.state_root()?))
+}
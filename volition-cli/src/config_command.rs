@@ -0,0 +1,271 @@
+// volition-cli/src/config_command.rs
+
+//! `volition config`: checks the merged configuration and the environment it
+//! needs, and prints the merged configuration with secrets masked.
+
+use crate::config::{ConfigLayers, load_config_layers};
+use anyhow::{Result, anyhow};
+use colored::*;
+use std::env;
+use std::path::{Path, PathBuf};
+use toml::value::Value;
+use url::Url;
+use volition_core::config::{AgentConfig, ConfigProblem};
+use volition_core::providers::ProviderFactoryRegistry;
+use volition_core::providers::router::ROUTER_TYPE;
+
+/// Replaces masked values in `config show`.
+const MASK: &str = "********";
+
+/// Words that mark a key, header or URL parameter as holding a secret.
+const SECRET_WORDS: &[&str] = &[
+    "auth",
+    "authorization",
+    "cookie",
+    "credentials",
+    "key",
+    "password",
+    "secret",
+    "token",
+];
+
+fn print_sources(layers: &ConfigLayers) {
+    for file in &layers.files {
+        println!("{} {}", "Config file:".bold(), file.path.display());
+    }
+    for env in &layers.env_overrides {
+        println!("{} ${} ({})", "Environment:".bold(), env.name, env.key);
+    }
+}
+
+/// The key path of a deserialization error, which toml reports as
+/// "... for key `providers.local.type`".
+fn error_key(message: &str) -> Option<&str> {
+    let start = message.find("for key `")? + "for key `".len();
+    let end = message[start..].find('`')?;
+    Some(&message[start..start + end])
+}
+
+/// Finds `command` as a path, relative to the current directory, or on `PATH`.
+fn resolve_command(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    env::split_paths(&env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", command, ext)))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Problems that depend on more than the configuration itself: unknown provider
+/// types, missing MCP server commands, unset API key variables and strategies
+/// naming unknown providers.
+fn environment_problems(config: &AgentConfig) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    let factories = ProviderFactoryRegistry::default();
+    let mut providers: Vec<_> = config.providers.iter().collect();
+    providers.sort_by_key(|(id, _)| *id);
+    for (id, provider) in providers {
+        let provider_type = provider.provider_type.as_str();
+        if !provider_type.trim().is_empty()
+            && provider_type != ROUTER_TYPE
+            && !factories.contains(provider_type)
+        {
+            problems.push(ConfigProblem::new(
+                format!("providers.{}.type", id),
+                format!(
+                    "Provider '{}' has unknown type '{}'. Supported types: {}, {}.",
+                    id,
+                    provider_type,
+                    factories.provider_types().join(", "),
+                    ROUTER_TYPE
+                ),
+            ));
+        }
+        let var = &provider.api_key_env_var;
+        if provider.needs_api_key()
+            && !var.trim().is_empty()
+            && env::var(var).map_or(true, |value| value.is_empty())
+        {
+            problems.push(ConfigProblem::new(
+                format!("providers.{}.api_key_env_var", id),
+                format!(
+                    "Environment variable '{}' holding the API key of provider '{}' is not set.",
+                    var, id
+                ),
+            ));
+        }
+    }
+
+    let mut servers: Vec<_> = config.mcp_servers.iter().collect();
+    servers.sort_by_key(|(id, _)| *id);
+    for (id, server) in servers {
+        if !server.command.trim().is_empty() && resolve_command(&server.command).is_none() {
+            problems.push(ConfigProblem::new(
+                format!("mcp_servers.{}.command", id),
+                format!(
+                    "Command '{}' of MCP server '{}' was not found on PATH or on disk.",
+                    server.command, id
+                ),
+            ));
+        }
+    }
+
+    let mut strategies: Vec<_> = config.strategies.iter().collect();
+    strategies.sort_by_key(|(id, _)| *id);
+    for (id, strategy) in strategies {
+        let references = [
+            ("planning_provider", &strategy.planning_provider),
+            ("execution_provider", &strategy.execution_provider),
+        ];
+        for (key, provider) in references {
+            if let Some(provider) = provider
+                && !config.providers.contains_key(provider)
+            {
+                problems.push(ConfigProblem::new(
+                    format!("strategies.{}.{}", id, key),
+                    format!(
+                        "Strategy '{}' uses unknown provider '{}' as its {}.",
+                        id, provider, key
+                    ),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks the merged configuration, printing each problem with the file and
+/// line (or environment variable) that set the key. Fails if any were found.
+pub fn validate_config() -> Result<()> {
+    let layers = load_config_layers()?;
+    print_sources(&layers);
+    println!();
+
+    let problems = match layers.merged.clone().try_into::<AgentConfig>() {
+        Ok(config) => {
+            let mut problems = config.problems();
+            problems.extend(environment_problems(&config));
+            problems
+        }
+        Err(e) => {
+            let message = e.to_string();
+            let key = error_key(&message).unwrap_or_default().to_string();
+            vec![ConfigProblem::new(key, message)]
+        }
+    };
+
+    if problems.is_empty() {
+        println!("{}", "Configuration is valid.".green());
+        return Ok(());
+    }
+    for problem in &problems {
+        let location = match layers.source_of(&problem.key) {
+            Some(source) => format!("{}: ", source),
+            None => String::new(),
+        };
+        let key = if problem.key.is_empty() {
+            String::new()
+        } else {
+            format!("{}: ", problem.key.bold())
+        };
+        println!(
+            "{} {}{}{}",
+            "error:".red().bold(),
+            location,
+            key,
+            problem.message
+        );
+    }
+    println!();
+    Err(anyhow!(
+        "Found {} problem(s) in the configuration",
+        problems.len()
+    ))
+}
+
+/// Whether `name` (a key, header or URL parameter) names a secret. Keys ending
+/// in `env_var` hold the name of a variable, not its value.
+fn is_secret(name: &str) -> bool {
+    let name = name.to_lowercase();
+    !name.ends_with("env_var")
+        && name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| SECRET_WORDS.contains(&word))
+}
+
+/// Masks the password and secret query parameters of a URL.
+fn mask_url(value: &str) -> Option<String> {
+    let mut url = Url::parse(value).ok()?;
+    let mut changed = false;
+    if url.password().is_some() {
+        url.set_password(Some(MASK)).ok()?;
+        changed = true;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if pairs.iter().any(|(name, _)| is_secret(name)) {
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs.iter().map(|(name, value)| {
+                (
+                    name.as_str(),
+                    if is_secret(name) {
+                        MASK
+                    } else {
+                        value.as_str()
+                    },
+                )
+            }));
+        changed = true;
+    }
+    changed.then(|| url.to_string())
+}
+
+/// Masks the values of secret keys and the credentials in URLs.
+fn mask_secrets(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if is_secret(key) && !value.is_table() {
+                    *value = Value::String(MASK.to_string());
+                } else {
+                    mask_secrets(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        Value::String(text) if text.contains("://") => {
+            if let Some(masked) = mask_url(text) {
+                *text = masked;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Prints the merged configuration as TOML, with secrets masked.
+pub fn show_config() -> Result<()> {
+    let layers = load_config_layers()?;
+    for file in &layers.files {
+        println!("# Config file: {}", file.path.display());
+    }
+    for env in &layers.env_overrides {
+        println!("# Environment: ${} ({})", env.name, env.key);
+    }
+    let mut merged = layers.merged;
+    mask_secrets(&mut merged);
+    println!();
+    print!("{}", toml::to_string_pretty(&merged)?);
+    Ok(())
+}
@@ -9,7 +9,7 @@ use std::{
     path::{Path, PathBuf}, // Added Path
 };
 use uuid::Uuid;
-use volition_core::models::chat::ChatMessage;
+use volition_core::models::chat::{ChatMessage, Role, TokenUsage};
 
 const HISTORY_SUBDIR: &str = ".volition/history"; // Store history relative to project root
 
@@ -19,10 +19,21 @@ pub struct ConversationHistory {
     pub created_at: DateTime<Utc>,
     pub last_updated_at: DateTime<Utc>,
     pub messages: Vec<ChatMessage>,
+    /// Token usage of every API call made in this conversation.
+    #[serde(default)]
+    pub usage: Vec<UsageEntry>,
     // Optional: Add title later if needed
     // pub title: Option<String>,
 }
 
+/// Token usage of one API call, stamped with the time its turn finished.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsageEntry {
+    pub recorded_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+}
+
 impl ConversationHistory {
     pub fn new(messages: Vec<ChatMessage>) -> Self {
         let now = Utc::now();
@@ -31,8 +42,18 @@ impl ConversationHistory {
             created_at: now,
             last_updated_at: now,
             messages,
+            usage: Vec::new(),
         }
     }
+
+    /// Appends the usage records of a finished turn.
+    pub fn record_usage(&mut self, usage: Vec<TokenUsage>) {
+        let now = Utc::now();
+        self.usage.extend(usage.into_iter().map(|usage| UsageEntry {
+            recorded_at: now,
+            usage,
+        }));
+    }
     // Removed append_message and append_messages as they were unused dead code
 }
 
@@ -109,25 +130,25 @@ pub fn list_histories(project_root: &Path) -> Result<Vec<ConversationHistory>> {
     {
         let entry = entry?;
         let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                if let Ok(id) = Uuid::parse_str(stem) {
-                    // Load the full history to sort easily
-                    // Pass project_root to the load_history call
-                    match load_history(project_root, id) {
-                        Ok(history) => histories.push(history),
-                        Err(e) => {
-                            // Log error or handle corrupted files?
-                            eprintln!("Warning: Failed to load history file {:?}: {}", path, e);
-                        }
-                    }
+        if path.is_file()
+            && path.extension().is_some_and(|ext| ext == "json")
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            && let Ok(id) = Uuid::parse_str(stem)
+        {
+            // Load the full history to sort easily
+            // Pass project_root to the load_history call
+            match load_history(project_root, id) {
+                Ok(history) => histories.push(history),
+                Err(e) => {
+                    // Log error or handle corrupted files?
+                    eprintln!("Warning: Failed to load history file {:?}: {}", path, e);
                 }
             }
         }
     }
 
     // Sort by last updated time, newest first
-    histories.sort_by(|a, b| b.last_updated_at.cmp(&a.last_updated_at));
+    histories.sort_by_key(|h| std::cmp::Reverse(h.last_updated_at));
 
     Ok(histories)
 }
@@ -136,10 +157,11 @@ pub fn list_histories(project_root: &Path) -> Result<Vec<ConversationHistory>> {
 pub fn get_history_preview(history: &ConversationHistory) -> String {
     history.messages
         .iter()
-        .find(|m| m.role.to_lowercase() == "user") // Find first user message
+        .find(|m| m.role == Role::User) // Find first user message
         .map(|m| {
-            // Handle Option<String> safely by providing a default empty string slice
-            let content_str = m.content.as_deref().unwrap_or("");
+            // The full text, including any text parts
+            let content = m.text();
+            let content_str = content.as_str();
             let preview: String = content_str.chars().take(70).collect();
             if content_str.chars().count() > 70 {
                 format!("{}...", preview)
@@ -1,45 +1,48 @@
 // volition-cli/src/main.rs
+mod config;
+mod config_command;
 mod models;
 mod rendering;
 mod history;
+mod model_list;
+mod usage;
 
 use anyhow::{anyhow, Context, Result};
 use colored::*;
-use serde::Deserialize;
 use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::sync::Arc;
-use toml;
 use uuid::Uuid;
-use chrono;
 
 use rustyline::error::ReadlineError;
 use rustyline::{Config, DefaultEditor};
 use indicatif::{ProgressBar, ProgressStyle};
-use dirs;
 use dialoguer::{Confirm, theme::ColorfulTheme};
 
 use volition_core::{
     agent::Agent,
     async_trait,
     config::AgentConfig,
-    errors::AgentError,
+    errors::{AgentError, ProviderError},
+    providers::cache::{clear_cache, default_cache_dir},
     strategies::{
         complete_task::CompleteTaskStrategy,
         plan_execute::PlanExecuteStrategy,
     },
-    UserInteraction,
+    ChatMessage, ContentPart, ImageSource, Role, StreamDelta, UserInteraction,
 };
 
 // Use models::cli::Cli directly since Commands is unused now
-use crate::models::cli::{Commands}; // Keep Commands import for matching
+use crate::models::cli::{CacheAction, Commands, ConfigAction, UsageGrouping}; // Keep Commands import for matching
+use crate::config::load_cli_config;
 use crate::rendering::print_formatted;
 use crate::history::{ // Keep ConversationHistory import
     save_history, load_history, list_histories, delete_history, get_history_preview, ConversationHistory
 };
+use crate::usage::{running_total, UsageReport};
 
 use clap::Parser;
 use tracing::{debug, error, info, warn, Level};
@@ -51,91 +54,148 @@ use tracing_subscriber::{
 };
 
 
-const CONFIG_FILENAME: &str = "Volition.toml";
 const LOG_FILE_NAME: &str = "volition-app.log";
 
 type CliAgent = Agent<CliUserInteraction>;
 type CliStrategy = Box<dyn volition_core::Strategy<CliUserInteraction> + Send + Sync>;
 
-struct CliUserInteraction;
+#[derive(Default)]
+struct CliUserInteraction {
+    stream: std::sync::Mutex<StreamOutput>,
+}
 
-#[async_trait]
-impl UserInteraction for CliUserInteraction {
-    async fn ask(&self, prompt: String, _options: Vec<String>) -> Result<String> {
-        // TODO: Consider using dialoguer::Input here for a nicer prompt
+/// Terminal state for streamed output during a single agent turn.
+#[derive(Default)]
+struct StreamOutput {
+    spinner: Option<ProgressBar>,
+    text: String,
+    /// Whether the last thing printed was reasoning.
+    in_reasoning: bool,
+    /// Whether any reasoning was printed during the turn.
+    reasoning_streamed: bool,
+}
 
-        // Current simple implementation:
-        print!("{} ", prompt.yellow().bold());
-        io::stdout().flush().context("Failed to flush stdout")?;
-        let mut buffer = String::new();
-        io::stdin()
-            .read_line(&mut buffer)
-            .context("Failed to read line from stdin")?;
-        Ok(buffer.trim().to_string())
-    }
+/// What was printed while a turn streamed.
+struct StreamedTurn {
+    text: String,
+    reasoning: bool,
 }
 
-fn find_project_root() -> Result<PathBuf> {
-    let current_dir = env::current_dir().context("Failed to get current directory")?;
-    let mut current = current_dir.as_path();
-    loop {
-        let config_path = current.join(CONFIG_FILENAME);
-        if config_path.exists() && config_path.is_file() {
-            return Ok(current.to_path_buf());
+impl CliUserInteraction {
+    /// Starts a turn; the spinner is cleared as soon as the first text arrives.
+    fn begin_turn(&self, spinner: ProgressBar) {
+        let mut stream = self.stream.lock().unwrap();
+        stream.spinner = Some(spinner);
+        stream.text.clear();
+    }
+
+    /// Ends a turn, returning what was streamed to the terminal during it.
+    fn end_turn(&self) -> StreamedTurn {
+        let mut stream = self.stream.lock().unwrap();
+        if let Some(spinner) = stream.spinner.take() {
+            spinner.finish_and_clear();
         }
-        match current.parent() {
-            Some(parent) => current = parent,
-            None => {
-                return Err(anyhow!(
-                    "Could not find '{}' in current directory or any parent directory.",
-                    CONFIG_FILENAME
-                ));
-            }
+        if std::mem::take(&mut stream.in_reasoning) {
+            println!();
+        }
+        StreamedTurn {
+            text: std::mem::take(&mut stream.text),
+            reasoning: std::mem::take(&mut stream.reasoning_streamed),
         }
     }
 }
 
-fn load_cli_config() -> Result<(AgentConfig, PathBuf)> {
-    let project_root = find_project_root()?;
-    let config_path = project_root.join(CONFIG_FILENAME);
-    info!("Found configuration file at: {:?}", config_path);
-    let config_toml_content = fs::read_to_string(&config_path)
-        .with_context(|| format!("Failed to read project config file: {:?}", config_path))?;
-    let agent_config = AgentConfig::from_toml_str(&config_toml_content)
-        .context("Failed to parse or validate configuration content")?;
-    Ok((agent_config, project_root))
+/// Whether the final answer was already printed as stream deltas.
+fn was_streamed(streamed: &str, final_message: &str) -> bool {
+    !final_message.trim().is_empty() && streamed.trim_end().ends_with(final_message.trim_end())
 }
 
-#[derive(Deserialize, Debug, Default)]
-struct GitServerCliConfig {
-    allowed_commands: Option<Vec<String>>,
+/// Prints the reasoning of the given messages, dimmed.
+fn print_reasoning(messages: &[ChatMessage]) {
+    for reasoning in messages.iter().filter_map(|m| m.reasoning.as_deref()) {
+        println!("\n{}", reasoning.dimmed());
+    }
 }
 
-#[derive(Deserialize, Debug, Default)]
-struct CliTomlConfig {
-    #[serde(default)]
-    git_server: GitServerCliConfig,
+/// Suggests what the user can do about a failed provider call.
+fn provider_error_hint(error: &AgentError) -> Option<String> {
+    let hint = match error.provider_error()? {
+        ProviderError::Unauthorized(_) => {
+            "Check that the environment variable named by `api_key_env_var` in Volition.toml holds a valid API key.".to_string()
+        }
+        ProviderError::RateLimited { retry_after: Some(delay), .. } => {
+            format!("The provider is rate limiting requests. Try again in {} seconds.", delay.as_secs())
+        }
+        ProviderError::RateLimited { retry_after: None, .. } => {
+            "The provider is rate limiting requests. Wait a moment and try again.".to_string()
+        }
+        ProviderError::ContextLengthExceeded(_) => {
+            "The conversation is too long for this model. Start a new conversation (type `new`) or use a model with a larger context window.".to_string()
+        }
+        ProviderError::ContentFiltered(_) => {
+            "The provider's content filter blocked the request or response. Rephrase the request and try again.".to_string()
+        }
+        ProviderError::ModelNotFound(_) => {
+            "Check `model_name` (and `deployment` for Azure) in Volition.toml.".to_string()
+        }
+        ProviderError::Transport(_) => {
+            "Could not reach the provider. Check your network connection and the configured `endpoint`.".to_string()
+        }
+        ProviderError::Unsupported(_) => {
+            "The configured provider cannot honor this request. Use a different provider for this strategy step.".to_string()
+        }
+        ProviderError::MalformedResponse(_) | ProviderError::Other { .. } => return None,
+    };
+    Some(hint)
 }
 
-fn load_git_server_allowed_commands(config_path: &Path) -> Option<Vec<String>> { // Use Path
-    match fs::read_to_string(config_path) {
-        Ok(toml_content) => match toml::from_str::<CliTomlConfig>(&toml_content) {
-            Ok(cli_config) => cli_config.git_server.allowed_commands,
-            Err(e) => {
-                 // Use warn! now that logging is likely initialized
-                 warn!(path = %config_path.display(), error = %e, "Failed to parse TOML for git_server config. Using default.");
-                None
+#[async_trait]
+impl UserInteraction for CliUserInteraction {
+    fn on_stream_delta(&self, delta: &StreamDelta) {
+        let mut stream = self.stream.lock().unwrap();
+        match delta {
+            StreamDelta::Text(text) => {
+                if let Some(spinner) = stream.spinner.take() {
+                    spinner.finish_and_clear();
+                }
+                if std::mem::take(&mut stream.in_reasoning) {
+                    println!("\n");
+                }
+                print!("{}", text);
+                let _ = io::stdout().flush();
+                stream.text.push_str(text);
             }
-        },
-        Err(e) => {
-             if config_path.exists() {
-                  warn!(path = %config_path.display(), error = %e, "Failed to read TOML for git_server config. Using default.");
-             }
-            None
+            StreamDelta::Reasoning(text) => {
+                if let Some(spinner) = stream.spinner.take() {
+                    spinner.finish_and_clear();
+                }
+                print!("{}", text.dimmed());
+                let _ = io::stdout().flush();
+                stream.in_reasoning = true;
+                stream.reasoning_streamed = true;
+            }
+            StreamDelta::ToolCall { name: Some(name), .. } => {
+                if let Some(spinner) = &stream.spinner {
+                    spinner.set_message(format!("Preparing {}...", name));
+                }
+            }
+            StreamDelta::ToolCall { .. } => {}
         }
     }
-}
 
+    async fn ask(&self, prompt: String, _options: Vec<String>) -> Result<String> {
+        // TODO: Consider using dialoguer::Input here for a nicer prompt
+
+        // Current simple implementation:
+        print!("{} ", prompt.yellow().bold());
+        io::stdout().flush().context("Failed to flush stdout")?;
+        let mut buffer = String::new();
+        io::stdin()
+            .read_line(&mut buffer)
+            .context("Failed to read line from stdin")?;
+        Ok(buffer.trim().to_string())
+    }
+}
 
 fn print_welcome_message(history_id: Option<Uuid>) {
     println!(
@@ -146,9 +206,10 @@ fn print_welcome_message(history_id: Option<Uuid>) {
         println!("{}: {}", "Current conversation".cyan(), id.to_string().dimmed());
     }
     println!(
-        "{}\n{}",
+        "{}\n{}\n{}",
         "Type 'exit', 'quit', Ctrl-D, or press Enter on an empty line to quit.".dimmed(),
-        "Type 'new' to start a fresh conversation.".dimmed()
+        "Type 'new' to start a fresh conversation.".dimmed(),
+        "Type 'attach <path>' to send an image with your next message.".dimmed()
     );
     println!(); // Add newline for spacing
 }
@@ -177,9 +238,45 @@ fn select_base_strategy(config: &AgentConfig) -> CliStrategy {
     }
 }
 
+/// Reads local images into image parts. The data is embedded so the saved
+/// conversation does not depend on the files.
+fn load_images(paths: &[PathBuf]) -> Result<Vec<ContentPart>> {
+    paths
+        .iter()
+        .map(|path| {
+            let source = ImageSource::embed(path)?;
+            debug!(path = %path.display(), "Attached image.");
+            Ok(ContentPart::Image { source })
+        })
+        .collect()
+}
+
+/// Builds the messages and user input to start an agent turn with.
+///
+/// Without images the input is passed to the agent as usual; with images the
+/// user message is added to the history here, since it needs the extra parts.
+fn turn_input(
+    history: &[ChatMessage],
+    input: String,
+    images: Vec<ContentPart>,
+) -> (Vec<ChatMessage>, String) {
+    let mut messages = history.to_vec();
+    if images.is_empty() {
+        return (messages, input);
+    }
+    messages.push(ChatMessage {
+        role: Role::User,
+        content: Some(input),
+        parts: images,
+        ..Default::default()
+    });
+    (messages, String::new())
+}
+
 /// Runs a single turn (non-interactive).
 async fn run_single_turn(
     initial_prompt: String,
+    image_paths: Vec<PathBuf>,
     mut history: ConversationHistory, // Takes ownership
     config: AgentConfig,
     project_root: PathBuf, // Keep PathBuf ownership
@@ -188,7 +285,9 @@ async fn run_single_turn(
     info!(task = %initial_prompt, history_id = %history.id, "Running non-interactive turn.");
 
     let base_strategy = select_base_strategy(&config);
-    let initial_messages = Some(history.messages.clone());
+    let images = load_images(&image_paths)?;
+    let (initial_messages, initial_prompt) = turn_input(&history.messages, initial_prompt, images);
+    let initial_messages = Some(initial_messages);
 
     // --- Add Spinner ---
     let pb = ProgressBar::new_spinner();
@@ -199,16 +298,17 @@ async fn run_single_turn(
     );
     pb.set_message("Thinking...");
     pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    ui_handler.begin_turn(pb);
     // --- End Spinner ---
 
     // Scope agent creation and run
     let agent_result = {
         let mut agent = CliAgent::new(
             config.clone(),
-            ui_handler,
+            Arc::clone(&ui_handler),
             base_strategy,
             initial_messages,
-            initial_prompt.clone(),
+            initial_prompt,
             None, // provider_registry_override
             None, // mcp_connections_override
         )
@@ -216,14 +316,19 @@ async fn run_single_turn(
          agent.run(&project_root).await // Pass project_root reference here
      };
 
-    pb.finish_and_clear(); // Stop spinner
+    let streamed = ui_handler.end_turn(); // Stop spinner
 
     match agent_result {
         Ok((final_message, updated_state)) => {
             info!("Agent session completed successfully.");
-            println!("{}", final_message); // Print raw response for non-interactive
+            if was_streamed(&streamed.text, &final_message) {
+                println!();
+            } else {
+                println!("{}", final_message); // Print raw response for non-interactive
+            }
 
             history.messages = updated_state.messages;
+            history.record_usage(updated_state.usage);
             history.last_updated_at = chrono::Utc::now();
             save_history(&project_root, &history)?; // Pass project_root reference
             info!(history_id = %history.id, "Saved updated conversation history.");
@@ -231,6 +336,9 @@ async fn run_single_turn(
         }
         Err(e) => {
             error!("Agent run encountered an error: {}", e);
+            if let Some(hint) = provider_error_hint(&e) {
+                eprintln!("{}", hint.yellow());
+            }
             // Don't save history on error in non-interactive mode
             Err(anyhow!(e))
         }
@@ -241,12 +349,18 @@ async fn run_single_turn(
 // --- run_interactive with rustyline ---
 /// Runs an interactive chat session using rustyline for a REPL experience.
 async fn run_interactive(
+    image_paths: Vec<PathBuf>,
     mut history: ConversationHistory, // Takes ownership
     config: AgentConfig,
     project_root: PathBuf, // Keep PathBuf ownership
     ui_handler: Arc<CliUserInteraction>,
 ) -> Result<()> {
+    // Images attached with --image or `attach`, sent with the next message.
+    let mut pending_images = load_images(&image_paths)?;
     print_welcome_message(Some(history.id));
+    if !pending_images.is_empty() {
+        println!("{}", format!("{} image(s) will be sent with your first message.", pending_images.len()).dimmed());
+    }
 
     // --- Rustyline Setup ---
     let rl_config = Config::builder()
@@ -302,10 +416,26 @@ async fn run_interactive(
                     continue; // Go to next loop iteration for new input
                 }
 
+                // Handle 'attach <path>' command
+                if let Some(path) = trimmed_input.strip_prefix("attach ") {
+                    match load_images(&[PathBuf::from(path.trim())]) {
+                        Ok(images) => {
+                            pending_images.extend(images);
+                            println!("{}", format!("Attached {}. It will be sent with your next message.", path.trim()).dimmed());
+                        }
+                        Err(e) => eprintln!("{} {:#}", "Error:".red(), e),
+                    }
+                    continue;
+                }
+
                 // --- Agent Execution Logic ---
-                let user_message = trimmed_input.to_string();
                 let agent_strategy = select_base_strategy(&config);
-                let current_messages = Some(history.messages.clone());
+                let (current_messages, user_message) = turn_input(
+                    &history.messages,
+                    trimmed_input.to_string(),
+                    std::mem::take(&mut pending_images),
+                );
+                let current_messages = Some(current_messages);
 
                 // --- Add Spinner ---
                 let pb = ProgressBar::new_spinner();
@@ -316,6 +446,7 @@ async fn run_interactive(
                 );
                 pb.set_message("Thinking...");
                 pb.enable_steady_tick(std::time::Duration::from_millis(100));
+                ui_handler.begin_turn(pb);
                 // --- End Spinner ---
 
                 let agent_result = { // Scope agent
@@ -324,7 +455,7 @@ async fn run_interactive(
                         Arc::clone(&ui_handler),
                         agent_strategy,
                         current_messages,
-                        user_message.clone(),
+                        user_message,
                         None, // provider_registry_override
                         None, // mcp_connections_override
                     )
@@ -332,19 +463,28 @@ async fn run_interactive(
                     agent.run(&project_root).await // Pass project_root
                 };
 
-                 pb.finish_and_clear(); // Stop spinner
+                let streamed = ui_handler.end_turn(); // Stop spinner
 
                 match agent_result {
                     Ok((final_message, updated_state)) => {
                         info!("Agent turn completed successfully.");
-                        println!("\n{}\n", "--- Agent Response ---".bold());
-                        if let Err(e) = print_formatted(&final_message) {
-                            error!("Failed to render final AI message markdown: {}. Printing raw.", e);
-                            println!("{}", final_message);
+                        if !streamed.reasoning {
+                            print_reasoning(&updated_state.messages[history.messages.len()..]);
                         }
+                        if was_streamed(&streamed.text, &final_message) {
+                            println!();
+                        } else {
+                            println!("\n{}\n", "--- Agent Response ---".bold());
+                            if let Err(e) = print_formatted(&final_message) {
+                                error!("Failed to render final AI message markdown: {}. Printing raw.", e);
+                                println!("{}", final_message);
+                            }
+                        }
+                        history.messages = updated_state.messages;
+                        history.record_usage(updated_state.usage);
+                        println!("{}", running_total(&history, &config).dimmed());
                         println!("\n----------------------");
 
-                        history.messages = updated_state.messages;
                         history.last_updated_at = chrono::Utc::now();
                         if let Err(e) = save_history(&project_root, &history) { // Pass project_root
                             error!(history_id=%history.id, "Failed to save conversation history: {}", e);
@@ -360,6 +500,9 @@ async fn run_interactive(
                             "Agent run encountered an error".red(),
                             e
                         );
+                        if let Some(hint) = provider_error_hint(&e) {
+                            eprintln!("{}", hint.yellow());
+                        }
                         // Save history even on error
                         history.last_updated_at = chrono::Utc::now();
                         if let Err(save_err) = save_history(&project_root, &history) { // Pass project_root
@@ -448,9 +591,13 @@ fn handle_view_conversation(project_root: &Path, id: Uuid, full: bool) -> Result
     println!("{}", "--- Messages ---".bold());
 
     for message in &history.messages {
-        println!("\n[{}]", message.role.to_uppercase().cyan());
-        // Safely get content as a string slice, default to empty string if None
-        let content_str = message.content.as_deref().unwrap_or("");
+        println!("\n[{}]", message.role.as_str().to_uppercase().cyan());
+        if full && let Some(reasoning) = &message.reasoning {
+            println!("{}", reasoning.dimmed());
+        }
+        // The full text, including any text parts
+        let content = message.text();
+        let content_str = content.as_str();
 
         if full {
              if let Err(e) = print_formatted(content_str) { // Try formatting even in full view
@@ -469,10 +616,17 @@ fn handle_view_conversation(project_root: &Path, id: Uuid, full: bool) -> Result
                  println!("{}", preview.trim());
              }
         }
+        for image in message.images() {
+            let label = match image {
+                ImageSource::Base64 { media_type, .. } => media_type.clone(),
+                ImageSource::Path(path) => path.display().to_string(),
+            };
+            println!("{}", format!("[image: {}]", label).dimmed());
+        }
     }
      println!("\n{}", "--- End ---".bold());
      let was_truncated = !full && history.messages.iter().any(|m| {
-         let c = m.content.as_deref().unwrap_or("");
+         let c = m.text();
          c.lines().count() > 1 || c.chars().count() > 100
      });
      if was_truncated {
@@ -482,6 +636,20 @@ fn handle_view_conversation(project_root: &Path, id: Uuid, full: bool) -> Result
     Ok(())
 }
 
+fn handle_usage_report(project_root: &Path, config: &AgentConfig, by: Option<UsageGrouping>) -> Result<()> {
+    let histories = list_histories(project_root)?;
+    println!("{}: {}\n", "Project".bold(), project_root.display());
+    UsageReport::build(&histories, config).print(by);
+    Ok(())
+}
+
+fn handle_cache_clear() -> Result<()> {
+    let dir = default_cache_dir()?;
+    let removed = clear_cache(&dir)?;
+    println!("Removed {} cached response(s) from {}", removed, dir.display());
+    Ok(())
+}
+
 // --- handle_delete_conversation UPDATED with dialoguer and project_root ---
 fn handle_delete_conversation(project_root: &Path, id: Uuid) -> Result<()> { // Accept project_root
     // Use dialoguer for confirmation
@@ -570,51 +738,57 @@ async fn main() -> ExitCode {
     );
     // --- End Logging Setup ---
 
+    // `cache` does not need a project, so it runs before the config is loaded.
+    if let Some(Commands::Cache { action }) = &cli.command {
+        let result = match action {
+            CacheAction::Clear => handle_cache_clear(),
+        };
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("Cache command failed: {:?}", e);
+                eprintln!("{} {:#}", "Error:".red(), e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // `config` reports problems that would stop the config from loading.
+    if let Some(Commands::Config { action }) = &cli.command {
+        let result = match action {
+            ConfigAction::Validate => config_command::validate_config(),
+            ConfigAction::Show => config_command::show_config(),
+        };
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("Config command failed: {:?}", e);
+                eprintln!("{} {:#}", "Error:".red(), e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
     // --- Load Config ---
-    let config_result = load_cli_config();
-     let mut config;
-     let project_root; // Keep ownership here
-
-     match config_result {
-         Ok((loaded_config, loaded_root)) => {
-             config = loaded_config;
-             project_root = loaded_root;
-
-             // Modify config with git server args
-            let config_toml_path = project_root.join(CONFIG_FILENAME);
-             // Use warn! now that logging is initialized
-             if let Some(allowed_commands) = load_git_server_allowed_commands(&config_toml_path) {
-                if let Some(git_server_conf) = config.mcp_servers.get_mut("git") { 
-                    if !allowed_commands.is_empty() {
-                        info!(commands = ?allowed_commands, "Found git allowed_commands in config. Passing to server.");
-                        let commands_str = allowed_commands.join(",");
-                        if !git_server_conf.args.contains(&"--allowed-commands".to_string()) {
-                            git_server_conf.args.push("--allowed-commands".to_string());
-                            git_server_conf.args.push(commands_str);
-                            debug!(server_id = "git", args = ?git_server_conf.args, "Updated git server args");
-                        }
-                    } else {
-                        info!("Empty git allowed_commands list found in config. Server will use its default.");
-                    }
-                } else {
-                     if !allowed_commands.is_empty() {
-                        warn!("git_server.allowed_commands found in TOML, but no MCP server with ID 'git' defined in config.");
-                     }
-                }
-            } else {
-                info!("No git_server.allowed_commands found in config. Server will use its default.");
+    let (mut config, project_root) = match load_cli_config() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            // Config loading failed *after* logging was initialized
+            error!("Failed to load configuration: {:?}", e); // Log the detailed error
+            eprintln!("{} {:#}", "Error:".red(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if cli.no_cache {
+        for provider in config.providers.values_mut() {
+            if let Some(cache) = &mut provider.model_config.cache {
+                cache.bypass = true;
             }
-         }
-         Err(e) => {
-             // Config loading failed *after* logging was initialized
-             error!("Failed to load configuration: {}", e); // Log the detailed error
-             eprintln!("{} Could not find or load '{}'. Ensure you are in a Volition project directory.", "Error:".red(), CONFIG_FILENAME); // User-friendly error
-             return ExitCode::FAILURE;
-         }
-     }
+        }
+    }
     // --- End Config Loading ---
 
-    let ui_handler: Arc<CliUserInteraction> = Arc::new(CliUserInteraction);
+    let ui_handler: Arc<CliUserInteraction> = Arc::new(CliUserInteraction::default());
 
     // --- Command Handling Logic ---
     let result = match cli.command {
@@ -630,16 +804,28 @@ async fn main() -> ExitCode {
         Some(Commands::Delete { id }) => {
              handle_delete_conversation(&project_root, id) // Pass reference (now uses dialoguer internally)
         }
+        // --- usage ---
+        Some(Commands::Usage { by }) => {
+            handle_usage_report(&project_root, &config, by)
+        }
+        // --- cache (handled before config loading) ---
+        Some(Commands::Cache { .. }) => Ok(()),
+        // --- config (handled before config loading) ---
+        Some(Commands::Config { .. }) => Ok(()),
+        // --- models ---
+        Some(Commands::Models { probe }) => {
+            model_list::print_models(&config, probe).await
+        }
         // --- resume ---
         Some(Commands::Resume { id, turn }) => {
             match load_history(&project_root, id) { // Pass reference
                 Ok(history) => {
                     if let Some(prompt) = turn {
                         // Resume + Single Turn (Non-interactive)
-                         run_single_turn(prompt, history, config, project_root, ui_handler).await // Pass ownership
+                         run_single_turn(prompt, cli.images, history, config, project_root, ui_handler).await // Pass ownership
                     } else {
                         // Resume Interactive (with rustyline)
-                         run_interactive(history, config, project_root, ui_handler).await // Pass ownership
+                         run_interactive(cli.images, history, config, project_root, ui_handler).await // Pass ownership
                     }
                 }
                 Err(e) => {
@@ -655,10 +841,10 @@ async fn main() -> ExitCode {
              info!(history_id=%initial_history.id, "Starting new conversation.");
             if let Some(prompt) = cli.turn {
                  // New Single Turn (Non-interactive)
-                 run_single_turn(prompt, initial_history, config, project_root, ui_handler).await // Pass ownership
+                 run_single_turn(prompt, cli.images, initial_history, config, project_root, ui_handler).await // Pass ownership
             } else {
                  // New Interactive (with rustyline)
-                 run_interactive(initial_history, config, project_root, ui_handler).await // Pass ownership
+                 run_interactive(cli.images, initial_history, config, project_root, ui_handler).await // Pass ownership
             }
         }
     };
@@ -669,7 +855,7 @@ async fn main() -> ExitCode {
         Err(e) => {
             // Use improved error checking from HEAD
             let error_string = e.to_string();
-             let is_dialoguer_error = matches!(e.downcast_ref::<dialoguer::Error>(), Some(_));
+             let is_dialoguer_error = e.downcast_ref::<dialoguer::Error>().is_some();
             let already_handled = error_string.contains("Could not load conversation history")
                || error_string.contains("Agent run encountered an error")
                || error_string.contains("Failed to load history") // Includes "History file not found"
@@ -0,0 +1,96 @@
+// volition-cli/src/model_list.rs
+
+//! `volition models`: the models each configured provider offers, and
+//! optionally whether tool calling works with the configured one.
+
+use anyhow::Result;
+use colored::*;
+use volition_core::config::AgentConfig;
+use volition_core::errors::ProviderError;
+use volition_core::providers::router::ROUTER_TYPE;
+use volition_core::providers::{Provider, ProviderFactoryRegistry, probe_tool_calling};
+
+/// Whether `listed` names the configured model. Ollama lists untagged models
+/// with their implicit `:latest` tag.
+fn is_configured(listed: &str, configured: &str) -> bool {
+    listed == configured || listed.strip_suffix(":latest") == Some(configured)
+}
+
+/// Prints the models of every configured provider, default provider first.
+/// Routers list their targets' models and are not probed, since their targets
+/// are probed under their own entries. The probe talks to the provider itself,
+/// not through the text tool calling or cache layers of the registry, so it
+/// reports whether the model calls tools natively.
+pub async fn print_models(config: &AgentConfig, probe: bool) -> Result<()> {
+    let factories = ProviderFactoryRegistry::default();
+    let http_client = reqwest::Client::new();
+    let registry = factories.build_registry(config, &http_client)?;
+
+    let mut ids: Vec<&String> = config.providers.keys().collect();
+    ids.sort_by_key(|id| (*id != &config.default_provider, id.as_str()));
+    for id in ids {
+        let provider_config = &config.providers[id];
+        let configured = &provider_config.model_config.model_name;
+        let default_marker = if id == &config.default_provider { ", default" } else { "" };
+        println!(
+            "{} ({}{})",
+            id.bold(),
+            provider_config.provider_type,
+            default_marker
+        );
+        let provider = registry.get(id)?;
+        let is_router = provider_config.provider_type == ROUTER_TYPE;
+        print_listing(provider, configured).await;
+        if probe && !is_router {
+            let unwrapped = factories.create_provider(id, provider_config, &http_client)?;
+            print_probe(unwrapped.as_ref(), configured).await;
+        }
+        println!();
+    }
+    Ok(())
+}
+
+async fn print_listing(provider: &dyn Provider, configured: &str) {
+    let models = match provider.list_models().await {
+        Ok(models) => models,
+        Err(e) => {
+            let reason = match ProviderError::find(e.as_ref()) {
+                Some(ProviderError::Unsupported(_)) => "this provider type cannot list models".to_string(),
+                _ => format!("{:#}", e),
+            };
+            println!("  {} {}", "Could not list models:".yellow(), reason);
+            println!("  {} {}", "*".green(), configured);
+            return;
+        }
+    };
+    if models.is_empty() {
+        println!("  {}", "No models offered.".dimmed());
+    }
+    for model in &models {
+        let marker = if is_configured(&model.id, configured) { "*".green() } else { " ".normal() };
+        match &model.details {
+            Some(details) => println!("  {} {}  {}", marker, model.id, details.dimmed()),
+            None => println!("  {} {}", marker, model.id),
+        }
+    }
+    // Routers have no model of their own.
+    if !configured.is_empty() && !models.iter().any(|m| is_configured(&m.id, configured)) {
+        println!(
+            "  {} configured model '{}' is not offered by this server",
+            "Warning:".yellow(),
+            configured
+        );
+    }
+}
+
+async fn print_probe(provider: &dyn Provider, configured: &str) {
+    match probe_tool_calling(provider).await {
+        Ok(true) => println!("  Tool calling with {}: {}", configured, "ok".green()),
+        Ok(false) => println!(
+            "  Tool calling with {}: {} (consider `tool_calling = \"json\"`)",
+            configured,
+            "answered without calling the tool".yellow()
+        ),
+        Err(e) => println!("  Tool calling with {}: {} {:#}", configured, "failed:".red(), e),
+    }
+}
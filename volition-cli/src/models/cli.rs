@@ -1,5 +1,6 @@
 // volition-cli/src/models/cli.rs
-use clap::{ArgAction, Parser, Subcommand}; // Import Subcommand
+use clap::{ArgAction, Parser, Subcommand, ValueEnum}; // Import Subcommand
+use std::path::PathBuf;
 use uuid::Uuid; // Import Uuid
 
 /// Volition: An AI-powered assistant for software engineering tasks.
@@ -23,6 +24,18 @@ pub struct Cli {
     #[arg(long)]
     pub turn: Option<String>,
 
+    /// Attach a local image (PNG, JPEG, GIF or WebP) to the first prompt.
+    ///
+    /// Can be repeated. Works with `--turn`, `resume --turn` and interactive mode.
+    #[arg(long = "image", value_name = "PATH", global = true)]
+    pub images: Vec<PathBuf>,
+
+    /// Send every request to the model instead of serving cached responses.
+    ///
+    /// Only affects providers with a `cache` table; their fresh responses are still cached.
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
     // Keep the old -t/--task for backward compatibility or remove if desired.
     // If kept, it should probably conflict with `turn` and subcommands.
     // For now, let's remove it to enforce the new structure.
@@ -62,5 +75,52 @@ pub enum Commands {
         /// ID of the conversation to delete.
         id: Uuid, // Use Uuid directly
     },
-    // Future commands like 'config' could go here
+    /// Report token usage and estimated cost of the project's conversations.
+    Usage {
+        /// Show only one grouping instead of all of them.
+        #[arg(long, value_enum)]
+        by: Option<UsageGrouping>,
+    },
+    /// List the models each configured provider offers; `*` marks the configured one.
+    Models {
+        /// Also send each configured model a small tool-call request to check tool support.
+        #[arg(long)]
+        probe: bool,
+    },
+    /// Manage the response cache of providers with a `cache` table.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Check or print the merged configuration.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+/// What `volition config` does.
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// Report problems with their file, line and key, including unknown provider
+    /// types, missing MCP server commands, unset API key variables and strategies
+    /// naming unknown providers.
+    Validate,
+    /// Print the merged configuration with secrets masked.
+    Show,
+}
+
+/// What `volition cache` does.
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheAction {
+    /// Delete all cached responses.
+    Clear,
+}
+
+/// How `volition usage` groups API calls.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    Conversation,
+    Day,
+    Provider,
 }
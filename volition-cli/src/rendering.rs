@@ -81,7 +81,7 @@ fn highlight_code<W: Write>(
 
         let ranges: Vec<(Style, &str)> = highlighter
             .highlight_line(line, syntax_set)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(io::Error::other)?;
 
         for (style, content) in ranges {
             let fg = style.foreground;
@@ -152,20 +152,14 @@ fn flush_markdown_buffer<W: Write>(
     cmark(events.iter(), &mut md_string)
         // Map CmarkError to io::Error
         .map_err(|e: CmarkError| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!("Markdown generation error: {}", e),
-            )
+            io::Error::other(format!("Markdown generation error: {}", e))
         })?;
 
     // Write the reconstructed Markdown string using termimad's skin
     // Map TermimadError to io::Error
     skin.write_text_on(writer, &md_string)
         .map_err(|e: TermimadError| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!("Termimad rendering error: {}", e),
-            )
+            io::Error::other(format!("Termimad rendering error: {}", e))
         })?;
 
     // Clear the original buffer now that it's been processed
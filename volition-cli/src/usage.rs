@@ -0,0 +1,182 @@
+// volition-cli/src/usage.rs
+
+//! Token usage and estimated cost reports built from saved conversation histories.
+
+use chrono::{Local, NaiveDate};
+use colored::*;
+use std::collections::BTreeMap;
+use volition_core::config::{AgentConfig, PricingConfig};
+use volition_core::models::chat::TokenUsage;
+
+use crate::history::ConversationHistory;
+use crate::models::cli::UsageGrouping;
+
+/// Summed token counts and estimated cost of a group of API calls.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub calls: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated cost of the calls whose model has a `[pricing]` table.
+    pub cost: f64,
+    /// Number of calls without pricing, which are missing from `cost`.
+    pub unpriced_calls: usize,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, usage: &TokenUsage, pricing: Option<&PricingConfig>) {
+        let prompt = u64::from(usage.prompt_tokens);
+        let completion = u64::from(usage.completion_tokens);
+        self.calls += 1;
+        self.prompt_tokens += prompt;
+        self.completion_tokens += completion;
+        match pricing {
+            Some(pricing) => self.cost += pricing.cost(prompt, completion),
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Formats the cost, or `-` when none of the calls are priced.
+    pub fn cost_label(&self) -> String {
+        if self.calls > 0 && self.unpriced_calls == self.calls {
+            "-".to_string()
+        } else if self.unpriced_calls > 0 {
+            format!("{:.4}*", self.cost)
+        } else {
+            format!("{:.4}", self.cost)
+        }
+    }
+}
+
+/// Finds the pricing of the model that served `usage`.
+///
+/// Prefers the provider entry the call was made through, as long as it still
+/// uses the same model, and otherwise any provider configured with that model.
+pub fn pricing_for<'a>(config: &'a AgentConfig, usage: &TokenUsage) -> Option<&'a PricingConfig> {
+    if let Some(provider) = config.providers.get(&usage.provider_id)
+        && provider.model_config.model_name == usage.model
+        && let Some(pricing) = &provider.model_config.pricing
+    {
+        return Some(pricing);
+    }
+    config
+        .providers
+        .values()
+        .filter(|p| p.model_config.model_name == usage.model)
+        .find_map(|p| p.model_config.pricing.as_ref())
+}
+
+/// Usage of all saved conversations, grouped three ways.
+#[derive(Debug, Default)]
+pub struct UsageReport {
+    /// Per conversation ID, newest conversation first.
+    pub by_conversation: Vec<(String, UsageTotals)>,
+    /// Per local calendar day.
+    pub by_day: BTreeMap<NaiveDate, UsageTotals>,
+    /// Per provider ID and model.
+    pub by_provider: BTreeMap<String, UsageTotals>,
+    pub total: UsageTotals,
+}
+
+impl UsageReport {
+    pub fn build(histories: &[ConversationHistory], config: &AgentConfig) -> Self {
+        let mut report = Self::default();
+        for history in histories {
+            let mut conversation = UsageTotals::default();
+            for entry in &history.usage {
+                let pricing = pricing_for(config, &entry.usage);
+                let day = entry.recorded_at.with_timezone(&Local).date_naive();
+                let provider = format!("{} ({})", entry.usage.provider_id, entry.usage.model);
+
+                conversation.add(&entry.usage, pricing);
+                report.by_day.entry(day).or_default().add(&entry.usage, pricing);
+                report.by_provider.entry(provider).or_default().add(&entry.usage, pricing);
+                report.total.add(&entry.usage, pricing);
+            }
+            if conversation.calls > 0 {
+                report.by_conversation.push((history.id.to_string(), conversation));
+            }
+        }
+        report
+    }
+
+    /// Prints the requested groupings, or all of them when `grouping` is `None`.
+    pub fn print(&self, grouping: Option<UsageGrouping>) {
+        if self.total.calls == 0 {
+            println!("No token usage recorded in this project yet.");
+            return;
+        }
+        let show = |g: UsageGrouping| grouping.is_none_or(|selected| selected == g);
+
+        if show(UsageGrouping::Conversation) {
+            print_table("Conversation", self.by_conversation.iter().map(|(id, t)| (id.clone(), t)));
+        }
+        if show(UsageGrouping::Day) {
+            print_table("Day", self.by_day.iter().map(|(day, t)| (day.to_string(), t)));
+        }
+        if show(UsageGrouping::Provider) {
+            print_table("Provider", self.by_provider.iter().map(|(p, t)| (p.clone(), t)));
+        }
+
+        println!(
+            "{}: {} calls, {} tokens ({} in / {} out), estimated cost {}",
+            "Total".bold(),
+            self.total.calls,
+            self.total.total_tokens(),
+            self.total.prompt_tokens,
+            self.total.completion_tokens,
+            self.total.cost_label()
+        );
+        if self.total.unpriced_calls > 0 {
+            println!(
+                "{}",
+                "(* excludes calls to models without a [pricing] table in Volition.toml)".dimmed()
+            );
+        }
+    }
+}
+
+fn print_table<'a>(label: &str, rows: impl Iterator<Item = (String, &'a UsageTotals)>) {
+    println!(
+        "{:<40} {:>6} {:>12} {:>12} {:>12}",
+        label.underline(),
+        "Calls".underline(),
+        "Input".underline(),
+        "Output".underline(),
+        "Cost".underline()
+    );
+    for (key, totals) in rows {
+        println!(
+            "{:<40} {:>6} {:>12} {:>12} {:>12}",
+            key,
+            totals.calls,
+            totals.prompt_tokens,
+            totals.completion_tokens,
+            totals.cost_label()
+        );
+    }
+    println!();
+}
+
+/// One-line summary of the tokens used so far in `history`, shown after each interactive turn.
+pub fn running_total(history: &ConversationHistory, config: &AgentConfig) -> String {
+    let mut totals = UsageTotals::default();
+    for entry in &history.usage {
+        totals.add(&entry.usage, pricing_for(config, &entry.usage));
+    }
+    let mut summary = format!(
+        "Session usage: {} tokens ({} in / {} out) over {} calls",
+        totals.total_tokens(),
+        totals.prompt_tokens,
+        totals.completion_tokens,
+        totals.calls
+    );
+    if totals.unpriced_calls < totals.calls {
+        summary.push_str(&format!(", estimated cost {}", totals.cost_label()));
+    }
+    summary
+}
@@ -0,0 +1,50 @@
+// volition-cli/tests/common/mod.rs
+
+//! Runs the `volition` binary apart from the environment of the machine: the
+//! user config, data and cache directories point into a test directory and
+//! `VOLITION_*` variables are not inherited, so a developer's global config
+//! (and the MCP servers it starts) or cached responses never reach a test.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// A `volition` command run in `dir`, with the user directories under `home`:
+/// the global config is read from `home/config/volition/config.toml`.
+pub fn volition(home: &Path, dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_volition"));
+    command
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("NO_COLOR", "1");
+    for (name, _) in env::vars_os() {
+        if name.to_string_lossy().starts_with("VOLITION_") {
+            command.env_remove(name);
+        }
+    }
+    command
+}
+
+/// Runs `command`, failing the test unless it succeeds, and returns its stdout.
+pub fn run(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        output.status.success(),
+        "{:?} failed.\nstdout:\n{}\nstderr:\n{}",
+        command,
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+/// A script for `scripted` providers that answers any message with `reply`.
+pub fn script(reply: &str) -> String {
+    format!("[[turns]]\ncontent = \"{}\"\n", reply)
+}
@@ -0,0 +1,162 @@
+// volition-cli/tests/config_command.rs
+
+//! Runs `volition config validate` and `volition config show` on a project
+//! config, without a global config.
+
+mod common;
+
+use common::volition;
+use std::fs;
+
+const CONFIG: &str = r#"
+system_prompt = """
+You are a test assistant.
+Keep answers short.
+"""
+default_provider = "hosted"
+
+[providers.hosted]
+type = "openai"
+api_key_env_var = "VOLITION_TEST_MISSING_API_KEY"
+[providers.hosted.model_config]
+model_name = "gpt-4o"
+endpoint = "https://gateway.example.com/v1/chat/completions?api_key=sk-secret-1"
+[providers.hosted.model_config.http]
+headers = { "X-Gateway-Token" = "sk-secret-2", "X-Team" = "tools" }
+
+[providers.local]
+type = "olama"
+[providers.local.model_config]
+model_name = "llama3"
+
+[mcp_servers.shell]
+command = "volition-test-no-such-server"
+
+[strategies.plan_execute]
+planning_provider = "hosted"
+execution_provider = "locale"
+"#;
+
+#[test]
+fn test_validate_reports_problems_with_their_location() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+
+    let output = volition(dir.path(), dir.path())
+        .args(["config", "validate"])
+        .env(
+            "VOLITION_PROVIDERS__HOSTED__MODEL_CONFIG__PARAMETERS__TOP_P",
+            "1.5",
+        )
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "stdout:\n{}", stdout);
+    let config_path = dir.path().join("Volition.toml");
+    let expected = [
+        format!(
+            "{}:10: providers.hosted.api_key_env_var: Environment variable 'VOLITION_TEST_MISSING_API_KEY'",
+            config_path.display()
+        ),
+        format!("{}:18: providers.local.type: Provider 'local' has unknown type 'olama'", config_path.display()),
+        format!("{}:23: mcp_servers.shell.command: Command 'volition-test-no-such-server'", config_path.display()),
+        format!(
+            "{}:27: strategies.plan_execute.execution_provider: Strategy 'plan_execute' uses unknown provider 'locale'",
+            config_path.display()
+        ),
+        "$VOLITION_PROVIDERS__HOSTED__MODEL_CONFIG__PARAMETERS__TOP_P: providers.hosted.model_config.parameters: Provider 'hosted' has an out-of-range"
+            .to_string(),
+    ];
+    for line in expected {
+        assert!(
+            stdout.contains(&line),
+            "missing {:?} in stdout:\n{}",
+            line,
+            stdout
+        );
+    }
+
+    // Type errors name the key and its line.
+    fs::write(
+        dir.path().join("Volition.toml"),
+        CONFIG.replace("model_name = \"llama3\"", "model_name = 3"),
+    )
+    .unwrap();
+    let output = volition(dir.path(), dir.path())
+        .args(["config", "validate"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains(&format!(
+            "{}:20: providers.local.model_config.model_name: invalid type",
+            config_path.display()
+        )),
+        "stdout:\n{}",
+        stdout
+    );
+
+    // Syntax errors stop the config from loading at all.
+    fs::write(dir.path().join("Volition.toml"), "default_provider = \n").unwrap();
+    let output = volition(dir.path(), dir.path())
+        .args(["config", "validate"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("at line 1 column"), "stderr:\n{}", stderr);
+}
+
+#[test]
+fn test_validate_ignores_api_key_variables_of_local_providers() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("Volition.toml"),
+        r#"
+system_prompt = "You are a test assistant."
+default_provider = "local"
+
+[providers.local]
+type = "ollama"
+api_key_env_var = "VOLITION_TEST_MISSING_API_KEY"
+[providers.local.model_config]
+model_name = "llama3"
+"#,
+    )
+    .unwrap();
+
+    let output = volition(dir.path(), dir.path())
+        .args(["config", "validate"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout:\n{}", stdout);
+}
+
+#[test]
+fn test_show_prints_merged_config_with_secrets_masked() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+
+    let output = volition(dir.path(), dir.path())
+        .args(["config", "show"])
+        .env("VOLITION_DEFAULT_PROVIDER", "local")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout:\n{}", stdout);
+    assert!(
+        stdout.contains("default_provider = 'local'"),
+        "stdout:\n{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("api_key_env_var = 'VOLITION_TEST_MISSING_API_KEY'"),
+        "stdout:\n{}",
+        stdout
+    );
+    assert!(stdout.contains("X-Team = 'tools'"), "stdout:\n{}", stdout);
+    assert!(!stdout.contains("sk-secret"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("api_key=********"), "stdout:\n{}", stdout);
+}
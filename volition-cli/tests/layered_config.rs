@@ -0,0 +1,65 @@
+// volition-cli/tests/layered_config.rs
+
+//! Runs single turns with a global config, redirected into a temporary
+//! directory, merged with project files and environment overrides.
+
+mod common;
+
+use common::{run, script, volition};
+use std::fs;
+
+/// Its relative `script` is next to it, not in the directory `volition` runs in.
+const GLOBAL_CONFIG: &str = r#"
+system_prompt = "You are a test assistant."
+default_provider = "scripted"
+
+[providers.scripted]
+type = "scripted"
+[providers.scripted.model_config]
+model_name = "scripted"
+script = "global-script.toml"
+"#;
+
+#[test]
+fn test_project_and_environment_override_global_config() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    let global_dir = home.join("config/volition");
+    fs::create_dir_all(&global_dir).unwrap();
+    fs::write(global_dir.join("config.toml"), GLOBAL_CONFIG).unwrap();
+    fs::write(global_dir.join("global-script.toml"), script("Global reply.")).unwrap();
+
+    // Outside a project, the global config is used alone.
+    let outside = tempfile::tempdir().unwrap();
+    let stdout = run(volition(home, outside.path()).args(["--turn", "Hello"]));
+    assert!(stdout.contains("Global reply."), "stdout:\n{}", stdout);
+    let history = fs::read_dir(home.join("data/volition/.volition/history")).unwrap();
+    assert_eq!(history.count(), 1);
+
+    // A project overrides a single key of the global provider.
+    let project = tempfile::tempdir().unwrap();
+    fs::write(
+        project.path().join("Volition.toml"),
+        "[providers.scripted.model_config]\nscript = \"project-script.toml\"\n",
+    )
+    .unwrap();
+    fs::write(project.path().join("project-script.toml"), script("Project reply.")).unwrap();
+    let stdout = run(volition(home, project.path()).args(["--turn", "Hello"]));
+    assert!(stdout.contains("Project reply."), "stdout:\n{}", stdout);
+
+    // Paths in the project file stay relative to it in a subdirectory.
+    let subdir = project.path().join("src/nested");
+    fs::create_dir_all(&subdir).unwrap();
+    let stdout = run(volition(home, &subdir).args(["--turn", "Hello"]));
+    assert!(stdout.contains("Project reply."), "stdout:\n{}", stdout);
+
+    // Environment variables override both files.
+    fs::write(project.path().join("env-script.toml"), script("Env reply.")).unwrap();
+    let stdout = run(
+        volition(home, project.path())
+            .args(["--turn", "Hello"])
+            .env("VOLITION_PROVIDERS__SCRIPTED__MODEL_CONFIG__SCRIPT", "env-script.toml")
+            .env("VOLITION_API_KEY", "not a config key"),
+    );
+    assert!(stdout.contains("Env reply."), "stdout:\n{}", stdout);
+}
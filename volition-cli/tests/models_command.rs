@@ -0,0 +1,128 @@
+// volition-cli/tests/models_command.rs
+
+//! Runs `volition models` against a scripted provider and a mocked Ollama server.
+
+mod common;
+
+use common::{run, volition};
+use httpmock::prelude::*;
+use serde_json::json;
+use std::fs;
+
+const SCRIPT: &str = r#"
+[[turns]]
+match = "ping tool"
+content = ""
+  [[turns.tool_calls]]
+  name = "ping"
+  arguments = { status = "ok" }
+"#;
+
+fn config(ollama_endpoint: &str) -> String {
+    format!(
+        r#"
+system_prompt = "You are a test assistant."
+default_provider = "scripted"
+
+[providers.scripted]
+type = "scripted"
+[providers.scripted.model_config]
+model_name = "scripted-model"
+script = "script.toml"
+
+[providers.local]
+type = "ollama"
+[providers.local.model_config]
+model_name = "llama3"
+endpoint = "{}"
+"#,
+        ollama_endpoint
+    )
+}
+
+#[test]
+fn test_models_lists_and_probes_each_provider() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/tags");
+        then.status(200).json_body(json!({
+            "models": [
+                {"name": "llama3:latest", "details": {"parameter_size": "8.0B", "quantization_level": "Q4_0"}},
+                {"name": "mistral:7b", "details": {}}
+            ]
+        }));
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/api/chat");
+        then.status(200).json_body(json!({
+            "model": "llama3",
+            "message": {"role": "assistant", "content": "Ping!"},
+            "done": true,
+            "done_reason": "stop"
+        }));
+    });
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("Volition.toml"),
+        config(&server.url("/api/chat")),
+    )
+    .unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["models", "--probe"]));
+
+    let scripted = stdout.find("scripted (scripted, default)").unwrap();
+    let local = stdout.find("local (ollama)").unwrap();
+    assert!(scripted < local, "stdout:\n{}", stdout);
+    assert!(stdout.contains("* scripted-model"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("Tool calling with scripted-model: ok"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("* llama3:latest  8.0B Q4_0"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("  mistral:7b"), "stdout:\n{}", stdout);
+    assert!(
+        stdout.contains("Tool calling with llama3: answered without calling the tool"),
+        "stdout:\n{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_probe_offers_the_tool_natively_when_tool_calling_defaults_to_json() {
+    // llama3 lacks native tool calling in the catalog, so the agent would
+    // describe tools in the prompt; the probe still offers the tool natively.
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/tags");
+        then.status(200)
+            .json_body(json!({ "models": [{ "name": "llama3:latest", "details": {} }] }));
+    });
+    let chat = server.mock(|when, then| {
+        when.method(POST)
+            .path("/api/chat")
+            .body_contains("\"tools\"")
+            .body_contains("\"ping\"");
+        then.status(200).json_body(json!({
+            "model": "llama3",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "function": { "name": "ping", "arguments": { "status": "ok" } } }]
+            },
+            "done": true,
+            "done_reason": "stop"
+        }));
+    });
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("Volition.toml"),
+        config(&server.url("/api/chat")),
+    )
+    .unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["models", "--probe"]));
+
+    chat.assert();
+    assert!(stdout.contains("Tool calling with llama3: ok"), "stdout:\n{}", stdout);
+}
@@ -0,0 +1,45 @@
+// volition-cli/tests/response_cache.rs
+
+//! Runs single turns against a cached `scripted` provider, with the user cache
+//! directory redirected into a temporary directory.
+
+mod common;
+
+use common::{run, script, volition};
+use std::fs;
+
+const CONFIG: &str = r#"
+system_prompt = "You are a test assistant."
+default_provider = "scripted"
+
+[providers.scripted]
+type = "scripted"
+[providers.scripted.model_config]
+model_name = "scripted"
+script = "script.toml"
+[providers.scripted.model_config.cache]
+ttl_secs = 3600
+"#;
+
+#[test]
+fn test_repeated_turn_is_served_from_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), script("First reply.")).unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["--turn", "Hello"]));
+    assert!(stdout.contains("First reply."), "stdout:\n{}", stdout);
+
+    // The script changes, but the identical request is answered from the cache.
+    fs::write(dir.path().join("script.toml"), script("Second reply.")).unwrap();
+    let stdout = run(volition(dir.path(), dir.path()).args(["--turn", "Hello"]));
+    assert!(stdout.contains("First reply."), "stdout:\n{}", stdout);
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["--no-cache", "--turn", "Hello"]));
+    assert!(stdout.contains("Second reply."), "stdout:\n{}", stdout);
+
+    // `cache clear` works outside of a project.
+    let outside = tempfile::tempdir().unwrap();
+    let stdout = run(volition(dir.path(), outside.path()).args(["cache", "clear"]));
+    assert!(stdout.contains("Removed 1 cached response(s)"), "stdout:\n{}", stdout);
+}
@@ -0,0 +1,217 @@
+// volition-cli/tests/scripted_turn.rs
+
+//! Drives the `volition` binary end to end with a `scripted` provider, so no
+//! model server or API key is needed.
+
+mod common;
+
+use common::{run, volition};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CONFIG: &str = r#"
+system_prompt = "You are a test assistant."
+default_provider = "scripted"
+
+[providers.scripted]
+type = "scripted"
+[providers.scripted.model_config]
+model_name = "scripted"
+script = "script.toml"
+"#;
+
+const SCRIPT: &str = r#"
+[[turns]]
+match = "greet"
+content = "Hello from the script."
+
+[[turns]]
+match = "think"
+content = "The answer is 4."
+reasoning = "2 + 2 = 4, so the answer is 4."
+"#;
+
+#[test]
+fn test_single_turn_prints_scripted_reply_and_saves_history() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["--turn", "Please greet me"]));
+    assert!(stdout.contains("Hello from the script."), "stdout:\n{}", stdout);
+
+    let history: Vec<_> = fs::read_dir(dir.path().join(".volition/history"))
+        .unwrap()
+        .collect();
+    assert_eq!(history.len(), 1);
+}
+
+#[test]
+fn test_unscripted_message_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+
+    let output = volition(dir.path(), dir.path())
+        .args(["--turn", "Something else"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("API call failed"),
+        "stderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_image_is_attached_to_the_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+    fs::write(dir.path().join("screenshot.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+    run(volition(dir.path(), dir.path()).args([
+        "--turn",
+        "Please greet me",
+        "--image",
+        "screenshot.png",
+    ]));
+    let entry = fs::read_dir(dir.path().join(".volition/history"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let history: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(entry.path()).unwrap()).unwrap();
+    assert_eq!(history["messages"][0]["role"], "user");
+    assert_eq!(history["messages"][0]["content"], "Please greet me");
+    assert_eq!(
+        history["messages"][0]["parts"][0],
+        serde_json::json!({
+            "type": "image",
+            "source": { "base64": { "media_type": "image/png", "data": "iVBORw==" } }
+        })
+    );
+}
+
+#[test]
+fn test_unsupported_image_type_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+    fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+
+    let output = volition(dir.path(), dir.path())
+        .args(["--turn", "Please greet me", "--image", "notes.txt"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Unsupported image type"),
+        "stderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_reasoning_is_saved_apart_from_the_answer() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("script.toml"), SCRIPT).unwrap();
+
+    let stdout = run(
+        volition(dir.path(), dir.path()).args(["--turn", "Please think about 2 + 2"]),
+    );
+    assert!(stdout.contains("The answer is 4."), "stdout:\n{}", stdout);
+    assert!(!stdout.contains("2 + 2 = 4"), "stdout:\n{}", stdout);
+
+    let entry = fs::read_dir(dir.path().join(".volition/history"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let history: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(entry.path()).unwrap()).unwrap();
+    assert_eq!(history["messages"][1]["content"], "The answer is 4.");
+    assert_eq!(history["messages"][1]["reasoning"], "2 + 2 = 4, so the answer is 4.");
+}
+
+/// The filesystem MCP server binary, built next to `volition` when a test run
+/// did not already build it.
+fn filesystem_server() -> PathBuf {
+    let server = Path::new(env!("CARGO_BIN_EXE_volition"))
+        .with_file_name(format!("volition-filesystem-server{}", env::consts::EXE_SUFFIX));
+    if !server.exists() {
+        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let status = Command::new(cargo)
+            .args(["build", "-p", "volition-filesystem-server"])
+            .status()
+            .unwrap();
+        assert!(status.success(), "Failed to build volition-filesystem-server");
+    }
+    server
+}
+
+#[test]
+fn test_tool_call_runs_on_the_mcp_server_and_reaches_the_next_turn() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = format!(
+        "{}\n[mcp_servers.filesystem]\ncommand = {:?}\n",
+        CONFIG,
+        filesystem_server().to_string_lossy()
+    );
+    fs::write(dir.path().join("Volition.toml"), config).unwrap();
+    fs::write(
+        dir.path().join("script.toml"),
+        r#"
+[[turns]]
+match = "notes"
+content = "Let me read the notes."
+[[turns.tool_calls]]
+name = "read_file"
+arguments = { path = "notes.txt" }
+
+[[turns]]
+match = "marmalade"
+content = "The notes are about marmalade."
+"#,
+    )
+    .unwrap();
+    fs::write(dir.path().join("notes.txt"), "Buy marmalade on Tuesday.").unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["--turn", "Summarize my notes"]));
+    assert!(stdout.contains("The notes are about marmalade."), "stdout:\n{}", stdout);
+
+    let entry = fs::read_dir(dir.path().join(".volition/history"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let history: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(entry.path()).unwrap()).unwrap();
+    let messages = history["messages"].as_array().unwrap();
+    let call = messages[1]["tool_calls"][0].clone();
+    assert_eq!(call["function"]["name"], "read_file");
+    let result = messages
+        .iter()
+        .find(|message| message["role"] == "tool")
+        .unwrap();
+    assert_eq!(result["tool_call_id"], call["id"]);
+    assert!(
+        result["content"]
+            .as_str()
+            .unwrap()
+            .contains("Buy marmalade on Tuesday."),
+        "tool result: {}",
+        result
+    );
+    assert_eq!(
+        messages.last().unwrap()["content"],
+        "The notes are about marmalade."
+    );
+}
@@ -0,0 +1,74 @@
+// volition-cli/tests/usage_report.rs
+
+//! Runs `volition usage` against a hand-written conversation history.
+
+mod common;
+
+use common::{run, volition};
+use std::fs;
+
+const CONFIG: &str = r#"
+system_prompt = "You are a test assistant."
+default_provider = "priced"
+
+[providers.priced]
+type = "scripted"
+[providers.priced.model_config]
+model_name = "model-a"
+script = "script.toml"
+[providers.priced.model_config.pricing]
+input_per_million = 2.0
+output_per_million = 8.0
+
+[providers.free]
+type = "scripted"
+[providers.free.model_config]
+model_name = "model-b"
+script = "script.toml"
+"#;
+
+const HISTORY: &str = r#"{
+  "id": "6f1c2a4e-93f7-4d0b-8a47-2d1d0f5b6c11",
+  "created_at": "2026-03-01T10:00:00Z",
+  "last_updated_at": "2026-03-01T10:05:00Z",
+  "messages": [{"role": "user", "content": "Hi"}],
+  "usage": [
+    {"recorded_at": "2026-03-01T10:01:00Z", "provider_id": "priced", "model": "model-a",
+     "prompt_tokens": 1000000, "completion_tokens": 250000, "total_tokens": 1250000},
+    {"recorded_at": "2026-03-01T10:05:00Z", "provider_id": "free", "model": "model-b",
+     "prompt_tokens": 300, "completion_tokens": 20, "total_tokens": 320}
+  ]
+}"#;
+
+#[test]
+fn test_usage_report_totals_and_costs() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+    let history_dir = dir.path().join(".volition/history");
+    fs::create_dir_all(&history_dir).unwrap();
+    fs::write(
+        history_dir.join("6f1c2a4e-93f7-4d0b-8a47-2d1d0f5b6c11.json"),
+        HISTORY,
+    )
+    .unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).arg("usage"));
+    assert!(stdout.contains("6f1c2a4e-93f7-4d0b-8a47-2d1d0f5b6c11"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("Total: 2 calls, 1250320 tokens"), "stdout:\n{}", stdout);
+    // 1M input at 2.0 plus 0.25M output at 8.0; the unpriced model is flagged.
+    assert!(stdout.contains("estimated cost 4.0000*"), "stdout:\n{}", stdout);
+
+    let stdout = run(volition(dir.path(), dir.path()).args(["usage", "--by", "provider"]));
+    assert!(stdout.contains("priced (model-a)"), "stdout:\n{}", stdout);
+    assert!(stdout.contains("free (model-b)"), "stdout:\n{}", stdout);
+    assert!(!stdout.contains("Conversation"), "stdout:\n{}", stdout);
+}
+
+#[test]
+fn test_usage_report_without_history() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Volition.toml"), CONFIG).unwrap();
+
+    let stdout = run(volition(dir.path(), dir.path()).arg("usage"));
+    assert!(stdout.contains("No token usage recorded"), "stdout:\n{}", stdout);
+}
@@ -2,13 +2,17 @@
 
 use crate::UserInteraction;
 use crate::config::AgentConfig;
-use crate::errors::AgentError;
+use crate::errors::{AgentError, ProviderError};
 use crate::mcp::McpConnection;
-use crate::models::chat::{ApiResponse, ChatMessage};
+use crate::models::chat::{
+    ApiResponse, ChatMessage, Role, StreamDelta, TokenUsage, compact_messages, estimate_tokens,
+};
+use crate::models::request::RequestOptions;
 use crate::models::tools::{
     ToolDefinition, ToolParameter, ToolParameterType, ToolParametersDefinition,
 };
-use crate::providers::{Provider, ProviderRegistry};
+use crate::providers::streaming::DeltaSink;
+use crate::providers::{ProviderFactoryRegistry, ProviderRegistry};
 use crate::strategies::{NextStep, Strategy};
 use anyhow::{Context, Result, anyhow};
 use rmcp::model::Tool as McpTool;
@@ -16,21 +20,28 @@ use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
 use tracing::{debug, info, trace, warn};
 
 use crate::AgentState;
 
+/// Tool result for a call whose arguments were cut off by the output token
+/// limit. Such calls are never run, since their arguments are incomplete.
+const TRUNCATED_TOOL_CALL: &str = "Error: This tool call was cut off by the output length limit \
+and was not run. Re-emit the complete call, splitting large content over several calls if needed.";
+
 pub struct Agent<UI: UserInteraction> {
     provider_registry: ProviderRegistry,
     mcp_connections: HashMap<String, Arc<Mutex<McpConnection>>>,
     #[allow(dead_code)] // Field currently unused
     http_client: reqwest::Client,
-    #[allow(dead_code)] // Field currently unused
     ui_handler: Arc<UI>,
     strategy: Box<dyn Strategy<UI> + Send + Sync>,
     state: AgentState,
     current_provider_id: String,
+    /// Tool calls that failed in a row, passed to providers as a routing hint.
+    consecutive_tool_failures: u32,
 }
 
 fn mcp_schema_to_tool_params(schema_val: Option<&Map<String, Value>>) -> ToolParametersDefinition {
@@ -194,49 +205,7 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
 
         let provider_registry = match provider_registry_override {
             Some(registry) => registry,
-            None => {
-                let mut registry = ProviderRegistry::new(config.default_provider.clone());
-                for (id, provider_conf) in config.providers {
-                    let api_key = if !provider_conf.api_key_env_var.is_empty() {
-                        match std::env::var(&provider_conf.api_key_env_var) {
-                            Ok(key) => key,
-                            Err(e) => {
-                                warn!(provider_id = %id, env_var = %provider_conf.api_key_env_var, error = %e, "API key environment variable not set or invalid");
-                                String::new()
-                            }
-                        }
-                    } else {
-                        String::new()
-                    };
-                    let model_config = provider_conf.model_config;
-                    let provider: Box<dyn Provider> = match provider_conf.provider_type.as_str() {
-                        "gemini" => Box::new(crate::providers::gemini::GeminiProvider::new(
-                            model_config,
-                            http_client.clone(),
-                            api_key,
-                        )),
-                        "ollama" => Box::new(crate::providers::ollama::OllamaProvider::new(
-                            model_config,
-                            http_client.clone(),
-                            api_key, // Note: OllamaProvider ignores the key in its `new` fn
-                        )),
-                        "openai" => Box::new(crate::providers::openai::OpenAIProvider::new(
-                            model_config,
-                            http_client.clone(),
-                            api_key,
-                        )),
-                        _ => {
-                            return Err(anyhow!(
-                                "Unsupported provider type: '{}' specified for provider ID '{}'. Supported types: gemini, ollama, openai.",
-                                provider_conf.provider_type,
-                                id // Added provider ID to error message for clarity
-                            ));
-                        }
-                    };
-                    registry.register(id.clone(), provider); // Register the created provider instance
-                }
-                registry
-            }
+            None => ProviderFactoryRegistry::default().build_registry(&config, &http_client)?,
         };
 
         let mcp_connections = match mcp_connections_override {
@@ -268,6 +237,7 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
             strategy,
             state: initial_state,
             current_provider_id: default_provider_id,
+            consecutive_tool_failures: 0,
         })
     }
 
@@ -297,10 +267,126 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
     ) -> Result<ApiResponse> {
         let provider = self.provider_registry.get(&self.current_provider_id)?;
         debug!(provider = %self.current_provider_id, num_messages = messages.len(), "Getting completion from provider");
-        provider.get_completion(messages, tools).await
+        provider.get_completion(messages, tools, options).await
+    }
+
+    /// Streams a completion from the current provider, forwarding each delta to the UI.
+    pub async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let provider = self.provider_registry.get(&self.current_provider_id)?;
+        if !provider.capabilities().streaming {
+            debug!(provider = %self.current_provider_id, "Model does not stream; requesting a complete response");
+            return provider.get_completion(messages, tools, options).await;
+        }
+        debug!(provider = %self.current_provider_id, num_messages = messages.len(), "Streaming completion from provider");
+        let ui = &self.ui_handler;
+        provider
+            .stream_completion(messages, tools, options, &|delta: StreamDelta| {
+                ui.on_stream_delta(&delta);
+                on_delta(delta);
+            })
+            .await
+    }
+
+    /// Streams a completion like [`stream_completion`](Self::stream_completion),
+    /// but when the provider rejects the prompt as longer than the model's
+    /// context window, drops the oldest exchanges and tries again until none
+    /// are left to drop.
+    async fn compact_and_complete(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        loop {
+            let error = match self.stream_completion(messages.clone(), tools, options, on_delta).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if !matches!(
+                ProviderError::find(error.as_ref()),
+                Some(ProviderError::ContextLengthExceeded(_))
+            ) {
+                return Err(error);
+            }
+            let compacted = compact_messages(messages.clone(), estimate_tokens(&messages) / 2);
+            if compacted.len() == messages.len() {
+                return Err(error);
+            }
+            info!(
+                provider = %self.current_provider_id,
+                dropped = messages.len() - compacted.len(),
+                "Model rejected the prompt as too long; dropped the oldest messages and retrying."
+            );
+            messages = compacted;
+        }
+    }
+
+    /// Prepares the conversation for the current provider's model: fails up
+    /// front on images or a response format the model cannot accept, and drops
+    /// the oldest exchanges once the prompt nears the model's context window.
+    fn fit_to_model(&self, messages: &[ChatMessage], options: &RequestOptions) -> Result<Vec<ChatMessage>> {
+        let provider = self.provider_registry.get(&self.current_provider_id)?;
+        let capabilities = provider.capabilities();
+        if !capabilities.images && messages.iter().any(|m| m.images().next().is_some()) {
+            return Err(ProviderError::Unsupported(format!(
+                "Model {} does not accept images. Set `capabilities.images = true` in its model_config if it does.",
+                provider.name()
+            ))
+            .into());
+        }
+        if !capabilities.json_mode && options.response_format.is_some() {
+            return Err(ProviderError::Unsupported(format!(
+                "Model {} does not support a JSON schema response format. Set `capabilities.json_mode = true` in its model_config if it does.",
+                provider.name()
+            ))
+            .into());
+        }
+        let Some(threshold) = capabilities.compaction_threshold() else {
+            return Ok(messages.to_vec());
+        };
+        let compacted = compact_messages(messages.to_vec(), threshold);
+        if compacted.len() < messages.len() {
+            info!(
+                provider = %self.current_provider_id,
+                dropped = messages.len() - compacted.len(),
+                threshold,
+                "Prompt nears the context window; dropped the oldest messages from the request."
+            );
+        }
+        Ok(compacted)
+    }
+
+    /// Appends the token counts of `response` to the run's usage records.
+    fn record_usage(&mut self, response: &ApiResponse) {
+        let model = self
+            .provider_registry
+            .get(&self.current_provider_id)
+            .map(|provider| provider.name().to_string())
+            .unwrap_or_default();
+        debug!(
+            provider = %self.current_provider_id,
+            prompt_tokens = response.prompt_tokens,
+            completion_tokens = response.completion_tokens,
+            "Recording token usage."
+        );
+        self.state.usage.push(TokenUsage {
+            provider_id: self.current_provider_id.clone(),
+            model,
+            prompt_tokens: response.prompt_tokens,
+            completion_tokens: response.completion_tokens,
+            total_tokens: response.total_tokens,
+        });
     }
 
     pub async fn call_mcp_tool(
@@ -349,6 +435,10 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
         info!(strategy = self.strategy.name(), "Starting MCP agent run.");
 
         let mut next_step = self.strategy.initialize_interaction(&mut self.state)?;
+        // Whether the UI already received the latest assistant text as stream deltas.
+        let text_streamed = AtomicBool::new(false);
+        // Whether the latest response stopped at the output token limit.
+        let mut truncated = false;
 
         loop {
             trace!(?next_step, "Processing next step.");
@@ -380,15 +470,30 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
                         "Sending request to AI provider."
                     );
 
+                    // Request options apply to this call only.
+                    let mut request_options = std::mem::take(&mut self.state.request_options);
+                    request_options.tool_failures = self.consecutive_tool_failures;
+                    let messages = self
+                        .fit_to_model(&self.state.messages, &request_options)
+                        .map_err(|e| AgentError::Api(e.context("Conversation cannot be sent to the model")))?;
+                    text_streamed.store(false, Ordering::Relaxed);
                     let api_response = self
-                        .get_completion(
-                            self.state.messages.clone(),
+                        .compact_and_complete(
+                            messages,
                             if tool_definitions.is_empty() { None } else { Some(&tool_definitions) },
+                            &request_options,
+                            &|delta: StreamDelta| {
+                                if matches!(delta, StreamDelta::Text(_)) {
+                                    text_streamed.store(true, Ordering::Relaxed);
+                                }
+                            },
                         )
                         .await
                         .map_err(|e| AgentError::Api(e.context("API call failed during agent run")))?;
 
                     debug!("Received response from AI.");
+                    self.record_usage(&api_response);
+                    truncated = api_response.is_truncated();
                     trace!(response = %serde_json::to_string_pretty(&api_response).unwrap_or_default(), "Full API Response");
 
                     next_step = self
@@ -406,15 +511,14 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
                         ));
                     }
 
-                    // Print assistant message before tool execution
-                    if let Some(last_message) = self.state.messages.last() {
-                        if last_message.role == "assistant" {
-                            if let Some(content) = &last_message.content {
-                                if !content.trim().is_empty() {
-                                     println!("\nAssistant: {}", content);
-                                }
-                            }
-                        }
+                    // Print assistant message before tool execution, unless it was streamed
+                    if !text_streamed.load(Ordering::Relaxed)
+                        && let Some(last_message) = self.state.messages.last()
+                        && last_message.role == Role::Assistant
+                        && let content = last_message.text()
+                        && !content.trim().is_empty()
+                    {
+                        println!("\nAssistant: {}", content);
                     }
 
                     info!(
@@ -426,6 +530,15 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
                     let mut tool_results = Vec::new();
                     for tool_call in &tool_calls_to_execute {
                         let tool_name = &tool_call.function.name;
+                        if truncated {
+                            warn!(tool_call_id = %tool_call.id, tool_name = %tool_name, "Not running a tool call cut off by the output length limit.");
+                            tool_results.push(crate::ToolResult {
+                                tool_call_id: tool_call.id.clone(),
+                                output: TRUNCATED_TOOL_CALL.to_string(),
+                                status: crate::ToolExecutionStatus::Failure,
+                            });
+                            continue;
+                        }
                         let args: Value = serde_json::from_str(&tool_call.function.arguments)
                             .map_err(|e| {
                                 warn!(tool_call_id = %tool_call.id, tool_name=%tool_name, args_str=%tool_call.function.arguments, error=%e, "Failed to parse tool arguments JSON string. Using null.");
@@ -500,6 +613,13 @@ impl<UI: UserInteraction + 'static> Agent<UI> {
                         }
                     } // End of for tool_call loop
 
+                    for result in &tool_results {
+                        self.consecutive_tool_failures = match result.status {
+                            crate::ToolExecutionStatus::Success => 0,
+                            crate::ToolExecutionStatus::Failure => self.consecutive_tool_failures + 1,
+                        };
+                    }
+
                     // Log summary
                     let results_map: HashMap<_, _> = tool_results
                         .iter()
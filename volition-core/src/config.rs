@@ -2,8 +2,9 @@
 
 //! Handles configuration structures and parsing for the agent library.
 
+use crate::providers::router::ROUTER_TYPE;
 use anyhow::{Context, Result, anyhow};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use url::Url;
 
@@ -26,10 +27,23 @@ pub struct ProviderInstanceConfig {
     // Use `type` in TOML, map to `provider_type`
     #[serde(rename = "type")]
     pub provider_type: String,
+    /// Environment variable holding the API key. Required for hosted provider types.
+    #[serde(default)]
     pub api_key_env_var: String,
     pub model_config: ModelConfig,
 }
 
+impl ProviderInstanceConfig {
+    /// Whether the provider calls a hosted API that requires a key. Local
+    /// servers, custom provider types and replayed providers need none.
+    pub fn needs_api_key(&self) -> bool {
+        matches!(
+            self.provider_type.as_str(),
+            "anthropic" | "azure_openai" | "gemini" | "openai"
+        ) && self.model_config.cassette.is_none()
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct McpServerConfig {
     pub command: String,
@@ -43,13 +57,255 @@ pub struct StrategyConfig {
     pub execution_provider: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct ModelConfig {
+    /// Required for every provider type except `router`.
+    #[serde(default)]
     pub model_name: String,
+    /// Generation settings, mapped by each provider to its request format.
     #[serde(default)]
-    pub parameters: Option<toml::Value>,
+    pub parameters: GenerationParams,
     #[serde(default)]
     pub endpoint: Option<String>,
+    /// How tools are offered to the model. Defaults to the provider's native
+    /// function calling, or `json` for models whose capabilities lack `tools`.
+    #[serde(default)]
+    pub tool_calling: Option<ToolCallingMode>,
+    /// Azure OpenAI deployment name. Defaults to `model_name`. Ignored by other provider types.
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Azure OpenAI `api-version` query parameter. Ignored by other provider types.
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// Cassette file to serve recorded responses from instead of calling the API.
+    /// Ignored by the `scripted` provider type.
+    #[serde(default)]
+    pub cassette: Option<String>,
+    /// Script of canned turns served by `scripted` providers. Ignored by other provider types.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Cassette file to append every request body and raw response of this
+    /// provider to. Ignored by the `scripted` provider type.
+    #[serde(default)]
+    pub record_to: Option<String>,
+    /// Quirk switches for `openai_compatible` servers. Ignored by other provider types.
+    #[serde(default)]
+    pub compatibility: Option<CompatibilityConfig>,
+    /// Token prices used to estimate the cost of recorded usage.
+    #[serde(default)]
+    pub pricing: Option<PricingConfig>,
+    /// Send the model's earlier reasoning back on later turns, inlined as
+    /// `<think>` blocks. By default reasoning is left out of the context.
+    #[serde(default)]
+    pub keep_reasoning: bool,
+    /// How many times a response cut off by the output token limit is
+    /// continued. Defaults to 3; `0` returns truncated responses as they are.
+    #[serde(default)]
+    pub max_continuations: Option<u32>,
+    /// Overrides the built-in capabilities of the model.
+    #[serde(default)]
+    pub capabilities: CapabilitiesConfig,
+    /// Timeouts, proxy, certificates and headers for this provider's requests.
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// Caches responses on disk and serves identical requests from the cache.
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    /// Rules of a `router` provider, tried in order. Ignored by other provider types.
+    #[serde(default)]
+    pub routes: Vec<RouteRule>,
+    /// Provider ID a `router` sends requests matching no rule to. Ignored by
+    /// other provider types.
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+/// One rule of a `router` provider. A request matches when it meets every
+/// condition that is set; a rule without conditions matches every request.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RouteRule {
+    /// Provider ID that matching requests are sent to.
+    pub provider: String,
+    /// Matches prompts estimated at this many tokens or more.
+    #[serde(default)]
+    pub min_prompt_tokens: Option<usize>,
+    /// Matches prompts estimated at this many tokens or fewer.
+    #[serde(default)]
+    pub max_prompt_tokens: Option<usize>,
+    /// Matches requests that offer tools (`true`) or none (`false`).
+    #[serde(default)]
+    pub tools: Option<bool>,
+    /// Matches requests made in this strategy phase, e.g. `"planning"`.
+    #[serde(default)]
+    pub phase: Option<String>,
+    /// Matches when the latest user message contains any of these words as a
+    /// whole word, ignoring case.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Matches after this many tool calls failed in a row.
+    #[serde(default)]
+    pub min_tool_failures: Option<u32>,
+}
+
+/// Settings of the response cache of one provider.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// Seconds a cached response is served. Defaults to one day.
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Always send requests, still storing their responses.
+    #[serde(default)]
+    pub bypass: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_cache_ttl_secs(),
+            bypass: false,
+        }
+    }
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+/// HTTP settings of one provider. Unset fields keep reqwest's defaults, which
+/// include honoring the `HTTPS_PROXY` environment variable and no timeouts.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HttpConfig {
+    /// Seconds allowed for establishing a connection.
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds a response may go without sending any data: the whole response
+    /// for non-streaming requests, the gap between chunks for streams.
+    #[serde(default)]
+    pub read_timeout_secs: Option<u64>,
+    /// Proxy URL for all requests, e.g. `http://proxy.corp:3128`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// PEM file with root certificates to trust in addition to the system's.
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+    /// Headers sent with every request.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// What a model supports, for models the built-in catalog gets wrong or lacks.
+/// Unset fields keep the catalog value.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CapabilitiesConfig {
+    #[serde(default)]
+    pub tools: Option<bool>,
+    #[serde(default)]
+    pub images: Option<bool>,
+    #[serde(default)]
+    pub streaming: Option<bool>,
+    #[serde(default)]
+    pub json_mode: Option<bool>,
+    /// Context window in tokens.
+    #[serde(default)]
+    pub context_window: Option<u32>,
+}
+
+/// Generation settings shared by all provider types.
+///
+/// Each provider maps the canonical keys to its own request fields and skips
+/// those it has no equivalent for. Any other key is provider-specific and is
+/// merged into the request body unchanged, so new API options can be used
+/// without a code change.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct GenerationParams {
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    /// Upper bound on generated tokens per response.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Stop sequences.
+    #[serde(default)]
+    pub stop: Vec<String>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    /// Context window size for Ollama models. Ignored by other provider types.
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    /// How long Ollama keeps the model loaded, e.g. `"10m"`. Ignored by other provider types.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Provider-specific request fields, merged into the request body as-is.
+    #[serde(flatten)]
+    pub extra: toml::value::Table,
+}
+
+/// Prices of a model, per million tokens, in whatever currency the user bills in.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PricingConfig {
+    #[serde(default)]
+    pub input_per_million: f64,
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+impl PricingConfig {
+    /// Estimated cost of a request with the given token counts.
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.input_per_million
+            + completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Describes how an `openai_compatible` server differs from api.openai.com.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CompatibilityConfig {
+    /// Extra headers sent with every request, e.g. a gateway token.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Set to false for servers that reject the `tools` request field.
+    #[serde(default = "default_true")]
+    pub supports_tools: bool,
+    /// Set to false for servers that reject `stream_options` on streamed requests.
+    #[serde(default = "default_true")]
+    pub supports_stream_usage: bool,
+    /// Response field holding `prompt_tokens`/`completion_tokens`.
+    #[serde(default = "default_usage_field")]
+    pub usage_field: String,
+}
+
+impl Default for CompatibilityConfig {
+    fn default() -> Self {
+        Self {
+            headers: HashMap::new(),
+            supports_tools: true,
+            supports_stream_usage: true,
+            usage_field: default_usage_field(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_usage_field() -> String {
+    "usage".to_string()
+}
+
+/// Selects how tool definitions and tool calls are exchanged with a model.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCallingMode {
+    /// Use the provider API's native tool/function calling.
+    #[default]
+    Native,
+    /// Describe tools in the prompt and parse fenced ```tool_call JSON blocks.
+    Json,
+    /// Describe tools in the prompt and parse `<tool_call>` XML tags.
+    Xml,
 }
 
 impl AgentConfig {
@@ -57,83 +313,261 @@ impl AgentConfig {
         let config: AgentConfig = match toml::from_str(config_toml_content) {
             Ok(cfg) => cfg,
             Err(e) => {
-                tracing::error!(error=%e, content=%config_toml_content, "Failed to parse TOML content");
+                // The content is not logged, as it may hold secrets.
+                tracing::error!(error=%e, "Failed to parse TOML content");
                 return Err(anyhow!(e))
                     .context("Failed to parse configuration TOML content. Check TOML syntax.");
             }
         };
+        config.validate()?;
+        tracing::info!("Successfully parsed and validated agent configuration.");
+        Ok(config)
+    }
 
-        // --- Basic Checks ---
-        if config.system_prompt.trim().is_empty() {
-            return Err(anyhow!("'system_prompt' in config content is empty."));
+    /// Like [`AgentConfig::from_toml_str`], for a configuration already parsed
+    /// into a TOML value, e.g. one merged from several files.
+    pub fn from_toml_value(value: toml::Value) -> Result<AgentConfig> {
+        let config: AgentConfig = value
+            .try_into()
+            .context("Failed to read configuration. Check key names and value types.")?;
+        config.validate()?;
+        tracing::info!("Successfully validated agent configuration.");
+        Ok(config)
+    }
+
+    /// Checks required keys and the values of provider and MCP server entries,
+    /// failing with the first problem found.
+    pub fn validate(&self) -> Result<()> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(anyhow!(problem.message)),
+            None => Ok(()),
         }
-        if config.default_provider.trim().is_empty() {
-            return Err(anyhow!(
-                "'default_provider' key in config content is empty."
+    }
+
+    /// Every problem [`AgentConfig::validate`] checks for, in key order.
+    pub fn problems(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        // --- Basic Checks ---
+        if self.system_prompt.trim().is_empty() {
+            problems.push(ConfigProblem::new(
+                "system_prompt",
+                "'system_prompt' in config content is empty.",
             ));
         }
-        if !config.providers.contains_key(&config.default_provider) {
-            return Err(anyhow!(
-                "Default provider '{}' not found in [providers] map.",
-                config.default_provider
+        if self.default_provider.trim().is_empty() {
+            problems.push(ConfigProblem::new(
+                "default_provider",
+                "'default_provider' key in config content is empty.",
+            ));
+        } else if !self.providers.contains_key(&self.default_provider) {
+            problems.push(ConfigProblem::new(
+                "default_provider",
+                format!(
+                    "Default provider '{}' not found in [providers] map.",
+                    self.default_provider
+                ),
             ));
         }
 
         // --- Provider Validation ---
-        for (key, provider) in &config.providers {
+        let mut providers: Vec<_> = self.providers.iter().collect();
+        providers.sort_by_key(|(key, _)| *key);
+        for (key, provider) in providers {
+            let path = |suffix: &str| format!("providers.{}.{}", key, suffix);
             // Check provider_type (which corresponds to `type` in TOML)
             if provider.provider_type.trim().is_empty() {
-                return Err(anyhow!(
-                    "Provider '{}' is missing 'type' (provider_type).",
-                    key
+                problems.push(ConfigProblem::new(
+                    path("type"),
+                    format!("Provider '{}' is missing 'type' (provider_type).", key),
                 ));
+                continue;
+            }
+            if provider.provider_type == ROUTER_TYPE {
+                self.check_router(key, &provider.model_config, &mut problems);
+                continue;
             }
             if provider.model_config.model_name.trim().is_empty() {
-                return Err(anyhow!(
-                    "Provider '{}' is missing 'model_config.model_name'.",
-                    key
+                problems.push(ConfigProblem::new(
+                    path("model_config.model_name"),
+                    format!("Provider '{}' is missing 'model_config.model_name'.", key),
+                ));
+            }
+            if provider.api_key_env_var.trim().is_empty() && provider.needs_api_key() {
+                problems.push(ConfigProblem::new(
+                    path("api_key_env_var"),
+                    format!("Provider '{}' is missing 'api_key_env_var'.", key),
                 ));
             }
-            if provider.api_key_env_var.trim().is_empty() && provider.provider_type != "ollama" {
-                // Allow empty for ollama
-                return Err(anyhow!("Provider '{}' is missing 'api_key_env_var'.", key));
+            if matches!(
+                provider.provider_type.as_str(),
+                "openai_compatible" | "azure_openai"
+            ) && provider.model_config.endpoint.is_none()
+            {
+                problems.push(ConfigProblem::new(
+                    path("model_config.endpoint"),
+                    format!(
+                        "Provider '{}' of type '{}' requires 'model_config.endpoint'.",
+                        key, provider.provider_type
+                    ),
+                ));
             }
+            // Ollama has a default endpoint; the other types build theirs from the model.
             if let Some(endpoint) = &provider.model_config.endpoint {
                 if endpoint.trim().is_empty() {
-                    return Err(anyhow!(
-                        "Provider '{}' has an empty 'model_config.endpoint'.",
-                        key
+                    problems.push(ConfigProblem::new(
+                        path("model_config.endpoint"),
+                        format!("Provider '{}' has an empty 'model_config.endpoint'.", key),
+                    ));
+                } else if let Err(e) = Url::parse(endpoint) {
+                    problems.push(ConfigProblem::new(
+                        path("model_config.endpoint"),
+                        format!(
+                            "Invalid URL format for endpoint ('{}') in provider '{}': {}",
+                            endpoint, key, e
+                        ),
                     ));
                 }
-                Url::parse(endpoint).with_context(|| {
-                    format!(
-                        "Invalid URL format for endpoint ('{}') in provider '{}'.",
-                        endpoint, key
-                    )
-                })?;
-            } else if provider.provider_type != "ollama" {
-                // Allow missing endpoint if type is ollama (it has a default)
-                // Consider adding validation if endpoint is strictly required for other types
             }
-            if let Some(params) = &provider.model_config.parameters {
-                if !params.is_table() && !params.is_str() {
-                    return Err(anyhow!(
-                        "Provider '{}' has invalid 'model_config.parameters'. Expected a TOML table or string.",
+            if provider.model_config.cassette.is_some()
+                && provider.model_config.record_to.is_some()
+            {
+                problems.push(ConfigProblem::new(
+                    path("model_config.record_to"),
+                    format!(
+                        "Provider '{}' sets both 'model_config.cassette' and 'model_config.record_to'; it can either replay or record.",
                         key
+                    ),
+                ));
+            }
+            // Only the HTTP providers record and replay their request bodies.
+            let uses_wire = matches!(
+                provider.provider_type.as_str(),
+                "anthropic" | "azure_openai" | "gemini" | "ollama" | "openai" | "openai_compatible"
+            );
+            let cassette_keys = [
+                ("cassette", &provider.model_config.cassette),
+                ("record_to", &provider.model_config.record_to),
+            ];
+            for (name, value) in cassette_keys {
+                if value.is_some() && !uses_wire {
+                    problems.push(ConfigProblem::new(
+                        path(&format!("model_config.{}", name)),
+                        format!(
+                            "Provider '{}' of type '{}' cannot use 'model_config.{}'; only HTTP provider types record and replay sessions.",
+                            key, provider.provider_type, name
+                        ),
                     ));
                 }
             }
+            let params = &provider.model_config.parameters;
+            if params.temperature.is_some_and(|t| t < 0.0)
+                || params.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p))
+            {
+                problems.push(ConfigProblem::new(
+                    path("model_config.parameters"),
+                    format!(
+                        "Provider '{}' has an out-of-range 'temperature' or 'top_p' in 'model_config.parameters'.",
+                        key
+                    ),
+                ));
+            }
+            if let Some(pricing) = &provider.model_config.pricing
+                && (pricing.input_per_million < 0.0 || pricing.output_per_million < 0.0)
+            {
+                problems.push(ConfigProblem::new(
+                    path("model_config.pricing"),
+                    format!(
+                        "Provider '{}' has a negative price in 'model_config.pricing'.",
+                        key
+                    ),
+                ));
+            }
         }
 
         // --- MCP Server Validation ---
-        for (key, server) in &config.mcp_servers {
+        let mut servers: Vec<_> = self.mcp_servers.iter().collect();
+        servers.sort_by_key(|(key, _)| *key);
+        for (key, server) in servers {
             if server.command.trim().is_empty() {
-                return Err(anyhow!("MCP Server '{}' has an empty 'command'.", key));
+                problems.push(ConfigProblem::new(
+                    format!("mcp_servers.{}.command", key),
+                    format!("MCP Server '{}' has an empty 'command'.", key),
+                ));
             }
         }
+        problems
+    }
 
-        tracing::info!("Successfully parsed and validated agent configuration.");
-        Ok(config)
+    /// Checks that the rules and fallback of router `key` name providers that
+    /// exist and are not routers themselves.
+    fn check_router(
+        &self,
+        key: &str,
+        model_config: &ModelConfig,
+        problems: &mut Vec<ConfigProblem>,
+    ) {
+        let Some(fallback) = model_config.fallback.as_deref() else {
+            problems.push(ConfigProblem::new(
+                format!("providers.{}.model_config.fallback", key),
+                format!(
+                    "Router provider '{}' is missing 'model_config.fallback'.",
+                    key
+                ),
+            ));
+            return;
+        };
+        let targets = model_config
+            .routes
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                (
+                    format!("providers.{}.model_config.routes[{}].provider", key, i),
+                    rule.provider.as_str(),
+                )
+            })
+            .chain(std::iter::once((
+                format!("providers.{}.model_config.fallback", key),
+                fallback,
+            )));
+        for (path, target) in targets {
+            match self.providers.get(target) {
+                None => problems.push(ConfigProblem::new(
+                    path,
+                    format!(
+                        "Router provider '{}' routes to unknown provider '{}'.",
+                        key, target
+                    ),
+                )),
+                Some(provider) if provider.provider_type == ROUTER_TYPE => {
+                    problems.push(ConfigProblem::new(
+                        path,
+                        format!(
+                            "Router provider '{}' routes to '{}', which is another router.",
+                            key, target
+                        ),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// A problem found in the configuration, with the dotted path of the key it
+/// concerns, e.g. `providers.local.model_config.endpoint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
     }
 }
 
@@ -201,11 +635,12 @@ mod tests {
             config.providers["openai_fast"].model_config.model_name,
             "gpt-4o-mini"
         );
-        assert!(
+        assert_eq!(
             config.providers["gemini_default"]
                 .model_config
                 .parameters
-                .is_some()
+                .temperature,
+            Some(0.6)
         );
         assert_eq!(config.mcp_servers.len(), 2);
         assert_eq!(config.mcp_servers["filesystem"].command, "echo");
@@ -238,5 +673,162 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_openai_compatible_without_api_key() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "local"
+            [providers.local]
+            type = "openai_compatible"
+            [providers.local.model_config]
+                model_name = "qwen2.5-coder"
+                endpoint = "http://localhost:8080/v1/chat/completions"
+                [providers.local.model_config.compatibility]
+                supports_tools = false
+                headers = { "X-Gateway" = "dev" }
+        "#;
+        let config = AgentConfig::from_toml_str(content).unwrap();
+        let compatibility = config.providers["local"]
+            .model_config
+            .compatibility
+            .clone()
+            .unwrap();
+        assert!(!compatibility.supports_tools);
+        assert!(compatibility.supports_stream_usage);
+        assert_eq!(compatibility.usage_field, "usage");
+        assert_eq!(compatibility.headers["X-Gateway"], "dev");
+    }
+
+    #[test]
+    fn test_openai_compatible_requires_endpoint() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "local"
+            [providers.local]
+            type = "openai_compatible"
+            [providers.local.model_config]
+                model_name = "qwen2.5-coder"
+        "#;
+        let error_string = AgentConfig::from_toml_str(content).unwrap_err().to_string();
+        assert!(
+            error_string.contains("requires 'model_config.endpoint'"),
+            "Unexpected error message: {}",
+            error_string
+        );
+    }
+
+    #[test]
+    fn test_router_targets_must_exist() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "auto"
+            [providers.local]
+            type = "ollama"
+            [providers.local.model_config]
+                model_name = "llama3"
+            [providers.auto]
+            type = "router"
+            [providers.auto.model_config]
+                fallback = "local"
+                [[providers.auto.model_config.routes]]
+                provider = "hosted"
+                keywords = ["refactor"]
+        "#;
+        let error_string = AgentConfig::from_toml_str(content).unwrap_err().to_string();
+        assert!(
+            error_string.contains("routes to unknown provider 'hosted'"),
+            "Unexpected error message: {}",
+            error_string
+        );
+
+        let config = AgentConfig::from_toml_str(&content.replace("hosted", "local")).unwrap();
+        let routes = &config.providers["auto"].model_config.routes;
+        assert_eq!(routes[0].keywords, vec!["refactor".to_string()]);
+    }
+
+    #[test]
+    fn test_cassettes_require_an_http_provider() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "local"
+            [providers.local]
+            type = "scripted"
+            [providers.local.model_config]
+                model_name = "script"
+                record_to = "session.json"
+        "#;
+        let error_string = AgentConfig::from_toml_str(content).unwrap_err().to_string();
+        assert!(
+            error_string.contains("type 'scripted' cannot use 'model_config.record_to'"),
+            "Unexpected error message: {}",
+            error_string
+        );
+
+        let content = content.replace("scripted", "ollama");
+        assert!(AgentConfig::from_toml_str(&content).is_ok());
+    }
+
+    #[test]
+    fn test_pricing_parse_and_cost() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "local"
+            [providers.local]
+            type = "ollama"
+            [providers.local.model_config]
+                model_name = "qwen2.5-coder"
+                [providers.local.model_config.pricing]
+                input_per_million = 2.5
+                output_per_million = 10.0
+        "#;
+        let config = AgentConfig::from_toml_str(content).unwrap();
+        let pricing = config.providers["local"].model_config.pricing.unwrap();
+        assert_eq!(pricing.cost(1_000_000, 500_000), 7.5);
+
+        let negative = content.replace("2.5", "-1.0");
+        let error_string = AgentConfig::from_toml_str(&negative).unwrap_err().to_string();
+        assert!(
+            error_string.contains("negative price"),
+            "Unexpected error message: {}",
+            error_string
+        );
+    }
+
+    #[test]
+    fn test_generation_params_keep_unknown_keys() {
+        let content = r#"
+            system_prompt = "Valid"
+            default_provider = "local"
+            [providers.local]
+            type = "ollama"
+            [providers.local.model_config]
+                model_name = "qwen2.5-coder"
+                [providers.local.model_config.parameters]
+                temperature = 0.2
+                max_tokens = 512
+                stop = ["</answer>"]
+                num_ctx = 32768
+                keep_alive = "10m"
+                options = { top_k = 40 }
+        "#;
+        let config = AgentConfig::from_toml_str(content).unwrap();
+        let params = &config.providers["local"].model_config.parameters;
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.max_tokens, Some(512));
+        assert_eq!(params.stop, vec!["</answer>".to_string()]);
+        assert_eq!(params.num_ctx, Some(32768));
+        assert_eq!(params.keep_alive.as_deref(), Some("10m"));
+        assert_eq!(params.extra.len(), 1);
+        assert_eq!(params.extra["options"]["top_k"].as_integer(), Some(40));
+
+        let invalid = content.replace("temperature = 0.2", "top_p = 1.5");
+        let error_string = AgentConfig::from_toml_str(&invalid).unwrap_err().to_string();
+        assert!(
+            error_string.contains("out-of-range"),
+            "Unexpected error message: {}",
+            error_string
+        );
+    }
+
     // Add more tests for other validation rules
 }
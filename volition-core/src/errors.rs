@@ -1,4 +1,6 @@
 // volition-agent-core/src/errors.rs
+use serde_json::Value;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur during Agent execution.
@@ -38,5 +40,304 @@ impl AgentError {
     pub fn config(msg: impl Into<String>) -> Self {
         AgentError::Config(msg.into())
     }
-    // Keep other helpers if needed
+
+    /// Returns the [`ProviderError`] behind this error, if a provider call failed.
+    pub fn provider_error(&self) -> Option<&ProviderError> {
+        ProviderError::find(self)
+    }
+}
+
+/// Classified failure of a model provider call.
+///
+/// Providers return these inside `anyhow::Error`, so they may be wrapped in
+/// context; use [`ProviderError::find`] or [`AgentError::provider_error`] to get
+/// them back out.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ProviderError {
+    /// The API key is missing, invalid or lacks access.
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// Too many requests; `retry_after` is the delay the provider asked for, if any.
+    #[error("Rate limited{}: {message}", retry_after.map(|d| format!(" (retry after {}s)", d.as_secs())).unwrap_or_default())]
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+
+    /// The prompt does not fit in the model's context window.
+    #[error("Context length exceeded: {0}")]
+    ContextLengthExceeded(String),
+
+    /// The prompt or completion was blocked by the provider's content filter.
+    #[error("Content filtered: {0}")]
+    ContentFiltered(String),
+
+    /// The configured model or deployment does not exist.
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+
+    /// The request did not complete, e.g. connection refused or timed out.
+    #[error("Transport error: {0}")]
+    Transport(String),
+
+    /// The response could not be parsed.
+    #[error("Malformed response: {0}")]
+    MalformedResponse(String),
+
+    /// The provider cannot honor a request option, e.g. a forced tool choice.
+    #[error("Unsupported request: {0}")]
+    Unsupported(String),
+
+    /// Any other error reported by the provider.
+    #[error("Provider error{}: {message}", status.map(|s| format!(" (HTTP {})", s)).unwrap_or_default())]
+    Other {
+        status: Option<u16>,
+        message: String,
+    },
+}
+
+impl ProviderError {
+    /// Classifies an unsuccessful HTTP response from its status code and body.
+    pub fn from_status(status: u16, retry_after: Option<Duration>, body: &str) -> Self {
+        let (message, codes) = match serde_json::from_str::<Value>(body) {
+            Ok(parsed) if !parsed["error"].is_null() => error_details(&parsed["error"]),
+            Ok(parsed) => error_details(&parsed),
+            Err(_) => (None, Vec::new()),
+        };
+        let message = message.unwrap_or_else(|| body.trim().to_string());
+        match status {
+            401 | 403 => ProviderError::Unauthorized(message),
+            429 => ProviderError::RateLimited {
+                retry_after,
+                message,
+            },
+            404 => ProviderError::ModelNotFound(message),
+            _ => match Self::classify(message, &codes, retry_after) {
+                Ok(error) => error,
+                Err(message) if status == 529 => ProviderError::RateLimited {
+                    retry_after,
+                    message,
+                },
+                Err(message) => ProviderError::Other {
+                    status: Some(status),
+                    message,
+                },
+            },
+        }
+    }
+
+    /// Classifies the `error` value of an otherwise successful response or
+    /// stream event: an object with a message and codes, or a plain string.
+    pub fn from_error(error: &Value) -> Self {
+        let (message, codes) = error_details(error);
+        let message = message.unwrap_or_else(|| error.to_string());
+        Self::classify(message, &codes, None).unwrap_or_else(|message| ProviderError::Other {
+            status: None,
+            message,
+        })
+    }
+
+    /// Finds a `ProviderError` in the source chain of `error`.
+    pub fn find<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a ProviderError> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(provider_error) = error.downcast_ref::<ProviderError>() {
+                return Some(provider_error);
+            }
+            current = error.source();
+        }
+        None
+    }
+
+    /// Classifies by the provider's error codes, falling back to a few phrases
+    /// of the message that are only used for one kind of failure. Gives the
+    /// message back if neither says what went wrong.
+    fn classify(
+        message: String,
+        codes: &[String],
+        retry_after: Option<Duration>,
+    ) -> std::result::Result<Self, String> {
+        for code in codes {
+            let error = match code.to_lowercase().as_str() {
+                "context_length_exceeded" => ProviderError::ContextLengthExceeded(message),
+                "content_filter" | "content_policy_violation" | "responsibleaipolicyviolation" => {
+                    ProviderError::ContentFiltered(message)
+                }
+                "invalid_api_key" | "api_key_invalid" | "authentication_error"
+                | "permission_error" | "unauthenticated" | "permission_denied" => {
+                    ProviderError::Unauthorized(message)
+                }
+                "model_not_found" | "deploymentnotfound" | "not_found_error" | "not_found" => {
+                    ProviderError::ModelNotFound(message)
+                }
+                "rate_limit_exceeded" | "rate_limit_error" | "resource_exhausted"
+                | "overloaded_error" => ProviderError::RateLimited {
+                    retry_after,
+                    message,
+                },
+                _ => continue,
+            };
+            return Ok(error);
+        }
+
+        const CONTEXT_LENGTH: &[&str] = &[
+            "maximum context length",
+            "prompt is too long",
+            "exceeds the maximum number of tokens",
+        ];
+        const CONTENT_FILTER: &[&str] = &["content management policy"];
+        const UNAUTHORIZED: &[&str] = &["api key not valid", "incorrect api key", "invalid api key"];
+
+        let lower = message.to_lowercase();
+        let matches = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+        if matches(CONTEXT_LENGTH) {
+            Ok(ProviderError::ContextLengthExceeded(message))
+        } else if matches(CONTENT_FILTER) {
+            Ok(ProviderError::ContentFiltered(message))
+        } else if matches(UNAUTHORIZED) {
+            Ok(ProviderError::Unauthorized(message))
+        } else if lower.starts_with("model ") && lower.contains(" not found") {
+            // Ollama: "model 'llama9' not found, try pulling it first".
+            Ok(ProviderError::ModelNotFound(message))
+        } else {
+            Err(message)
+        }
+    }
+}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(error: reqwest::Error) -> Self {
+        ProviderError::Transport(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ProviderError {
+    fn from(error: serde_json::Error) -> Self {
+        ProviderError::MalformedResponse(error.to_string())
+    }
+}
+
+/// Pulls the human-readable message and the error codes out of an `error`
+/// value. OpenAI sets `code` and `type`, Anthropic `type`, and Gemini `status`
+/// and a `reason` per entry of `details`; Ollama's errors are plain strings.
+fn error_details(error: &Value) -> (Option<String>, Vec<String>) {
+    if let Some(message) = error.as_str() {
+        return (Some(message.to_string()), Vec::new());
+    }
+    let message = error["message"].as_str().map(String::from);
+    let details = error["details"].as_array().into_iter().flatten();
+    let codes = [&error["code"], &error["type"], &error["status"]]
+        .into_iter()
+        .chain(details.map(|detail| &detail["reason"]))
+        .filter_map(|code| code.as_str())
+        .map(String::from)
+        .collect();
+    (message, codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status_classification() {
+        assert!(matches!(
+            ProviderError::from_status(401, None, r#"{"error":{"message":"Incorrect API key"}}"#),
+            ProviderError::Unauthorized(m) if m == "Incorrect API key"
+        ));
+        assert_eq!(
+            ProviderError::from_status(429, Some(Duration::from_secs(20)), "slow down"),
+            ProviderError::RateLimited {
+                retry_after: Some(Duration::from_secs(20)),
+                message: "slow down".to_string()
+            }
+        );
+        assert!(matches!(
+            ProviderError::from_status(
+                400,
+                None,
+                r#"{"error":{"message":"This model's maximum context length is 8192 tokens","code":"context_length_exceeded"}}"#
+            ),
+            ProviderError::ContextLengthExceeded(_)
+        ));
+        assert!(matches!(
+            ProviderError::from_status(400, None, r#"{"error":{"message":"API key not valid. Please pass a valid API key."}}"#),
+            ProviderError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            ProviderError::from_status(404, None, r#"{"error":"model 'llama9' not found"}"#),
+            ProviderError::ModelNotFound(_)
+        ));
+        assert!(matches!(
+            ProviderError::from_status(529, None, r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#),
+            ProviderError::RateLimited { retry_after: None, .. }
+        ));
+        assert_eq!(
+            ProviderError::from_status(500, None, "boom"),
+            ProviderError::Other {
+                status: Some(500),
+                message: "boom".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_classification_uses_error_codes() {
+        // Words like "safety", "authentication" or "not found" alone say nothing.
+        assert_eq!(
+            ProviderError::from_status(
+                400,
+                None,
+                r#"{"error":{"code":400,"message":"Invalid value at 'safety_settings[0].category'","status":"INVALID_ARGUMENT"}}"#
+            ),
+            ProviderError::Other {
+                status: Some(400),
+                message: "Invalid value at 'safety_settings[0].category'".to_string()
+            }
+        );
+        assert!(matches!(
+            ProviderError::from_status(500, None, "Upstream authentication proxy: file not found"),
+            ProviderError::Other { .. }
+        ));
+        assert!(matches!(
+            ProviderError::from_status(
+                400,
+                None,
+                r#"{"error":{"code":400,"message":"Bad key","status":"INVALID_ARGUMENT","details":[{"reason":"API_KEY_INVALID"}]}}"#
+            ),
+            ProviderError::Unauthorized(m) if m == "Bad key"
+        ));
+        assert!(matches!(
+            ProviderError::from_status(
+                400,
+                None,
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#
+            ),
+            ProviderError::ContextLengthExceeded(_)
+        ));
+        assert!(matches!(
+            ProviderError::from_error(&serde_json::json!({"code": "content_filter", "message": "Blocked"})),
+            ProviderError::ContentFiltered(m) if m == "Blocked"
+        ));
+        assert!(matches!(
+            ProviderError::from_error(&serde_json::json!("model 'llama9' not found, try pulling it first")),
+            ProviderError::ModelNotFound(_)
+        ));
+        assert!(matches!(
+            ProviderError::from_error(&serde_json::json!({"type": "overloaded_error", "message": "Overloaded"})),
+            ProviderError::RateLimited { retry_after: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_find_through_context() {
+        let error = anyhow::Error::new(ProviderError::ContentFiltered("blocked".to_string()))
+            .context("API call failed during agent run");
+        let agent_error = AgentError::Api(error);
+        assert_eq!(
+            agent_error.provider_error(),
+            Some(&ProviderError::ContentFiltered("blocked".to_string()))
+        );
+    }
 }
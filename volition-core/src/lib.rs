@@ -18,7 +18,10 @@ use anyhow::Result;
 use std::path::Path;
 
 pub use config::{AgentConfig, ModelConfig};
-pub use models::chat::{ApiResponse, ChatMessage, Choice};
+pub use models::chat::{
+    ApiResponse, ChatMessage, Choice, ContentPart, ImageSource, Role, StreamDelta, TokenUsage,
+};
+pub use models::request::{RequestOptions, ResponseFormat, ToolChoice};
 pub use models::tools::{
     ToolCall,
     ToolDefinition,
@@ -49,6 +52,10 @@ pub trait ToolProvider: Send + Sync {
 #[async_trait]
 pub trait UserInteraction: Send + Sync {
     async fn ask(&self, prompt: String, options: Vec<String>) -> Result<String>;
+
+    /// Called for each streamed fragment of a completion as it arrives.
+    /// The default implementation ignores deltas.
+    fn on_stream_delta(&self, _delta: &StreamDelta) {}
 }
 
 // --- Structs for Strategy Interaction ---
@@ -58,6 +65,14 @@ pub struct AgentState {
     pub messages: Vec<ChatMessage>,
     // This field is specific to the old tool system
     pub pending_tool_calls: Vec<ToolCall>,
+    /// Token usage of each completion request made during the run, oldest first.
+    #[serde(default)]
+    pub usage: Vec<TokenUsage>,
+    /// Options for the next API call only. Strategies set these before returning
+    /// [`NextStep::CallApi`](strategies::NextStep::CallApi); the agent clears them
+    /// once the call is made.
+    #[serde(default)]
+    pub request_options: RequestOptions,
 }
 
 impl AgentState {
@@ -67,7 +82,7 @@ impl AgentState {
         // Only add user message if input is not empty
         if !current_user_input.is_empty() {
             messages.push(ChatMessage {
-                role: "user".to_string(),
+                role: Role::User,
                 content: Some(current_user_input),
                 ..Default::default()
             });
@@ -75,6 +90,8 @@ impl AgentState {
         Self {
             messages,
             pending_tool_calls: Vec::new(),
+            usage: Vec::new(),
+            request_options: RequestOptions::default(),
         }
     }
 
@@ -92,7 +109,7 @@ impl AgentState {
     pub fn add_tool_results(&mut self, results: Vec<ToolResult>) {
         for result in results {
             self.messages.push(ChatMessage {
-                role: "tool".to_string(),
+                role: Role::Tool,
                 content: Some(result.output),
                 tool_call_id: Some(result.tool_call_id),
                 ..Default::default()
@@ -140,5 +157,6 @@ pub enum ToolExecutionStatus {
 
 pub mod models {
     pub mod chat;
+    pub mod request;
     pub mod tools;
 }
@@ -3,18 +3,117 @@
 //! Structures related to chat messages and API responses for OpenAI-compatible endpoints.
 
 use super::tools::ToolCall;
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The author of a [`ChatMessage`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    #[default]
+    User,
+    Assistant,
+    Tool,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A piece of message content in addition to the text in [`ChatMessage::content`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// More text, sent after `content`.
+    Text { text: String },
+    /// An image, e.g. a screenshot or diagram.
+    Image { source: ImageSource },
+    /// The output of a tool call. Providers send it the same way as a
+    /// [`Role::Tool`] message with the same `tool_call_id`.
+    ToolResult { tool_call_id: String, content: String },
+}
+
+/// Where the bytes of an image part come from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+    /// Base64-encoded image data with its MIME type, e.g. `image/png`.
+    Base64 { media_type: String, data: String },
+    /// A local file, read when the request is sent.
+    Path(PathBuf),
+}
+
+impl ImageSource {
+    /// Reads a local image into a [`ImageSource::Base64`] so the message no
+    /// longer depends on the file.
+    pub fn embed(path: &Path) -> Result<Self> {
+        let (media_type, data) = Self::Path(path.to_path_buf()).to_base64()?;
+        Ok(Self::Base64 { media_type, data })
+    }
+
+    /// Returns the MIME type and base64 data, reading the file for [`ImageSource::Path`].
+    pub fn to_base64(&self) -> Result<(String, String)> {
+        match self {
+            ImageSource::Base64 { media_type, data } => Ok((media_type.clone(), data.clone())),
+            ImageSource::Path(path) => {
+                let media_type = image_media_type(path).ok_or_else(|| {
+                    anyhow!(
+                        "Unsupported image type {}. Use a PNG, JPEG, GIF or WebP file.",
+                        path.display()
+                    )
+                })?;
+                let bytes = std::fs::read(path)
+                    .with_context(|| format!("Failed to read image {}", path.display()))?;
+                let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+                Ok((media_type.to_string(), data))
+            }
+        }
+    }
+}
+
+/// Guesses an image MIME type from the file extension.
+fn image_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
 
 /// Represents a message in the chat history sequence sent to/from the AI.
 ///
 /// This struct is used for all roles (`system`, `user`, `assistant`, `tool`)
 /// within the conversation history managed by the [`Agent`](crate::Agent).
 /// Optional fields are handled by `serde` during serialization/deserialization.
+///
+/// The text of a message is `content` followed by any [`ContentPart::Text`]
+/// parts, so read it with [`ChatMessage::text`] rather than from `content`
+/// alone. `parts` is authoritative for everything else, such as images.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ChatMessage {
-    /// The role of the message sender (e.g., "system", "user", "assistant", "tool").
-    pub role: String,
-    /// The text content of the message.
+    /// The role of the message sender.
+    pub role: Role,
+    /// The leading text of the message; see [`ChatMessage::text`] for all of it.
     /// Present for `system`, `user`, and final `assistant` messages, and `tool` messages.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub content: Option<String>,
@@ -26,6 +125,153 @@ pub struct ChatMessage {
     /// Only present for `tool` messages.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub tool_call_id: Option<String>,
+    /// Content beyond the text in `content`, such as images.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub parts: Vec<ContentPart>,
+    /// The model's reasoning before its answer, kept apart from `content`.
+    /// Providers do not send it back to the model.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reasoning: Option<String>,
+}
+
+impl ChatMessage {
+    /// The message text: `content` followed by any text parts.
+    pub fn text(&self) -> String {
+        let mut text = self.content.clone().unwrap_or_default();
+        for part in &self.parts {
+            if let ContentPart::Text { text: more } = part {
+                if !text.is_empty() {
+                    text.push_str("\n\n");
+                }
+                text.push_str(more);
+            }
+        }
+        text
+    }
+
+    /// The message with its text parts folded into `content`, so code that
+    /// rewrites `content` keeps the whole text in order. Other parts are kept.
+    pub fn with_text_in_content(mut self) -> Self {
+        if self
+            .parts
+            .iter()
+            .any(|part| matches!(part, ContentPart::Text { .. }))
+        {
+            self.content = Some(self.text());
+            self.parts
+                .retain(|part| !matches!(part, ContentPart::Text { .. }));
+        }
+        self
+    }
+
+    /// The image parts of the message.
+    pub fn images(&self) -> impl Iterator<Item = &ImageSource> {
+        self.parts.iter().filter_map(|part| match part {
+            ContentPart::Image { source } => Some(source),
+            _ => None,
+        })
+    }
+}
+
+/// Tokens counted for each image, whose real cost varies by size and provider.
+const IMAGE_TOKENS: usize = 1_000;
+
+/// Rough token count of `messages` at four characters per token, plus a fixed
+/// cost per message and image. Good enough to compare against a context window.
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            let mut chars = message.text().len();
+            for call in message.tool_calls.iter().flatten() {
+                chars += call.function.name.len() + call.function.arguments.len();
+            }
+            let mut images = 0;
+            for part in &message.parts {
+                match part {
+                    ContentPart::ToolResult { content, .. } => chars += content.len(),
+                    ContentPart::Image { .. } => images += 1,
+                    ContentPart::Text { .. } => {}
+                }
+            }
+            4 + chars.div_ceil(4) + images * IMAGE_TOKENS
+        })
+        .sum()
+}
+
+/// Drops the oldest exchanges, each running from a user prompt up to the next,
+/// until the estimated size of `messages` fits `limit`. System messages and the
+/// exchange started by the latest prompt are always kept.
+pub fn compact_messages(mut messages: Vec<ChatMessage>, limit: usize) -> Vec<ChatMessage> {
+    // User messages carrying tool results continue an exchange rather than start one.
+    let is_prompt = |m: &ChatMessage| {
+        m.role == Role::User
+            && !m
+                .parts
+                .iter()
+                .any(|p| matches!(p, ContentPart::ToolResult { .. }))
+    };
+    while estimate_tokens(&messages) > limit {
+        let Some(start) = messages.iter().position(|m| m.role != Role::System) else {
+            break;
+        };
+        let Some(end) = messages[start + 1..]
+            .iter()
+            .position(is_prompt)
+            .map(|i| start + 1 + i)
+        else {
+            break;
+        };
+        messages.drain(start..end);
+    }
+    messages
+}
+
+/// Moves [`ContentPart::ToolResult`] parts out into [`Role::Tool`] messages placed
+/// before the rest of their message, so providers only have to handle one form
+/// of tool result. Messages left without content are dropped.
+pub fn split_tool_results(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    let mut split = Vec::with_capacity(messages.len());
+    for message in messages {
+        if !message
+            .parts
+            .iter()
+            .any(|p| matches!(p, ContentPart::ToolResult { .. }))
+        {
+            split.push(message.clone());
+            continue;
+        }
+        let (results, rest): (Vec<ContentPart>, Vec<ContentPart>) = message
+            .parts
+            .iter()
+            .cloned()
+            .partition(|p| matches!(p, ContentPart::ToolResult { .. }));
+        for result in results {
+            if let ContentPart::ToolResult {
+                tool_call_id,
+                content,
+            } = result
+            {
+                split.push(ChatMessage {
+                    role: Role::Tool,
+                    content: Some(content),
+                    tool_call_id: Some(tool_call_id),
+                    ..Default::default()
+                });
+            }
+        }
+        let remaining = ChatMessage {
+            parts: rest,
+            ..message.clone()
+        };
+        if remaining.content.as_deref().is_some_and(|c| !c.is_empty())
+            || !remaining.parts.is_empty()
+            || remaining.tool_calls.is_some()
+        {
+            split.push(remaining);
+        }
+    }
+    split
 }
 
 /// Represents one of the possible responses provided by the AI model.
@@ -57,3 +303,140 @@ pub struct ApiResponse {
     /// A list of completion choices. Currently, only the first choice is used by the [`Agent`](crate::Agent).
     pub choices: Vec<Choice>,
 }
+
+impl ApiResponse {
+    /// Whether the model stopped because it hit the output token limit
+    /// (`length`, or Gemini's `MAX_TOKENS`).
+    pub fn is_truncated(&self) -> bool {
+        matches!(self.finish_reason.as_str(), "length" | "MAX_TOKENS")
+    }
+}
+
+/// Token counts reported for one completion request, recorded by the [`Agent`](crate::Agent)
+/// in [`AgentState::usage`](crate::AgentState::usage).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TokenUsage {
+    /// The ID of the provider entry in the configuration that served the request.
+    pub provider_id: String,
+    /// The model name reported by the provider.
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// An incremental piece of a streamed completion, forwarded to the UI as it arrives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDelta {
+    /// A fragment of the assistant's text content.
+    Text(String),
+    /// A fragment of the assistant's reasoning, streamed before its answer.
+    Reasoning(String),
+    /// A fragment of a tool call. `index` identifies the call within the response;
+    /// `id` and `name` are usually only present on the first fragment of each call.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_reads_image_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("screenshot.PNG");
+        std::fs::write(&path, [0x89, b'P', b'N', b'G']).unwrap();
+
+        let source = ImageSource::embed(&path).unwrap();
+        assert_eq!(
+            source,
+            ImageSource::Base64 {
+                media_type: "image/png".to_string(),
+                data: "iVBORw==".to_string(),
+            }
+        );
+
+        let text_file = dir.path().join("notes.txt");
+        std::fs::write(&text_file, "not an image").unwrap();
+        let error = ImageSource::embed(&text_file).unwrap_err();
+        assert!(error.to_string().contains("Unsupported image type"));
+    }
+
+    #[test]
+    fn test_text_in_content_keeps_text_order_and_images() {
+        let image = ContentPart::Image {
+            source: ImageSource::Path(PathBuf::from("diagram.png")),
+        };
+        let message = ChatMessage {
+            role: Role::User,
+            content: Some("Look at this.".to_string()),
+            parts: vec![
+                image.clone(),
+                ContentPart::Text {
+                    text: "What is wrong?".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let merged = message.with_text_in_content();
+        assert_eq!(
+            merged.content.as_deref(),
+            Some("Look at this.\n\nWhat is wrong?")
+        );
+        assert_eq!(merged.parts, vec![image]);
+    }
+
+    #[test]
+    fn test_split_tool_results_places_results_first() {
+        let message = ChatMessage {
+            role: Role::User,
+            content: Some("Continue.".to_string()),
+            parts: vec![ContentPart::ToolResult {
+                tool_call_id: "call_1".to_string(),
+                content: "done".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        let split = split_tool_results(&[message]);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].role, Role::Tool);
+        assert_eq!(split[0].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(split[0].content.as_deref(), Some("done"));
+        assert_eq!(split[1].text(), "Continue.");
+        assert!(split[1].parts.is_empty());
+    }
+
+    #[test]
+    fn test_compact_messages_drops_oldest_exchanges() {
+        let message = |role: Role, content: &str| ChatMessage {
+            role,
+            content: Some(content.to_string()),
+            ..Default::default()
+        };
+        let long = "x".repeat(400);
+        let messages = vec![
+            message(Role::System, "You are helpful."),
+            message(Role::User, &long),
+            message(Role::Assistant, &long),
+            message(Role::User, "Second question"),
+            message(Role::Assistant, &long),
+            message(Role::User, "Latest question"),
+        ];
+        assert!(estimate_tokens(&messages) > 300);
+
+        let compacted = compact_messages(messages.clone(), 100);
+        let contents: Vec<_> = compacted.iter().filter_map(|m| m.content.as_deref()).collect();
+        assert_eq!(contents, ["You are helpful.", "Latest question"]);
+
+        // The latest exchange is kept even if it does not fit.
+        assert_eq!(compact_messages(messages.clone(), 0).len(), 2);
+        assert_eq!(compact_messages(messages, 10_000).len(), 6);
+    }
+}
@@ -0,0 +1,96 @@
+// volition-agent-core/src/models/request.rs
+
+//! Per-request options that strategies pass to providers alongside the messages.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Options for a single completion request.
+///
+/// Providers map each option to their own API and fail with
+/// [`ProviderError::Unsupported`](crate::errors::ProviderError::Unsupported)
+/// when they cannot honor it, rather than silently ignoring it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+    /// Requires the response text to be JSON matching a schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Controls whether and which tool the model must call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// The strategy phase making the request, e.g. `"planning"`. Only used to
+    /// pick a provider by a `router`; never sent to the model.
+    #[serde(skip)]
+    pub phase: Option<String>,
+    /// Tool calls that failed in a row just before this request. Only used to
+    /// pick a provider by a `router`; never sent to the model.
+    #[serde(skip)]
+    pub tool_failures: u32,
+}
+
+impl RequestOptions {
+    /// Whether no option is set, i.e. the provider's defaults apply. Routing
+    /// hints do not count.
+    pub fn is_empty(&self) -> bool {
+        self.response_format.is_none() && self.tool_choice.is_none()
+    }
+
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
+    pub fn with_phase(mut self, phase: impl Into<String>) -> Self {
+        self.phase = Some(phase.into());
+        self
+    }
+}
+
+/// A JSON schema the response text must conform to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResponseFormat {
+    /// A short identifier for the schema, required by some APIs (e.g. `"plan"`).
+    pub name: String,
+    /// The JSON schema object.
+    pub schema: Value,
+}
+
+impl ResponseFormat {
+    pub fn json_schema(name: impl Into<String>, schema: Value) -> Self {
+        Self {
+            name: name.into(),
+            schema,
+        }
+    }
+}
+
+/// Which tool, if any, the model must call.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoice {
+    /// The model decides. This is the default when tools are offered.
+    Auto,
+    /// The model must not call any tool.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named tool.
+    Tool(String),
+}
+
+impl fmt::Display for ToolChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolChoice::Auto => write!(f, "auto"),
+            ToolChoice::None => write!(f, "none"),
+            ToolChoice::Required => write!(f, "required"),
+            ToolChoice::Tool(name) => write!(f, "tool '{}'", name),
+        }
+    }
+}
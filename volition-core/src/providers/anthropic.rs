@@ -0,0 +1,816 @@
+// volition-agent-core/src/providers/anthropic.rs
+use super::{Provider, check_response, check_tool_choice, merge_extra_params};
+use super::capabilities::ModelCapabilities;
+use super::reasoning::split_reasoning;
+use super::cassette::{Wire, normalize_request};
+use super::streaming::{DeltaSink, StreamAccumulator, sse_data};
+use crate::config::ModelConfig;
+use crate::errors::ProviderError;
+use crate::models::chat::{
+    ApiResponse, ChatMessage, Choice, Role, StreamDelta, split_tool_results,
+};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::{Result, Context};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+const DEFAULT_ANTHROPIC_ENDPOINT: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`; used when the config does not set one.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Clone)]
+pub struct AnthropicProvider {
+    config: ModelConfig,
+    http_client: Client,
+    api_key: String,
+    wire: Wire,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: ModelConfig, http_client: Client, api_key: String) -> Self {
+        Self {
+            wire: Wire::from_config(&config),
+            config,
+            http_client,
+            api_key,
+        }
+    }
+
+    fn build_payload(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<Value> {
+        debug!("Building Anthropic payload...");
+        debug!("Model name: {}", self.config.model_name);
+        debug!("Message count: {}", messages.len());
+
+        let (system, converted) = convert_messages(&messages)?;
+        let params = &self.config.parameters;
+        let max_tokens = params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+
+        let mut payload = json!({
+            "model": self.config.model_name,
+            "max_tokens": max_tokens,
+            "messages": converted,
+        });
+        if let Some(system) = system {
+            payload["system"] = json!(system);
+        }
+
+        // Add tools if present
+        if let Some(tools) = tools
+            && !tools.is_empty()
+        {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|t| {
+                    json!({
+                        "name": t.name,
+                        "description": t.description,
+                        "input_schema": t.parameters
+                    })
+                })
+                .collect();
+            payload["tools"] = json!(tools);
+        }
+
+        // Add request options
+        check_tool_choice(options, tools)?;
+        if let Some(choice) = &options.tool_choice
+            && payload.get("tools").is_some()
+        {
+            payload["tool_choice"] = match choice {
+                ToolChoice::Auto => json!({ "type": "auto" }),
+                ToolChoice::None => json!({ "type": "none" }),
+                ToolChoice::Required => json!({ "type": "any" }),
+                ToolChoice::Tool(name) => json!({ "type": "tool", "name": name }),
+            };
+        }
+        if options.response_format.is_some() {
+            return Err(ProviderError::Unsupported(format!(
+                "Anthropic model {} does not support a JSON schema response format",
+                self.config.model_name
+            ))
+            .into());
+        }
+
+        // Add generation parameters
+        if let Some(temperature) = params.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            payload["top_p"] = json!(top_p);
+        }
+        if !params.stop.is_empty() {
+            payload["stop_sequences"] = json!(params.stop);
+        }
+        if params.seed.is_some() {
+            debug!("Anthropic does not support 'seed'; ignoring it");
+        }
+        merge_extra_params(&mut payload, &params.extra);
+
+        debug!("Final payload: {}", serde_json::to_string_pretty(&payload)?);
+        Ok(payload)
+    }
+
+    fn parse_response(&self, response_body: &str) -> Result<ApiResponse> {
+        debug!("Parsing Anthropic response...");
+        debug!("Response body: {}", response_body);
+
+        let raw_response: Value =
+            serde_json::from_str(response_body).map_err(ProviderError::from)?;
+        if raw_response["error"].is_object() {
+            return Err(ProviderError::from_error(&raw_response["error"]))
+                .context("Anthropic returned an error");
+        }
+
+        let blocks = raw_response["content"]
+            .as_array()
+            .ok_or_else(|| {
+                ProviderError::MalformedResponse("Missing content in Anthropic response".to_string())
+            })?;
+        let mut content = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("text") => content.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("thinking") => thinking.push_str(block["thinking"].as_str().unwrap_or_default()),
+                Some("tool_use") => tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    call_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: block["input"].to_string(),
+                    },
+                }),
+                _ => {}
+            }
+        }
+        let (reasoning, content) = split_reasoning(Some(&thinking), &content, false);
+        debug!("Extracted content: {}", content);
+        debug!("Extracted {} tool call(s)", tool_calls.len());
+
+        let finish_reason = map_stop_reason(raw_response["stop_reason"].as_str());
+        debug!("Finish reason: {}", finish_reason);
+        if finish_reason == "refusal" && content.is_empty() && tool_calls.is_empty() {
+            return Err(ProviderError::ContentFiltered(
+                "Anthropic refused to respond".to_string(),
+            )
+            .into());
+        }
+
+        let usage = &raw_response["usage"];
+        let prompt_tokens = usage["input_tokens"].as_u64().unwrap_or(0) as u32;
+        let completion_tokens = usage["output_tokens"].as_u64().unwrap_or(0) as u32;
+        debug!("Token usage - prompt: {}, completion: {}", prompt_tokens, completion_tokens);
+
+        let result = ApiResponse {
+            id: raw_response["id"]
+                .as_str()
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            content: content.clone(),
+            finish_reason: finish_reason.clone(),
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(content),
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                    reasoning,
+                    ..Default::default()
+                },
+                finish_reason,
+            }],
+        };
+
+        debug!("Parsed response: {:?}", result);
+        Ok(result)
+    }
+
+    /// Applies one SSE `data:` payload. Tool calls are tracked by content block
+    /// index, since text and `tool_use` blocks share the same numbering.
+    fn parse_stream_event(
+        &self,
+        data: &str,
+        acc: &mut StreamAccumulator,
+        tool_blocks: &mut HashMap<u64, usize>,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<()> {
+        let event: Value = serde_json::from_str(data)
+            .map_err(ProviderError::from)
+            .with_context(|| format!("Invalid JSON in Anthropic stream event: {}", data))?;
+
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let message = &event["message"];
+                acc.id = message["id"].as_str().unwrap_or_default().to_string();
+                acc.prompt_tokens = message["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32;
+            }
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                if block["type"].as_str() == Some("tool_use") {
+                    let index = acc.tool_call_count();
+                    tool_blocks.insert(event["index"].as_u64().unwrap_or(0), index);
+                    acc.push(
+                        StreamDelta::ToolCall {
+                            index,
+                            id: block["id"].as_str().map(String::from),
+                            name: block["name"].as_str().map(String::from),
+                            arguments: String::new(),
+                        },
+                        on_delta,
+                    );
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => acc.push(
+                        StreamDelta::Text(delta["text"].as_str().unwrap_or_default().to_string()),
+                        on_delta,
+                    ),
+                    Some("thinking_delta") => acc.push(
+                        StreamDelta::Reasoning(
+                            delta["thinking"].as_str().unwrap_or_default().to_string(),
+                        ),
+                        on_delta,
+                    ),
+                    Some("input_json_delta") => {
+                        let block = event["index"].as_u64().unwrap_or(0);
+                        if let Some(index) = tool_blocks.get(&block) {
+                            acc.push(
+                                StreamDelta::ToolCall {
+                                    index: *index,
+                                    id: None,
+                                    name: None,
+                                    arguments: delta["partial_json"]
+                                        .as_str()
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                },
+                                on_delta,
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some("message_delta") => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    acc.finish_reason = Some(map_stop_reason(Some(reason)));
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    acc.completion_tokens = output_tokens as u32;
+                }
+            }
+            Some("error") => {
+                return Err(ProviderError::from_error(&event["error"]))
+                    .context("Anthropic returned an error");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn send_request(&self, payload: &Value) -> Result<reqwest::Response> {
+        if self.api_key.is_empty() {
+            warn!(
+                "API key is empty for Anthropic provider model {}. The API call will likely fail.",
+                self.config.model_name
+            );
+        }
+        let endpoint = self
+            .config
+            .endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_ANTHROPIC_ENDPOINT);
+        debug!("Using Anthropic endpoint: {}", endpoint);
+
+        let response = self
+            .http_client
+            .post(endpoint)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(payload)
+            .send()
+            .await
+            .map_err(ProviderError::from)
+            .context("Failed to send request to Anthropic API")?;
+        Ok(check_response(response).await?)
+    }
+
+    async fn call_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let payload = self.build_payload(messages, tools, options)?;
+        let response_body = self
+            .wire
+            .body(request, &payload, self.send_request(&payload))
+            .await
+            .context("Failed to read response from Anthropic API")?;
+
+        self.parse_response(&response_body)
+    }
+
+    async fn stream_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let mut payload = self.build_payload(messages, tools, options)?;
+        payload["stream"] = json!(true);
+
+        let mut acc = StreamAccumulator::default();
+        let mut tool_blocks = HashMap::new();
+        self.wire
+            .lines(
+                request,
+                &payload,
+                self.send_request(&payload),
+                |line| match sse_data(line) {
+                    Some(data) => {
+                        self.parse_stream_event(data, &mut acc, &mut tool_blocks, on_delta)
+                    }
+                    None => Ok(()),
+                },
+            )
+            .await
+            .context("Failed to read streamed response from Anthropic API")?;
+        Ok(acc.finish(on_delta))
+    }
+}
+
+/// Splits chat history into the top-level `system` prompt and Messages API turns.
+///
+/// Assistant tool calls become `tool_use` blocks and tool results become
+/// `tool_result` blocks in a user turn. Images become base64 `image` blocks,
+/// which the API only accepts from the user. Consecutive messages with the same
+/// role are merged because the API requires user and assistant turns to alternate.
+fn convert_messages(messages: &[ChatMessage]) -> Result<(Option<String>, Vec<Value>)> {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for msg in &split_tool_results(messages) {
+        let text = msg.text();
+        if msg.images().next().is_some() && msg.role != Role::User {
+            return Err(ProviderError::Unsupported(format!(
+                "Anthropic only accepts images in user messages, not {} messages",
+                msg.role
+            ))
+            .into());
+        }
+        let (role, blocks) = match msg.role {
+            Role::System => {
+                system.push(text);
+                continue;
+            }
+            Role::Assistant => {
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                for call in msg.tool_calls.iter().flatten() {
+                    let input: Value =
+                        serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": input
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": msg.tool_call_id.as_deref().unwrap_or_default(),
+                    "content": text
+                })],
+            ),
+            Role::User => {
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                for image in msg.images() {
+                    let (media_type, data) = image.to_base64()?;
+                    blocks.push(json!({
+                        "type": "image",
+                        "source": { "type": "base64", "media_type": media_type, "data": data }
+                    }));
+                }
+                ("user", blocks)
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let system = if system.is_empty() {
+        None
+    } else {
+        Some(system.join("\n\n"))
+    };
+    let turns = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    Ok((system, turns))
+}
+
+/// Maps Anthropic's `stop_reason` onto the finish reasons used by the other providers.
+fn map_stop_reason(stop_reason: Option<&str>) -> String {
+    match stop_reason {
+        Some("tool_use") => "tool_calls",
+        Some("max_tokens") => "length",
+        Some("end_turn") | Some("stop_sequence") | None => "stop",
+        Some(other) => other,
+    }
+    .to_string()
+}
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    fn name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::resolve(&self.config.model_name, &self.config.capabilities)
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.call_chat_completion_api(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.stream_chat_completion_api(messages, tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ContentPart, ImageSource};
+    use crate::models::request::ResponseFormat;
+    use httpmock::prelude::*;
+    use std::sync::Mutex;
+
+    fn provider(endpoint: String) -> AnthropicProvider {
+        AnthropicProvider::new(
+            ModelConfig {
+                model_name: "claude-test".to_string(),
+                endpoint: Some(endpoint),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        )
+    }
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_convert_messages_merges_roles_and_tool_results() {
+        let messages = vec![
+            message(Role::System, "Be brief."),
+            message(Role::User, "Read a.rs and b.rs"),
+            ChatMessage {
+                role: Role::Assistant,
+                content: Some("Reading.".to_string()),
+                tool_calls: Some(vec![
+                    ToolCall {
+                        id: "toolu_1".to_string(),
+                        call_type: "function".to_string(),
+                        function: ToolFunction {
+                            name: "read_file".to_string(),
+                            arguments: "{\"path\":\"a.rs\"}".to_string(),
+                        },
+                    },
+                    ToolCall {
+                        id: "toolu_2".to_string(),
+                        call_type: "function".to_string(),
+                        function: ToolFunction {
+                            name: "read_file".to_string(),
+                            arguments: "{\"path\":\"b.rs\"}".to_string(),
+                        },
+                    },
+                ]),
+                tool_call_id: None,
+                ..Default::default()
+            },
+            ChatMessage {
+                tool_call_id: Some("toolu_1".to_string()),
+                ..message(Role::Tool, "A")
+            },
+            ChatMessage {
+                tool_call_id: Some("toolu_2".to_string()),
+                ..message(Role::Tool, "B")
+            },
+            message(Role::User, "Now summarize."),
+        ];
+
+        let (system, turns) = convert_messages(&messages).unwrap();
+
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[1]["role"], "assistant");
+        assert_eq!(
+            turns[1]["content"][1],
+            json!({ "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": { "path": "a.rs" } })
+        );
+        assert_eq!(
+            turns[2],
+            json!({
+                "role": "user",
+                "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": "A" },
+                    { "type": "tool_result", "tool_use_id": "toolu_2", "content": "B" },
+                    { "type": "text", "text": "Now summarize." }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_response_separates_thinking_blocks() {
+        let body = json!({
+            "id": "msg_1",
+            "content": [
+                { "type": "thinking", "thinking": "They want a short answer.", "signature": "sig" },
+                { "type": "text", "text": "Yes." }
+            ],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 5, "output_tokens": 7 }
+        });
+        let response = provider(String::new())
+            .parse_response(&body.to_string())
+            .unwrap();
+        assert_eq!(response.content, "Yes.");
+        assert_eq!(
+            response.choices[0].message.reasoning.as_deref(),
+            Some("They want a short answer.")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_leaves_think_tags_in_the_answer() {
+        let body = json!({
+            "id": "msg_1",
+            "content": [{ "type": "text", "text": "<think>Not reasoning.</think>Yes." }],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 5, "output_tokens": 7 }
+        });
+        let response = provider(String::new())
+            .parse_response(&body.to_string())
+            .unwrap();
+        assert_eq!(response.content, "<think>Not reasoning.</think>Yes.");
+        assert!(response.choices[0].message.reasoning.is_none());
+    }
+
+    #[test]
+    fn test_convert_messages_sends_image_and_tool_result_parts() {
+        let messages = vec![ChatMessage {
+            parts: vec![
+                ContentPart::ToolResult {
+                    tool_call_id: "toolu_1".to_string(),
+                    content: "saved".to_string(),
+                },
+                ContentPart::Image {
+                    source: ImageSource::Base64 {
+                        media_type: "image/jpeg".to_string(),
+                        data: "/9j/4AAQ".to_string(),
+                    },
+                },
+            ],
+            ..message(Role::User, "Here is the screenshot.")
+        }];
+
+        let (_, turns) = convert_messages(&messages).unwrap();
+
+        assert_eq!(
+            turns,
+            vec![json!({
+                "role": "user",
+                "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": "saved" },
+                    { "type": "text", "text": "Here is the screenshot." },
+                    {
+                        "type": "image",
+                        "source": { "type": "base64", "media_type": "image/jpeg", "data": "/9j/4AAQ" }
+                    }
+                ]
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_completion_parses_tool_use_and_usage() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/messages")
+                    .header("x-api-key", "test-key")
+                    .header("anthropic-version", ANTHROPIC_VERSION)
+                    .json_body_partial(r#"{"system": "Be brief.", "max_tokens": 4096}"#);
+                then.status(200).json_body(json!({
+                    "id": "msg_1",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [
+                        { "type": "text", "text": "Let me look." },
+                        { "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": { "path": "a.rs" } }
+                    ],
+                    "stop_reason": "tool_use",
+                    "usage": { "input_tokens": 12, "output_tokens": 8 }
+                }));
+            })
+            .await;
+
+        let response = provider(server.url("/v1/messages"))
+            .get_completion(
+                vec![message(Role::System, "Be brief."), message(Role::User, "Read a.rs")],
+                None,
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.content, "Let me look.");
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.total_tokens, 20);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "toolu_1");
+        assert_eq!(calls[0].function.arguments, "{\"path\":\"a.rs\"}");
+    }
+
+    #[tokio::test]
+    async fn test_stream_completion_assembles_events() {
+        let server = MockServer::start_async().await;
+        let body = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"id":"msg_2","usage":{"input_tokens":5,"output_tokens":1}}}"#,
+            "event: content_block_start",
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            "event: content_block_start",
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_9","name":"read_file","input":{}}}"#,
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"path\":"}}"#,
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"a.rs\"}"}}"#,
+            "event: message_delta",
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+            "event: message_stop",
+            r#"data: {"type":"message_stop"}"#,
+        ]
+        .join("\n");
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/messages")
+                    .json_body_partial(r#"{"stream": true}"#);
+                then.status(200)
+                    .header("content-type", "text/event-stream")
+                    .body(body);
+            })
+            .await;
+
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = provider(server.url("/v1/messages"))
+            .stream_completion(vec![message(Role::User, "hi")], None, &RequestOptions::default(), &sink)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(deltas.lock().unwrap().len(), 4);
+        assert_eq!(response.id, "msg_2");
+        assert_eq!(response.content, "Hi");
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.total_tokens, 14);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "toolu_9");
+        assert_eq!(calls[0].function.arguments, "{\"path\":\"a.rs\"}");
+    }
+
+    #[test]
+    fn test_map_stop_reason() {
+        assert_eq!(map_stop_reason(Some("end_turn")), "stop");
+        assert_eq!(map_stop_reason(Some("tool_use")), "tool_calls");
+        assert_eq!(map_stop_reason(Some("max_tokens")), "length");
+        assert_eq!(map_stop_reason(None), "stop");
+    }
+
+    #[test]
+    fn test_payload_maps_generation_params() {
+        let provider = AnthropicProvider::new(
+            ModelConfig {
+                model_name: "claude-test".to_string(),
+                parameters: toml::from_str(
+                    r#"
+                    temperature = 0.3
+                    max_tokens = 1024
+                    stop = ["</plan>"]
+                    top_k = 20
+                    "#,
+                )
+                .unwrap(),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        );
+        let payload = provider.build_payload(Vec::new(), None, &RequestOptions::default()).unwrap();
+
+        assert_eq!(payload["temperature"], json!(0.3));
+        assert_eq!(payload["max_tokens"], json!(1024));
+        assert_eq!(payload["stop_sequences"], json!(["</plan>"]));
+        assert_eq!(payload["top_k"], json!(20));
+    }
+
+    #[test]
+    fn test_payload_maps_request_options() {
+        let tools = vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: crate::models::tools::ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: Default::default(),
+                required: vec![],
+            },
+        }];
+        let payload = provider(String::new())
+            .build_payload(
+                Vec::new(),
+                Some(&tools),
+                &RequestOptions::default().with_tool_choice(ToolChoice::Required),
+            )
+            .unwrap();
+        assert_eq!(payload["tool_choice"], json!({ "type": "any" }));
+
+        let structured = RequestOptions::default()
+            .with_response_format(ResponseFormat::json_schema("plan", json!({ "type": "object" })));
+        let error = provider(String::new())
+            .build_payload(Vec::new(), None, &structured)
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::Unsupported(_))
+        ));
+    }
+}
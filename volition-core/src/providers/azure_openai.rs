@@ -0,0 +1,192 @@
+// volition-agent-core/src/providers/azure_openai.rs
+
+//! Provider for Azure OpenAI deployments. Requests use the OpenAI message and
+//! tool-call format, but go to a deployment URL with an `api-version` query
+//! parameter and authenticate with an `api-key` header.
+
+use super::Provider;
+use super::capabilities::ModelCapabilities;
+use super::openai::{Dialect, OpenAIProvider};
+use super::streaming::DeltaSink;
+use crate::config::ModelConfig;
+use crate::models::chat::{ApiResponse, ChatMessage};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::{debug, warn};
+
+const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+#[derive(Clone)]
+pub struct AzureOpenAIProvider {
+    inner: OpenAIProvider,
+}
+
+impl AzureOpenAIProvider {
+    pub fn new(config: ModelConfig, http_client: Client, api_key: String) -> Result<Self> {
+        if api_key.is_empty() {
+            warn!(
+                "API key is empty for Azure OpenAI provider model {}. API calls will likely fail.",
+                config.model_name
+            );
+        }
+        let endpoint = build_endpoint(&config)?;
+        debug!("Using Azure OpenAI deployment URL: {}", endpoint);
+
+        let dialect = Dialect {
+            label: "Azure OpenAI",
+            endpoint,
+            auth_header: Some(("api-key".to_string(), api_key)),
+            headers: Vec::new(),
+            supports_tools: true,
+            supports_stream_usage: true,
+            usage_field: "usage".to_string(),
+            think_tags: false,
+        };
+        Ok(Self {
+            inner: OpenAIProvider::with_dialect(config, http_client, dialect),
+        })
+    }
+}
+
+/// Builds `{endpoint}/openai/deployments/{deployment}/chat/completions?api-version=...`
+/// from the resource endpoint, e.g. `https://my-resource.openai.azure.com`.
+fn build_endpoint(config: &ModelConfig) -> Result<String> {
+    let resource = config.endpoint.as_deref().ok_or_else(|| {
+        anyhow!(
+            "The azure_openai provider for model {} requires an endpoint.",
+            config.model_name
+        )
+    })?;
+    let deployment = config.deployment.as_deref().unwrap_or(&config.model_name);
+    let api_version = config
+        .api_version
+        .as_deref()
+        .unwrap_or(DEFAULT_AZURE_API_VERSION);
+
+    let mut url = url::Url::parse(resource)?;
+    url.set_path(&format!(
+        "{}/openai/deployments/{}/chat/completions",
+        url.path().trim_end_matches('/'),
+        deployment
+    ));
+    url.query_pairs_mut().append_pair("api-version", api_version);
+    Ok(url.to_string())
+}
+
+#[async_trait]
+impl Provider for AzureOpenAIProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.inner.get_completion(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.inner.stream_completion(messages, tools, options, on_delta).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::Role;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    fn config(endpoint: String) -> ModelConfig {
+        ModelConfig {
+            model_name: "gpt-4o".to_string(),
+            endpoint: Some(endpoint),
+            deployment: Some("team-gpt4o".to_string()),
+            api_version: Some("2024-06-01".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_endpoint_defaults() {
+        let config = ModelConfig {
+            model_name: "gpt-4o".to_string(),
+            endpoint: Some("https://res.openai.azure.com/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_endpoint(&config).unwrap(),
+            format!(
+                "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version={}",
+                DEFAULT_AZURE_API_VERSION
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_uses_deployment_url_and_api_key_header() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/openai/deployments/team-gpt4o/chat/completions")
+                    .query_param("api-version", "2024-06-01")
+                    .header("api-key", "azure-key");
+                then.status(200).json_body(json!({
+                    "id": "chatcmpl-az",
+                    "choices": [{
+                        "index": 0,
+                        "message": {
+                            "role": "assistant",
+                            "content": null,
+                            "tool_calls": [{
+                                "id": "call_1",
+                                "type": "function",
+                                "function": { "name": "list_dir", "arguments": "{}" }
+                            }]
+                        },
+                        "finish_reason": "tool_calls"
+                    }],
+                    "usage": { "prompt_tokens": 4, "completion_tokens": 6, "total_tokens": 10 }
+                }));
+            })
+            .await;
+
+        let provider =
+            AzureOpenAIProvider::new(config(server.base_url()), Client::new(), "azure-key".to_string())
+                .unwrap();
+        let response = provider
+            .get_completion(
+                vec![ChatMessage {
+                    role: Role::User,
+                    content: Some("list".to_string()),
+                    ..Default::default()
+                }],
+                None,
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.total_tokens, 10);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "call_1");
+    }
+}
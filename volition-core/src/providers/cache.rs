@@ -0,0 +1,484 @@
+// volition-agent-core/src/providers/cache.rs
+
+//! Disk cache of provider responses, for iterating on prompts and strategies
+//! without paying for identical requests again.
+//!
+//! [`ResponseCacheProvider`] wraps a provider configured with a `cache` table.
+//! Requests are keyed by a SHA-256 hash of the provider's identity (every
+//! `model_config` setting that changes the request), the normalized messages
+//! (see [`normalize_request`]) with local images read into their bytes, the
+//! full tool definitions and the request options.
+//! Each response is stored as a JSON file in the user cache directory, e.g.
+//! `~/.cache/volition/responses` on Linux, and expires after `ttl_secs`.
+
+use super::capabilities::ModelCapabilities;
+use super::cassette::normalize_request;
+use super::streaming::DeltaSink;
+use super::{ModelInfo, Provider};
+use crate::config::CacheConfig;
+use crate::models::chat::{ApiResponse, ChatMessage, ContentPart, ImageSource};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// A cached response and when it was stored.
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    /// Seconds since the Unix epoch.
+    stored_at: u64,
+    response: ApiResponse,
+}
+
+/// The directory responses are cached in.
+pub fn default_cache_dir() -> Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|dir| dir.join("volition").join("responses"))
+        .ok_or_else(|| anyhow!("Could not determine the user cache directory"))
+}
+
+/// Deletes every cached response in `dir`, returning how many were removed.
+pub fn clear_cache(dir: &Path) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read cache directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed += 1;
+        }
+    }
+    debug!(dir = %dir.display(), removed, "Cleared response cache");
+    Ok(removed)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Replaces local image paths with the images they hold, so an image edited
+/// in place is not answered from the cache. Unreadable images are kept as
+/// paths; the request fails on them anyway.
+fn embed_images(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    messages
+        .iter()
+        .map(|message| ChatMessage {
+            parts: message
+                .parts
+                .iter()
+                .map(|part| match part {
+                    ContentPart::Image {
+                        source: ImageSource::Path(path),
+                    } => ContentPart::Image {
+                        source: ImageSource::embed(path)
+                            .unwrap_or_else(|_| ImageSource::Path(path.clone())),
+                    },
+                    other => other.clone(),
+                })
+                .collect(),
+            ..message.clone()
+        })
+        .collect()
+}
+
+/// Serves repeated requests from the disk cache and stores new responses.
+pub struct ResponseCacheProvider {
+    inner: Box<dyn Provider>,
+    dir: PathBuf,
+    ttl: Duration,
+    bypass: bool,
+    /// Everything about the provider that changes its responses.
+    scope: Value,
+}
+
+impl ResponseCacheProvider {
+    /// Wraps `inner`, caching in [`default_cache_dir`]. `scope` identifies the
+    /// provider setup; requests only hit entries stored with an equal scope.
+    pub fn new(inner: Box<dyn Provider>, config: &CacheConfig, scope: Value) -> Result<Self> {
+        Ok(Self::with_dir(inner, config, scope, default_cache_dir()?))
+    }
+
+    pub fn with_dir(
+        inner: Box<dyn Provider>,
+        config: &CacheConfig,
+        scope: Value,
+        dir: PathBuf,
+    ) -> Self {
+        debug!(
+            provider = inner.name(),
+            dir = %dir.display(),
+            ttl_secs = config.ttl_secs,
+            bypass = config.bypass,
+            "Caching provider responses"
+        );
+        Self {
+            inner,
+            dir,
+            ttl: Duration::from_secs(config.ttl_secs),
+            bypass: config.bypass,
+            scope,
+        }
+    }
+
+    fn key(
+        &self,
+        messages: &[ChatMessage],
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> String {
+        let request = json!({
+            "scope": self.scope,
+            "request": normalize_request(&embed_images(messages), tools, options),
+            "tools": tools,
+        });
+        let digest = Sha256::digest(request.to_string().as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Returns the cached response for `key` unless it is missing, expired or
+    /// bypassed. Unreadable entries count as missing.
+    fn lookup(&self, key: &str) -> Option<ApiResponse> {
+        if self.bypass {
+            return None;
+        }
+        let path = self.path(key);
+        let entry: CacheEntry = serde_json::from_str(&std::fs::read_to_string(&path).ok()?)
+            .inspect_err(
+                |e| warn!(path = %path.display(), error = %e, "Ignoring unreadable cache entry"),
+            )
+            .ok()?;
+        if now_secs().saturating_sub(entry.stored_at) > self.ttl.as_secs() {
+            debug!(key, "Cached response expired");
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        debug!(key, "Serving response from cache");
+        Some(entry.response)
+    }
+
+    /// Stores `response` under `key`. Failures are logged, not returned, so a
+    /// read-only cache directory does not break requests.
+    fn store(&self, key: &str, response: &ApiResponse) {
+        let entry = CacheEntry {
+            stored_at: now_secs(),
+            response: response.clone(),
+        };
+        let result = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(serde_json::to_string(&entry)?))
+            .and_then(|content| Ok(std::fs::write(self.path(key), content)?));
+        match result {
+            Ok(()) => debug!(key, "Cached response"),
+            Err(e) => warn!(dir = %self.dir.display(), error = %e, "Failed to cache response"),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for ResponseCacheProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let key = self.key(&messages, tools, options);
+        if let Some(response) = self.lookup(&key) {
+            return Ok(response);
+        }
+        let response = self.inner.get_completion(messages, tools, options).await?;
+        self.store(&key, &response);
+        Ok(response)
+    }
+
+    /// A cache hit returns the whole response without emitting deltas, as
+    /// providers without streaming do.
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let key = self.key(&messages, tools, options);
+        if let Some(response) = self.lookup(&key) {
+            return Ok(response);
+        }
+        let response = self
+            .inner
+            .stream_completion(messages, tools, options, on_delta)
+            .await?;
+        self.store(&key, &response);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{Choice, Role};
+    use crate::providers::reasoning::KeepReasoningProvider;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn get_completion(
+            &self,
+            messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let content = format!("reply {} to {}", call, messages[0].text());
+            Ok(ApiResponse {
+                id: format!("resp-{}", call),
+                content: content.clone(),
+                finish_reason: "stop".to_string(),
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                choices: vec![Choice {
+                    index: 0,
+                    message: ChatMessage {
+                        role: Role::Assistant,
+                        content: Some(content),
+                        ..Default::default()
+                    },
+                    finish_reason: "stop".to_string(),
+                }],
+            })
+        }
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }]
+    }
+
+    fn cached(dir: &Path, inner: &CountingProvider, config: CacheConfig) -> ResponseCacheProvider {
+        ResponseCacheProvider::with_dir(
+            Box::new(inner.clone()),
+            &config,
+            json!({ "model": "counting" }),
+            dir.to_path_buf(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_are_served_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let provider = cached(dir.path(), &inner, CacheConfig::default());
+        let options = RequestOptions::default();
+
+        let first = provider
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+        // Whitespace differences are normalized away.
+        let deltas = Mutex::new(0);
+        let second = provider
+            .stream_completion(user(" hi\n"), None, &options, &|_| {
+                *deltas.lock().unwrap() += 1
+            })
+            .await
+            .unwrap();
+        assert_eq!(first.content, "reply 1 to hi");
+        assert_eq!(second.content, first.content);
+        assert_eq!(*deltas.lock().unwrap(), 0);
+
+        let other = provider
+            .get_completion(user("bye"), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(other.content, "reply 2 to bye");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+
+        // A bypassing provider asks again and refreshes the entry.
+        let bypassing = cached(
+            dir.path(),
+            &inner,
+            CacheConfig {
+                bypass: true,
+                ..Default::default()
+            },
+        );
+        let refreshed = bypassing
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.content, "reply 3 to hi");
+        let again = provider
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(again.content, "reply 3 to hi");
+
+        assert_eq!(clear_cache(dir.path()).unwrap(), 2);
+        provider
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refetched() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let provider = cached(
+            dir.path(),
+            &inner,
+            CacheConfig {
+                ttl_secs: 0,
+                ..Default::default()
+            },
+        );
+        let options = RequestOptions::default();
+        provider
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+
+        // Backdate the entry past the TTL.
+        let key = provider.key(&user("hi"), None, &options);
+        let mut entry: CacheEntry =
+            serde_json::from_str(&std::fs::read_to_string(provider.path(&key)).unwrap()).unwrap();
+        entry.stored_at -= 10;
+        std::fs::write(provider.path(&key), serde_json::to_string(&entry).unwrap()).unwrap();
+
+        let response = provider
+            .get_completion(user("hi"), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(response.content, "reply 2 to hi");
+    }
+
+    #[tokio::test]
+    async fn test_image_files_are_keyed_by_their_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        let provider = cached(dir.path(), &inner, CacheConfig::default());
+        let image = dir.path().join("screenshot.png");
+        let messages = vec![ChatMessage {
+            role: Role::User,
+            content: Some("What changed?".to_string()),
+            parts: vec![ContentPart::Image {
+                source: ImageSource::Path(image.clone()),
+            }],
+            ..Default::default()
+        }];
+        let options = RequestOptions::default();
+
+        std::fs::write(&image, [0x89, b'P', b'N', b'G', 1]).unwrap();
+        provider
+            .get_completion(messages.clone(), None, &options)
+            .await
+            .unwrap();
+        provider
+            .get_completion(messages.clone(), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        // Same path, new screenshot.
+        std::fs::write(&image, [0x89, b'P', b'N', b'G', 2]).unwrap();
+        provider
+            .get_completion(messages, None, &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_kept_reasoning_is_part_of_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = CountingProvider::default();
+        // Wrapped as the registry does for `keep_reasoning = true`.
+        let provider = KeepReasoningProvider::new(Box::new(cached(
+            dir.path(),
+            &inner,
+            CacheConfig::default(),
+        )));
+        let history = |reasoning: &str| {
+            vec![
+                ChatMessage {
+                    role: Role::User,
+                    content: Some("Pick a number.".to_string()),
+                    ..Default::default()
+                },
+                ChatMessage {
+                    role: Role::Assistant,
+                    content: Some("7".to_string()),
+                    reasoning: Some(reasoning.to_string()),
+                    ..Default::default()
+                },
+                ChatMessage {
+                    role: Role::User,
+                    content: Some("Why?".to_string()),
+                    ..Default::default()
+                },
+            ]
+        };
+        let options = RequestOptions::default();
+
+        provider
+            .get_completion(history("Lucky number."), None, &options)
+            .await
+            .unwrap();
+        provider
+            .get_completion(history("Lucky number."), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+        provider
+            .get_completion(history("Prime and odd."), None, &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,172 @@
+// volition-agent-core/src/providers/capabilities.rs
+
+//! What a model can do, as reported by [`Provider::capabilities`](super::Provider::capabilities).
+//!
+//! Capabilities start from a built-in catalog of well-known model names and are
+//! overridden by the `capabilities` table of the model's `model_config`, so a
+//! fine-tune or an unlisted model only needs the fields that differ.
+
+use crate::config::CapabilitiesConfig;
+
+/// The features of a model that change how Volition talks to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Native tool/function calling.
+    pub tools: bool,
+    /// Image inputs.
+    pub images: bool,
+    /// Streamed responses.
+    pub streaming: bool,
+    /// Responses constrained to a JSON schema.
+    pub json_mode: bool,
+    /// Context window in tokens, if known.
+    pub context_window: Option<u32>,
+}
+
+/// Assumed for models missing from the catalog: text, tools and JSON schema
+/// output, no images. A model that cannot follow a schema is left to its
+/// provider to refuse.
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            images: false,
+            streaming: true,
+            json_mode: true,
+            context_window: None,
+        }
+    }
+}
+
+/// Share of the context window a prompt may fill before old turns are dropped;
+/// the rest is left for the response.
+const COMPACTION_RATIO: f64 = 0.75;
+
+/// Model name prefixes, most specific first:
+/// (prefix, tools, images, json_mode, context_window).
+const CATALOG: &[(&str, bool, bool, bool, u32)] = &[
+    // OpenAI
+    ("gpt-4o", true, true, true, 128_000),
+    ("gpt-4.1", true, true, true, 1_047_576),
+    ("gpt-4-turbo", true, true, true, 128_000),
+    ("gpt-4", true, false, false, 8_192),
+    ("gpt-3.5-turbo", true, false, true, 16_385),
+    ("o1", true, true, true, 200_000),
+    ("o3", true, true, true, 200_000),
+    ("o4", true, true, true, 200_000),
+    // Anthropic
+    ("claude-", true, true, false, 200_000),
+    // Gemini
+    ("gemini-1.5-pro", true, true, true, 2_097_152),
+    ("gemini-", true, true, true, 1_048_576),
+    // Models commonly served by Ollama and OpenAI-compatible servers
+    ("llama3.1", true, false, true, 131_072),
+    ("llama3.2-vision", false, true, true, 131_072),
+    ("llama3.2", true, false, true, 131_072),
+    ("llama3.3", true, false, true, 131_072),
+    ("llama3", false, false, true, 8_192),
+    ("qwen2.5vl", false, true, true, 128_000),
+    ("qwen2.5", true, false, true, 32_768),
+    ("qwen3", true, false, true, 40_960),
+    ("deepseek-r1", false, false, true, 131_072),
+    ("mistral", true, false, true, 32_768),
+    ("llava", false, true, true, 4_096),
+    ("gemma3", false, true, true, 131_072),
+    ("phi4", false, false, true, 16_384),
+];
+
+impl ModelCapabilities {
+    /// Capabilities of providers that accept anything, such as offline test
+    /// doubles.
+    pub fn unrestricted() -> Self {
+        Self {
+            tools: true,
+            images: true,
+            streaming: true,
+            json_mode: true,
+            context_window: None,
+        }
+    }
+
+    /// Built-in capabilities of `model_name`, or the defaults for an unknown model.
+    ///
+    /// Matching ignores case and any `vendor/` or `models/` prefix, so
+    /// `models/gemini-2.0-flash` and `meta-llama/Llama3.1-8B` are recognized.
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.to_lowercase();
+        let name = name.rsplit('/').next().unwrap_or_default();
+        CATALOG
+            .iter()
+            .find(|(prefix, ..)| name.starts_with(prefix))
+            .map(|&(_, tools, images, json_mode, context_window)| Self {
+                tools,
+                images,
+                streaming: true,
+                json_mode,
+                context_window: Some(context_window),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Catalog capabilities of `model_name`, overridden by the configured ones.
+    pub fn resolve(model_name: &str, config: &CapabilitiesConfig) -> Self {
+        Self::for_model(model_name).with_overrides(config)
+    }
+
+    /// Replaces every capability set in `config`.
+    pub fn with_overrides(self, config: &CapabilitiesConfig) -> Self {
+        Self {
+            tools: config.tools.unwrap_or(self.tools),
+            images: config.images.unwrap_or(self.images),
+            streaming: config.streaming.unwrap_or(self.streaming),
+            json_mode: config.json_mode.unwrap_or(self.json_mode),
+            context_window: config.context_window.or(self.context_window),
+        }
+    }
+
+    /// Estimated prompt size, in tokens, above which the agent drops old turns
+    /// from the request. `None` if the context window is unknown.
+    pub fn compaction_threshold(&self) -> Option<usize> {
+        self.context_window
+            .map(|window| (window as f64 * COMPACTION_RATIO) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_lookup() {
+        let gpt = ModelCapabilities::for_model("gpt-4o-mini");
+        assert!(gpt.images && gpt.tools && gpt.json_mode);
+        assert_eq!(gpt.context_window, Some(128_000));
+
+        assert_eq!(
+            ModelCapabilities::for_model("models/gemini-2.0-flash").context_window,
+            Some(1_048_576)
+        );
+        assert!(!ModelCapabilities::for_model("deepseek-r1:14b").tools);
+        assert!(ModelCapabilities::for_model("Qwen/Qwen2.5-Coder-32B-Instruct").tools);
+        assert_eq!(
+            ModelCapabilities::for_model("my-finetune"),
+            ModelCapabilities::default()
+        );
+        assert!(ModelCapabilities::for_model("my-finetune").json_mode);
+        assert!(!ModelCapabilities::for_model("claude-sonnet-4").json_mode);
+    }
+
+    #[test]
+    fn test_configured_capabilities_override_the_catalog() {
+        let config = CapabilitiesConfig {
+            images: Some(true),
+            context_window: Some(8_000),
+            ..Default::default()
+        };
+        let capabilities = ModelCapabilities::resolve("deepseek-r1:14b", &config);
+        assert!(capabilities.images);
+        assert!(!capabilities.tools);
+        assert_eq!(capabilities.context_window, Some(8_000));
+        assert_eq!(capabilities.compaction_threshold(), Some(6_000));
+    }
+}
@@ -0,0 +1,530 @@
+// volition-agent-core/src/providers/cassette.rs
+
+//! Record and replay of provider interactions for offline tests.
+//!
+//! With `record_to` set, an HTTP provider appends the request body it sends
+//! and the raw response body it receives to a JSON cassette file. With
+//! `cassette` set, it serves the recorded bodies instead of calling the API, so
+//! the replayed bodies still go through the provider's own response parsing.
+//! Requests are matched against the recorded ones after normalization, and a
+//! request that matches none fails with a description of the difference.
+//!
+//! Requests are normalized so that a replay does not depend on details that
+//! change between runs: tool call IDs are dropped, tool arguments are compared
+//! as JSON values, message text is trimmed and tools are compared by name.
+
+use super::streaming::for_each_line;
+use crate::config::ModelConfig;
+use crate::errors::ProviderError;
+use crate::models::chat::{ChatMessage, ContentPart};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Context, Result, anyhow};
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+/// One recorded HTTP exchange.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    /// The normalized request, used to match requests on replay.
+    pub request: Value,
+    /// The request body sent to the API.
+    pub payload: Value,
+    /// Whether the response was streamed, in which case `response` holds its
+    /// non-empty lines.
+    #[serde(default)]
+    pub streamed: bool,
+    /// The raw response body.
+    pub response: String,
+}
+
+/// The contents of a cassette file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse cassette file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cassette directory {}", parent.display())
+            })?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write cassette file {}", path.display()))
+    }
+}
+
+/// Builds the normalized form of a request used for recording and matching.
+/// Request options are only included when set, so older cassettes still match.
+pub fn normalize_request(
+    messages: &[ChatMessage],
+    tools: Option<&[ToolDefinition]>,
+    options: &RequestOptions,
+) -> Value {
+    let messages: Vec<Value> = messages
+        .iter()
+        .map(|msg| {
+            let mut normalized = json!({
+                "role": msg.role,
+                "content": msg
+                    .content
+                    .as_deref()
+                    .unwrap_or_default()
+                    .replace("\r\n", "\n")
+                    .trim(),
+            });
+            if let Some(calls) = &msg.tool_calls
+                && !calls.is_empty()
+            {
+                normalized["tool_calls"] = calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "name": call.function.name,
+                            "arguments": serde_json::from_str::<Value>(&call.function.arguments)
+                                .unwrap_or_else(|_| json!(call.function.arguments)),
+                        })
+                    })
+                    .collect();
+            }
+            if !msg.parts.is_empty() {
+                normalized["parts"] = msg.parts.iter().map(normalize_part).collect();
+            }
+            normalized
+        })
+        .collect();
+
+    let mut tool_names: Vec<&str> = tools
+        .unwrap_or_default()
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    tool_names.sort_unstable();
+
+    let mut request = json!({ "messages": messages, "tools": tool_names });
+    if !options.is_empty() {
+        request["options"] = json!(options);
+    }
+    request
+}
+
+fn normalize_part(part: &ContentPart) -> Value {
+    match part {
+        ContentPart::Text { text } => json!({ "type": "text", "text": text.replace("\r\n", "\n").trim() }),
+        ContentPart::Image { source } => json!({ "type": "image", "source": source }),
+        ContentPart::ToolResult { content, .. } => json!({
+            "type": "tool_result",
+            "content": content.replace("\r\n", "\n").trim(),
+        }),
+    }
+}
+
+/// How an HTTP provider exchanges completion requests with its API, from the
+/// `record_to` and `cassette` settings of its `model_config`.
+#[derive(Clone, Default)]
+pub(crate) enum Wire {
+    /// Calls the API.
+    #[default]
+    Live,
+    /// Calls the API and appends each exchange to a cassette.
+    Record(Arc<CassetteFile>),
+    /// Serves responses from a cassette without calling the API.
+    Replay(Arc<CassetteFile>),
+}
+
+impl Wire {
+    pub(crate) fn from_config(config: &ModelConfig) -> Self {
+        match (&config.cassette, &config.record_to) {
+            (Some(path), _) => {
+                info!("Replaying provider interactions from {}", path);
+                Wire::Replay(Arc::new(CassetteFile::new(path)))
+            }
+            (None, Some(path)) => {
+                info!("Recording provider interactions to {}", path);
+                Wire::Record(Arc::new(CassetteFile::new(path)))
+            }
+            (None, None) => Wire::Live,
+        }
+    }
+
+    /// Returns the body of the response to `payload`, which `send` sends, or
+    /// the recorded body of the interaction matching `request`.
+    pub(crate) async fn body(
+        &self,
+        request: Value,
+        payload: &Value,
+        send: impl Future<Output = Result<Response>>,
+    ) -> Result<String> {
+        if let Wire::Replay(cassette) = self {
+            return cassette.replay(&request, false);
+        }
+        let body = send
+            .await?
+            .text()
+            .await
+            .map_err(ProviderError::from)
+            .context("Failed to read response body")?;
+        if let Wire::Record(cassette) = self {
+            cassette.record(request, payload, false, body.clone())?;
+        }
+        Ok(body)
+    }
+
+    /// Like [`Wire::body`] for streamed responses, passing each non-empty line
+    /// to `handle_line` as it arrives.
+    pub(crate) async fn lines(
+        &self,
+        request: Value,
+        payload: &Value,
+        send: impl Future<Output = Result<Response>>,
+        mut handle_line: impl FnMut(&str) -> Result<()>,
+    ) -> Result<()> {
+        match self {
+            Wire::Live => for_each_line(send.await?, handle_line).await,
+            Wire::Record(cassette) => {
+                let mut lines = Vec::new();
+                for_each_line(send.await?, |line| {
+                    lines.push(line.to_string());
+                    handle_line(line)
+                })
+                .await?;
+                cassette.record(request, payload, true, lines.join("\n"))
+            }
+            Wire::Replay(cassette) => cassette
+                .replay(&request, true)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .try_for_each(handle_line),
+        }
+    }
+}
+
+/// A cassette file, loaded on first use.
+pub(crate) struct CassetteFile {
+    path: PathBuf,
+    state: Mutex<Option<CassetteState>>,
+}
+
+struct CassetteState {
+    cassette: Cassette,
+    /// Which interactions have been replayed.
+    used: Vec<bool>,
+}
+
+impl CassetteFile {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Runs `f` on the cassette, loading it first. A missing file is an empty
+    /// cassette when `create` is set.
+    fn with_state<T>(
+        &self,
+        create: bool,
+        f: impl FnOnce(&mut CassetteState) -> Result<T>,
+    ) -> Result<T> {
+        let mut state = self.state.lock().unwrap();
+        if state.is_none() {
+            let cassette = if create && !self.path.exists() {
+                Cassette::default()
+            } else {
+                Cassette::load(&self.path)?
+            };
+            debug!(
+                "Loaded {} interaction(s) from {}",
+                cassette.interactions.len(),
+                self.path.display()
+            );
+            *state = Some(CassetteState {
+                used: vec![false; cassette.interactions.len()],
+                cassette,
+            });
+        }
+        f(state.as_mut().unwrap())
+    }
+
+    /// Appends an exchange and saves the cassette. Existing interactions are
+    /// kept, so several agent runs can record into one cassette.
+    fn record(
+        &self,
+        request: Value,
+        payload: &Value,
+        streamed: bool,
+        response: String,
+    ) -> Result<()> {
+        self.with_state(true, |state| {
+            state.cassette.interactions.push(Interaction {
+                request,
+                payload: payload.clone(),
+                streamed,
+                response,
+            });
+            debug!(
+                "Recorded interaction {} to {}",
+                state.cassette.interactions.len(),
+                self.path.display()
+            );
+            state.cassette.save(&self.path)
+        })
+    }
+
+    /// Returns the response of the first unused interaction matching `request`.
+    fn replay(&self, request: &Value, streamed: bool) -> Result<String> {
+        self.with_state(false, |state| {
+            let interactions = &state.cassette.interactions;
+            let found = interactions.iter().enumerate().position(|(i, interaction)| {
+                !state.used[i] && interaction.streamed == streamed && interaction.request == *request
+            });
+            if let Some(index) = found {
+                debug!("Replaying interaction {} from {}", index + 1, self.path.display());
+                state.used[index] = true;
+                return Ok(interactions[index].response.clone());
+            }
+
+            let actual = serde_json::to_string_pretty(request)?;
+            match state.used.iter().position(|u| !u) {
+                Some(next) => Err(anyhow!(
+                    "Replay mismatch in cassette {}: no unused {} interaction matches the request.\n\
+                     Next unused interaction ({}, {}):\n{}\n\nActual request:\n{}",
+                    self.path.display(),
+                    if streamed { "streamed" } else { "non-streamed" },
+                    next + 1,
+                    if interactions[next].streamed { "streamed" } else { "non-streamed" },
+                    serde_json::to_string_pretty(&interactions[next].request)?,
+                    actual
+                )),
+                None => Err(anyhow!(
+                    "Replay cassette {} is exhausted ({} interaction(s) used). Unexpected request:\n{}",
+                    self.path.display(),
+                    interactions.len(),
+                    actual
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ApiResponse, Role, StreamDelta};
+    use crate::models::tools::{ToolCall, ToolFunction};
+    use crate::providers::Provider;
+    use crate::providers::openai::OpenAIProvider;
+    use httpmock::prelude::*;
+    use reqwest::Client;
+
+    fn provider(
+        endpoint: String,
+        record_to: Option<&Path>,
+        cassette: Option<&Path>,
+    ) -> OpenAIProvider {
+        OpenAIProvider::new(
+            ModelConfig {
+                model_name: "gpt-test".to_string(),
+                endpoint: Some(endpoint),
+                record_to: record_to.map(|p| p.display().to_string()),
+                cassette: cassette.map(|p| p.display().to_string()),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        )
+    }
+
+    /// An endpoint nothing listens on, for providers that must not call the API.
+    const OFFLINE: &str = "http://127.0.0.1:9/v1/chat/completions";
+
+    fn conversation(tool_call_id: &str) -> Vec<ChatMessage> {
+        vec![
+            ChatMessage {
+                role: Role::User,
+                content: Some("List files\r\n".to_string()),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Assistant,
+                content: None,
+                tool_calls: Some(vec![ToolCall {
+                    id: tool_call_id.to_string(),
+                    call_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: "list_dir".to_string(),
+                        arguments: "{ \"path\": \".\" }".to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("a.rs".to_string()),
+                tool_call_id: Some(tool_call_id.to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    async fn complete(provider: &OpenAIProvider, tool_call_id: &str) -> Result<ApiResponse> {
+        provider
+            .get_completion(conversation(tool_call_id), None, &RequestOptions::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_through_provider_parsing() {
+        let server = MockServer::start_async().await;
+        let raw = r#"{"id":"chatcmpl-1","choices":[{"index":0,"message":{"role":"assistant","content":null,"tool_calls":[{"id":"call_r","type":"function","function":{"name":"read_file","arguments":"{\"path\":\"a.rs\"}"}}]},"finish_reason":"tool_calls"}],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/v1/chat/completions");
+                then.status(200).body(raw);
+            })
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassettes/session.json");
+
+        let recorder = provider(server.url("/v1/chat/completions"), Some(&path), None);
+        complete(&recorder, "call_first_run").await.unwrap();
+        mock.assert_async().await;
+
+        // The wire payload and the raw body are recorded, not the parsed response.
+        let cassette = Cassette::load(&path).unwrap();
+        assert_eq!(cassette.interactions.len(), 1);
+        let interaction = &cassette.interactions[0];
+        assert_eq!(interaction.payload["model"], "gpt-test");
+        assert_eq!(
+            interaction.payload["messages"][1]["tool_calls"][0]["id"],
+            "call_first_run"
+        );
+        assert_eq!(interaction.response, raw);
+        assert!(!interaction.streamed);
+
+        // Tool call IDs differ between runs but still match.
+        let replay = provider(OFFLINE.to_string(), None, Some(&path));
+        let response = complete(&replay, "call_second_run").await.unwrap();
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.total_tokens, 7);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.name, "read_file");
+
+        let exhausted = format!(
+            "{:#}",
+            complete(&replay, "call_third_run").await.unwrap_err()
+        );
+        assert!(exhausted.contains("exhausted"), "{}", exhausted);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_responses_replay_as_streams() {
+        let server = MockServer::start_async().await;
+        let body = [
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+            "data: [DONE]",
+        ]
+        .join("\n\n");
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/v1/chat/completions");
+                then.status(200)
+                    .header("content-type", "text/event-stream")
+                    .body(body);
+            })
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let recorder = provider(server.url("/v1/chat/completions"), Some(&path), None);
+        recorder
+            .stream_completion(
+                conversation("call_1"),
+                None,
+                &RequestOptions::default(),
+                &|_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Cassette::load(&path).unwrap().interactions[0].payload["stream"],
+            true
+        );
+
+        let replay = provider(OFFLINE.to_string(), None, Some(&path));
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = replay
+            .stream_completion(
+                conversation("call_2"),
+                None,
+                &RequestOptions::default(),
+                &sink,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Hello");
+        assert_eq!(
+            deltas.lock().unwrap()[0],
+            StreamDelta::Text("Hel".to_string())
+        );
+
+        // A streamed recording does not answer a complete request.
+        let replay = provider(OFFLINE.to_string(), None, Some(&path));
+        let error = format!("{:#}", complete(&replay, "call_3").await.unwrap_err());
+        assert!(
+            error.contains("no unused non-streamed interaction"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_mismatch_fails_loudly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        Cassette {
+            interactions: vec![Interaction {
+                request: normalize_request(
+                    &conversation("call_1"),
+                    None,
+                    &RequestOptions::default(),
+                ),
+                payload: json!({}),
+                streamed: false,
+                response: "{}".to_string(),
+            }],
+        }
+        .save(&path)
+        .unwrap();
+
+        let mut changed = conversation("call_1");
+        changed[0].content = Some("Delete files".to_string());
+        let error = provider(OFFLINE.to_string(), None, Some(&path))
+            .get_completion(changed, None, &RequestOptions::default())
+            .await
+            .unwrap_err();
+        let error = format!("{:#}", error);
+        assert!(error.contains("Replay mismatch"), "{}", error);
+        assert!(error.contains("Delete files"), "{}", error);
+    }
+}
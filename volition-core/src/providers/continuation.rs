@@ -0,0 +1,338 @@
+// volition-agent-core/src/providers/continuation.rs
+
+//! Automatic continuation of responses cut off by the output token limit.
+//!
+//! [`ContinuationProvider`] wraps another [`Provider`]. When a response ends
+//! because it hit the limit, the partial answer is sent back with a request to
+//! continue, and the pieces are stitched into a single response. Tool calls
+//! cannot be continued that way, so a response cut off in its tool calls is
+//! returned still truncated, and the [`Agent`](crate::Agent) does not run them.
+//! Their arguments are deliberately not repaired by closing the JSON: a
+//! `write_file` call cut off in its content would still parse and match the
+//! tool's schema, and would write half a file.
+
+use super::{ModelInfo, Provider};
+use super::capabilities::ModelCapabilities;
+use super::streaming::DeltaSink;
+use crate::models::chat::{ApiResponse, ChatMessage, Role};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+/// Continuations requested per response unless `max_continuations` is set.
+pub const DEFAULT_MAX_CONTINUATIONS: u32 = 3;
+
+const CONTINUE_PROMPT: &str = "Your previous response was cut off by the output length limit. \
+Continue exactly where it stopped, without repeating any of it or adding a preamble.";
+
+pub struct ContinuationProvider {
+    inner: Box<dyn Provider>,
+    max_continuations: u32,
+}
+
+impl ContinuationProvider {
+    pub fn new(inner: Box<dyn Provider>, max_continuations: u32) -> Self {
+        debug!(
+            provider = inner.name(),
+            max_continuations, "Wrapping provider with automatic continuation"
+        );
+        Self {
+            inner,
+            max_continuations,
+        }
+    }
+
+    async fn call(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: Option<DeltaSink<'_>>,
+    ) -> Result<ApiResponse> {
+        match on_delta {
+            Some(on_delta) => {
+                self.inner
+                    .stream_completion(messages, tools, options, on_delta)
+                    .await
+            }
+            None => self.inner.get_completion(messages, tools, options).await,
+        }
+    }
+
+    async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: Option<DeltaSink<'_>>,
+    ) -> Result<ApiResponse> {
+        let mut response = self
+            .call(messages.clone(), tools, options, on_delta)
+            .await?;
+        // A continuation must extend the partial text, not start a new JSON document.
+        let continue_options = RequestOptions {
+            response_format: None,
+            ..options.clone()
+        };
+
+        let mut continuations = 0;
+        while response.is_truncated() {
+            let message = &response
+                .choices
+                .first()
+                .ok_or_else(|| anyhow!("Truncated response has no choices"))?
+                .message;
+            if message.tool_calls.is_some() {
+                warn!("Response was cut off in its tool calls, which cannot be continued");
+                break;
+            }
+            if continuations == self.max_continuations {
+                warn!(
+                    continuations,
+                    "Response still truncated after the maximum number of continuations"
+                );
+                break;
+            }
+            continuations += 1;
+            info!(
+                continuations,
+                "Response was cut off by the length limit; requesting a continuation"
+            );
+
+            let mut request = messages.clone();
+            request.push(ChatMessage {
+                role: Role::Assistant,
+                content: Some(response.content.clone()),
+                ..Default::default()
+            });
+            request.push(ChatMessage {
+                role: Role::User,
+                content: Some(CONTINUE_PROMPT.to_string()),
+                ..Default::default()
+            });
+            let next = self
+                .call(request, tools, &continue_options, on_delta)
+                .await?;
+            response = stitch(response, next);
+        }
+        Ok(response)
+    }
+}
+
+/// Appends a continuation to the response it continues, summing token counts.
+fn stitch(mut first: ApiResponse, next: ApiResponse) -> ApiResponse {
+    let next_message = next
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message)
+        .unwrap_or_default();
+
+    first.content.push_str(&next.content);
+    first.finish_reason = next.finish_reason.clone();
+    first.prompt_tokens += next.prompt_tokens;
+    first.completion_tokens += next.completion_tokens;
+    first.total_tokens += next.total_tokens;
+    if let Some(choice) = first.choices.first_mut() {
+        choice.message.content = Some(first.content.clone());
+        choice.message.tool_calls = next_message.tool_calls;
+        choice.message.reasoning = match (choice.message.reasoning.take(), next_message.reasoning) {
+            (Some(a), Some(b)) => Some(format!("{}\n\n{}", a, b)),
+            (a, b) => a.or(b),
+        };
+        choice.finish_reason = next.finish_reason;
+    }
+    first
+}
+
+#[async_trait]
+impl Provider for ContinuationProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.complete(messages, tools, options, None).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.complete(messages, tools, options, Some(on_delta))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::Choice;
+    use crate::models::tools::{ToolCall, ToolFunction};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone)]
+    struct QueuedProvider {
+        responses: Arc<Mutex<Vec<ApiResponse>>>,
+        requests: Arc<Mutex<Vec<Vec<ChatMessage>>>>,
+    }
+
+    #[async_trait]
+    impl Provider for QueuedProvider {
+        fn name(&self) -> &str {
+            "queued"
+        }
+
+        async fn get_completion(
+            &self,
+            messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            self.requests.lock().unwrap().push(messages);
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    fn response(
+        content: &str,
+        finish_reason: &str,
+        tool_calls: Option<Vec<ToolCall>>,
+    ) -> ApiResponse {
+        ApiResponse {
+            id: "resp".to_string(),
+            content: content.to_string(),
+            finish_reason: finish_reason.to_string(),
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(content.to_string()),
+                    tool_calls,
+                    ..Default::default()
+                },
+                finish_reason: finish_reason.to_string(),
+            }],
+        }
+    }
+
+    fn wrap(
+        responses: Vec<ApiResponse>,
+        max_continuations: u32,
+    ) -> (ContinuationProvider, QueuedProvider) {
+        let inner = QueuedProvider {
+            responses: Arc::new(Mutex::new(responses)),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        };
+        (
+            ContinuationProvider::new(Box::new(inner.clone()), max_continuations),
+            inner,
+        )
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_truncated_text_is_continued_and_stitched() {
+        let (provider, inner) = wrap(
+            vec![
+                response("fn main() {\n    println!(", "length", None),
+                response("\"hi\");\n}", "MAX_TOKENS", None),
+                response("\n", "stop", None),
+            ],
+            3,
+        );
+
+        let result = provider
+            .get_completion(user("Write main"), None, &RequestOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.content, "fn main() {\n    println!(\"hi\");\n}\n");
+        assert_eq!(
+            result.choices[0].message.content.as_deref(),
+            Some(result.content.as_str())
+        );
+        assert_eq!(result.finish_reason, "stop");
+        assert_eq!(result.total_tokens, 45);
+
+        let requests = inner.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].len(), 3);
+        assert_eq!(
+            requests[1][1].content.as_deref(),
+            Some("fn main() {\n    println!(")
+        );
+        assert_eq!(requests[1][2].content.as_deref(), Some(CONTINUE_PROMPT));
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_limited() {
+        let (provider, inner) = wrap(
+            vec![response("a", "length", None), response("b", "length", None)],
+            1,
+        );
+
+        let result = provider
+            .get_completion(user("Go"), None, &RequestOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.content, "ab");
+        assert!(result.is_truncated());
+        assert_eq!(inner.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_tool_call_is_returned_truncated() {
+        let arguments = "{\"path\": \"a.rs\", \"content\": \"fn ma";
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: ToolFunction {
+                name: "write_file".to_string(),
+                arguments: arguments.to_string(),
+            },
+        };
+        let (provider, inner) = wrap(vec![response("", "length", Some(vec![call]))], 3);
+
+        let result = provider
+            .get_completion(user("Write a.rs"), None, &RequestOptions::default())
+            .await
+            .unwrap();
+
+        // The call is neither continued nor made to look complete, so the agent
+        // can tell it must not run it.
+        assert!(result.is_truncated());
+        let calls = result.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.arguments, arguments);
+        assert_eq!(inner.requests.lock().unwrap().len(), 1);
+    }
+}
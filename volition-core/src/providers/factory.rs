@@ -0,0 +1,363 @@
+// volition-agent-core/src/providers/factory.rs
+
+//! Maps the TOML `type` of a provider entry to the code that constructs it.
+//!
+//! The built-in providers register themselves through the same
+//! [`ProviderFactoryRegistry::register`] call that downstream crates use, so a
+//! new provider type never requires patching the agent. Build a
+//! [`ProviderRegistry`] with [`ProviderFactoryRegistry::build_registry`] and pass
+//! it to [`Agent::new`](crate::agent::Agent::new) as the registry override.
+
+use super::anthropic::AnthropicProvider;
+use super::azure_openai::AzureOpenAIProvider;
+use super::cache::ResponseCacheProvider;
+use super::continuation::{ContinuationProvider, DEFAULT_MAX_CONTINUATIONS};
+use super::gemini::GeminiProvider;
+use super::http::{ReadTimeoutProvider, build_client};
+use super::ollama::OllamaProvider;
+use super::openai::OpenAIProvider;
+use super::openai_compatible::OpenAICompatibleProvider;
+use super::reasoning::KeepReasoningProvider;
+use super::router::{ROUTER_TYPE, RouterProvider};
+use super::scripted::ScriptedProvider;
+use super::text_tools::TextToolProvider;
+use super::{Provider, ProviderRegistry};
+use crate::config::{AgentConfig, ModelConfig, ProviderInstanceConfig, ToolCallingMode};
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Constructs providers for one provider `type`.
+///
+/// Implemented for any `Fn(ModelConfig, Client, String) -> Result<Box<dyn Provider>>`,
+/// where the string is the API key read from `api_key_env_var` (empty if unset).
+pub trait ProviderFactory: Send + Sync {
+    fn create(
+        &self,
+        config: ModelConfig,
+        http_client: Client,
+        api_key: String,
+    ) -> Result<Box<dyn Provider>>;
+}
+
+impl<F> ProviderFactory for F
+where
+    F: Fn(ModelConfig, Client, String) -> Result<Box<dyn Provider>> + Send + Sync,
+{
+    fn create(
+        &self,
+        config: ModelConfig,
+        http_client: Client,
+        api_key: String,
+    ) -> Result<Box<dyn Provider>> {
+        self(config, http_client, api_key)
+    }
+}
+
+/// Provider factories keyed by the TOML `type` string.
+#[derive(Clone)]
+pub struct ProviderFactoryRegistry {
+    factories: HashMap<String, Arc<dyn ProviderFactory>>,
+}
+
+impl Default for ProviderFactoryRegistry {
+    /// Creates a registry holding the built-in provider types.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("anthropic", |config, client, key| {
+            Ok(Box::new(AnthropicProvider::new(config, client, key)) as Box<dyn Provider>)
+        });
+        registry.register("azure_openai", |config, client, key| {
+            Ok(Box::new(AzureOpenAIProvider::new(config, client, key)?) as Box<dyn Provider>)
+        });
+        registry.register("gemini", |config, client, key| {
+            Ok(Box::new(GeminiProvider::new(config, client, key)) as Box<dyn Provider>)
+        });
+        registry.register("ollama", |config, client, key| {
+            Ok(Box::new(OllamaProvider::new(config, client, key)) as Box<dyn Provider>)
+        });
+        registry.register("openai", |config, client, key| {
+            Ok(Box::new(OpenAIProvider::new(config, client, key)) as Box<dyn Provider>)
+        });
+        registry.register("openai_compatible", |config, client, key| {
+            Ok(Box::new(OpenAICompatibleProvider::new(config, client, key)?) as Box<dyn Provider>)
+        });
+        registry.register("scripted", |config: ModelConfig, _client, _key| {
+            Ok(Box::new(ScriptedProvider::from_config(&config)?) as Box<dyn Provider>)
+        });
+        registry
+    }
+}
+
+impl ProviderFactoryRegistry {
+    /// Creates a registry without any provider types.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers `factory` for `provider_type`, replacing any existing factory.
+    pub fn register(
+        &mut self,
+        provider_type: impl Into<String>,
+        factory: impl ProviderFactory + 'static,
+    ) {
+        self.factories
+            .insert(provider_type.into(), Arc::new(factory));
+    }
+
+    pub fn get(&self, provider_type: &str) -> Option<&dyn ProviderFactory> {
+        self.factories.get(provider_type).map(|f| f.as_ref())
+    }
+
+    pub fn contains(&self, provider_type: &str) -> bool {
+        self.factories.contains_key(provider_type)
+    }
+
+    /// Registered provider types, sorted.
+    pub fn provider_types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        types.sort_unstable();
+        types
+    }
+
+    /// Creates the provider for the entry `id` with the factory of its type,
+    /// `http_client` (or a client derived from it for the entry's `http`
+    /// settings) and the API key from `api_key_env_var`, without any of the
+    /// wrappers [`build_registry`](Self::build_registry) adds. Router entries
+    /// are not built by a factory and fail here.
+    pub fn create_provider(
+        &self,
+        id: &str,
+        provider_conf: &ProviderInstanceConfig,
+        http_client: &Client,
+    ) -> Result<Box<dyn Provider>> {
+        let factory = self.get(&provider_conf.provider_type).ok_or_else(|| {
+            anyhow!(
+                "Unsupported provider type: '{}' specified for provider ID '{}'. Supported types: {}, {}.",
+                provider_conf.provider_type,
+                id,
+                self.provider_types().join(", "),
+                ROUTER_TYPE
+            )
+        })?;
+
+        let api_key = if !provider_conf.api_key_env_var.is_empty() {
+            match std::env::var(&provider_conf.api_key_env_var) {
+                Ok(key) => key,
+                Err(e) => {
+                    warn!(provider_id = %id, env_var = %provider_conf.api_key_env_var, error = %e, "API key environment variable not set or invalid");
+                    String::new()
+                }
+            }
+        } else {
+            String::new()
+        };
+
+        let model_config = provider_conf.model_config.clone();
+        let client = build_client(&model_config.http, http_client)
+            .with_context(|| format!("Invalid HTTP settings for provider '{}'", id))?;
+        factory
+            .create(model_config, client, api_key)
+            .with_context(|| format!("Failed to create provider '{}'", id))
+    }
+
+    /// Creates a provider for every entry in `config.providers` with
+    /// [`create_provider`](Self::create_provider), wrapping those with
+    /// `read_timeout_secs` set in a [`ReadTimeoutProvider`], all but those with
+    /// `max_continuations = 0` in a [`ContinuationProvider`], those with a text
+    /// `tool_calling` mode (the default for models whose capabilities lack
+    /// `tools`) in a [`TextToolProvider`], those with a `cache` table in a
+    /// [`ResponseCacheProvider`] and, outermost, those with `keep_reasoning` in
+    /// a [`KeepReasoningProvider`], so cache keys cover the reasoning sent back.
+    ///
+    /// Entries of type `router` are built last, as [`RouterProvider`]s over the
+    /// providers already registered.
+    pub fn build_registry(
+        &self,
+        config: &AgentConfig,
+        http_client: &Client,
+    ) -> Result<ProviderRegistry> {
+        let mut registry = ProviderRegistry::new(config.default_provider.clone());
+        let mut routers = Vec::new();
+        for (id, provider_conf) in &config.providers {
+            if provider_conf.provider_type == ROUTER_TYPE {
+                routers.push((id, &provider_conf.model_config));
+                continue;
+            }
+            let model_config = &provider_conf.model_config;
+            let tool_calling = model_config.tool_calling;
+            let keep_reasoning = model_config.keep_reasoning;
+            let max_continuations = model_config
+                .max_continuations
+                .unwrap_or(DEFAULT_MAX_CONTINUATIONS);
+            let cache = model_config.cache.clone();
+            // Every setting that changes what is sent, or what answers it.
+            let cache_scope = json!({
+                "type": provider_conf.provider_type,
+                "model": model_config.model_name,
+                "endpoint": model_config.endpoint,
+                "deployment": model_config.deployment,
+                "api_version": model_config.api_version,
+                "parameters": model_config.parameters,
+                "tool_calling": model_config.tool_calling,
+                "keep_reasoning": model_config.keep_reasoning,
+                "max_continuations": model_config.max_continuations,
+                "capabilities": model_config.capabilities,
+                "compatibility": model_config.compatibility,
+                "headers": model_config.http.headers,
+                "cassette": model_config.cassette,
+                "script": model_config.script,
+            });
+            let read_timeout = model_config.http.read_timeout_secs;
+            let mut provider = self.create_provider(id, provider_conf, http_client)?;
+            if let Some(secs) = read_timeout {
+                provider = Box::new(ReadTimeoutProvider::new(provider, Duration::from_secs(secs)));
+            }
+            // Continue before text tool parsing, so it sees the stitched text.
+            if max_continuations > 0 {
+                provider = Box::new(ContinuationProvider::new(provider, max_continuations));
+            }
+            // Models without native tool calling get tools described in the prompt.
+            let tool_calling = tool_calling.unwrap_or_else(|| {
+                if provider.capabilities().tools {
+                    ToolCallingMode::Native
+                } else {
+                    debug!(provider_id = %id, "Model lacks native tool calling; using JSON tool calls");
+                    ToolCallingMode::Json
+                }
+            });
+            let provider: Box<dyn Provider> = if tool_calling == ToolCallingMode::Native {
+                provider
+            } else {
+                Box::new(TextToolProvider::new(provider, tool_calling))
+            };
+            let provider: Box<dyn Provider> = match cache {
+                Some(cache) => Box::new(ResponseCacheProvider::new(provider, &cache, cache_scope)?),
+                None => provider,
+            };
+            let provider: Box<dyn Provider> = if keep_reasoning {
+                Box::new(KeepReasoningProvider::new(provider))
+            } else {
+                provider
+            };
+            registry.register(id.clone(), provider);
+        }
+        for (id, model_config) in routers {
+            let router = RouterProvider::new(id, model_config, &registry)
+                .with_context(|| format!("Failed to create provider '{}'", id))?;
+            registry.register(id.clone(), Box::new(router));
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ApiResponse, ChatMessage, Choice, Role};
+    use crate::models::request::RequestOptions;
+    use crate::models::tools::ToolDefinition;
+    use async_trait::async_trait;
+
+    /// Answers with its model name.
+    struct EchoProvider {
+        model: String,
+    }
+
+    #[async_trait]
+    impl Provider for EchoProvider {
+        fn name(&self) -> &str {
+            &self.model
+        }
+
+        async fn get_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            Ok(ApiResponse {
+                id: self.model.clone(),
+                content: self.model.clone(),
+                finish_reason: "stop".to_string(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                choices: vec![Choice {
+                    index: 0,
+                    message: ChatMessage {
+                        role: Role::Assistant,
+                        content: Some(self.model.clone()),
+                        ..Default::default()
+                    },
+                    finish_reason: "stop".to_string(),
+                }],
+            })
+        }
+    }
+
+    fn config(provider_type: &str) -> AgentConfig {
+        AgentConfig::from_toml_str(&format!(
+            r#"
+            system_prompt = "Test"
+            default_provider = "main"
+            [providers.main]
+            type = "{}"
+            api_key_env_var = "VOLITION_TEST_UNSET_KEY"
+            [providers.main.model_config]
+                model_name = "echo-1"
+                endpoint = "http://localhost:1234"
+            "#,
+            provider_type
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_builtin_types_are_registered() {
+        assert_eq!(
+            ProviderFactoryRegistry::default().provider_types(),
+            vec![
+                "anthropic",
+                "azure_openai",
+                "gemini",
+                "ollama",
+                "openai",
+                "openai_compatible",
+                "scripted"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_registry_uses_registered_factory() {
+        let mut factories = ProviderFactoryRegistry::default();
+        factories.register("echo", |config: ModelConfig, _client, _key| {
+            Ok(Box::new(EchoProvider {
+                model: config.model_name,
+            }) as Box<dyn Provider>)
+        });
+
+        let registry = factories
+            .build_registry(&config("echo"), &Client::new())
+            .unwrap();
+        assert_eq!(registry.default().unwrap().name(), "echo-1");
+    }
+
+    #[test]
+    fn test_build_registry_rejects_unknown_type() {
+        let error = ProviderFactoryRegistry::default()
+            .build_registry(&config("echo"), &Client::new())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("Unsupported provider type: 'echo'"), "{}", error);
+        assert!(error.contains("openai_compatible"), "{}", error);
+    }
+}
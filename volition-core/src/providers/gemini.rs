@@ -1,12 +1,21 @@
 // volition-agent-core/src/providers/gemini.rs
-use super::Provider;
+use super::{ModelInfo, Provider, check_response, check_tool_choice, merge_extra_params};
+use super::capabilities::ModelCapabilities;
+use super::reasoning::split_reasoning;
+use super::cassette::{Wire, normalize_request};
+use super::streaming::{DeltaSink, StreamAccumulator, sse_data};
 use crate::config::ModelConfig;
-use crate::models::chat::{ApiResponse, ChatMessage, Choice};
-use crate::models::tools::ToolDefinition;
-use anyhow::{Result, anyhow, Context};
+use crate::errors::ProviderError;
+use crate::models::chat::{
+    ApiResponse, ChatMessage, Choice, Role, StreamDelta, split_tool_results,
+};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::{Result, Context};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use tracing::debug;
 
 const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
@@ -16,11 +25,13 @@ pub struct GeminiProvider {
     config: ModelConfig,
     http_client: Client,
     api_key: String,
+    wire: Wire,
 }
 
 impl GeminiProvider {
     pub fn new(config: ModelConfig, http_client: Client, api_key: String) -> Self {
         Self {
+            wire: Wire::from_config(&config),
             config,
             http_client,
             api_key,
@@ -36,58 +47,116 @@ impl GeminiProvider {
         }
     }
 
+    /// The models list URL: the configured endpoint up to `/models`, keeping its
+    /// query (and so any `key`), or the public API.
+    fn models_endpoint(&self) -> Result<String> {
+        let Some(endpoint) = &self.config.endpoint else {
+            return Ok(format!("{}?pageSize=1000&key={}", DEFAULT_BASE_URL, self.api_key));
+        };
+        let mut url = url::Url::parse(endpoint)
+            .with_context(|| format!("Invalid Gemini endpoint URL: {}", endpoint))?;
+        let path = url.path().to_string();
+        if let Some(pos) = path.find("/models/") {
+            url.set_path(&path[..pos + "/models".len()]);
+        }
+        url.query_pairs_mut().append_pair("pageSize", "1000");
+        Ok(url.to_string())
+    }
+
+    /// Builds the `streamGenerateContent` endpoint with server-sent events enabled.
+    fn build_stream_endpoint(&self) -> String {
+        if let Some(endpoint) = &self.config.endpoint {
+            let endpoint = endpoint.replace(":generateContent", ":streamGenerateContent");
+            let separator = if endpoint.contains('?') { '&' } else { '?' };
+            format!("{}{}alt=sse", endpoint, separator)
+        } else {
+            format!(
+                "{}/{}:streamGenerateContent?alt=sse&key={}",
+                DEFAULT_BASE_URL, self.config.model_name, self.api_key
+            )
+        }
+    }
+
     fn build_payload(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
     ) -> Result<Value> {
         debug!("Building Gemini payload...");
         debug!("Model name: {}", self.config.model_name);
         debug!("Message count: {}", messages.len());
 
-        let mut payload = json!({
-            "contents": messages.iter().map(|msg| {
-                json!({
-                    "role": msg.role,
-                    "parts": [{"text": msg.content.as_deref().unwrap_or_default()}]
-                })
-            }).collect::<Vec<_>>()
-        });
+        let (system_instruction, contents) = convert_messages(&messages)?;
+        let mut payload = json!({ "contents": contents });
+        if let Some(system_instruction) = system_instruction {
+            payload["systemInstruction"] = system_instruction;
+        }
 
-        // Add tools if present
-        if let Some(tools) = tools {
-            if !tools.is_empty() {
-                let tools_with_type: Vec<Value> = tools
-                    .iter()
-                    .map(|t| {
-                        json!({
-                            "functionDeclarations": [{
-                                "name": t.name,
-                                "description": t.description,
-                                "parameters": t.parameters
-                            }]
-                        })
-                    })
-                    .collect();
-                payload["tools"] = json!(tools_with_type);
-            }
+        // Add tools if present. Gemini expects a single tool holding every declaration.
+        if let Some(tools) = tools
+            && !tools.is_empty()
+        {
+            let declarations: Vec<Value> = tools.iter().map(function_declaration).collect();
+            payload["tools"] = json!([{ "functionDeclarations": declarations }]);
         }
 
-        // Add parameters if present
-        if let Some(params) = &self.config.parameters {
-            if let Some(generation_config) = params.get("generation_config") {
-                if let Some(table) = generation_config.as_table() {
-                    debug!("Adding generation config parameters");
-                    let mut generation_config = json!({});
-                    for (key, value) in table {
-                        if let Some(num) = value.as_float() {
-                            generation_config[key] = json!(num);
-                        }
-                    }
-                    payload["generationConfig"] = generation_config;
-                }
-            }
+        // Add request options
+        check_tool_choice(options, tools)?;
+        if let Some(choice) = &options.tool_choice
+            && payload.get("tools").is_some()
+        {
+            let config = match choice {
+                ToolChoice::Auto => json!({ "mode": "AUTO" }),
+                ToolChoice::None => json!({ "mode": "NONE" }),
+                ToolChoice::Required => json!({ "mode": "ANY" }),
+                ToolChoice::Tool(name) => json!({ "mode": "ANY", "allowedFunctionNames": [name] }),
+            };
+            payload["toolConfig"] = json!({ "functionCallingConfig": config });
+        }
+        if options.response_format.is_some()
+            && payload.get("tools").is_some()
+            && options.tool_choice != Some(ToolChoice::None)
+        {
+            return Err(ProviderError::Unsupported(format!(
+                "Gemini model {} cannot combine a JSON schema response format with function calling; pass no tools or tool_choice = none",
+                self.config.model_name
+            ))
+            .into());
+        }
+
+        // Add generation parameters
+        let params = &self.config.parameters;
+        let mut generation_config = json!({});
+        if let Some(temperature) = params.temperature {
+            generation_config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            generation_config["topP"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            generation_config["maxOutputTokens"] = json!(max_tokens);
         }
+        if !params.stop.is_empty() {
+            generation_config["stopSequences"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            generation_config["seed"] = json!(seed);
+        }
+        let mut extra = params.extra.clone();
+        // Older configs put raw generationConfig fields under `generation_config`.
+        if let Some(toml::Value::Table(legacy)) = extra.remove("generation_config") {
+            debug!("Adding legacy generation_config parameters");
+            merge_extra_params(&mut generation_config, &legacy);
+        }
+        if let Some(format) = &options.response_format {
+            generation_config["responseMimeType"] = json!("application/json");
+            generation_config["responseSchema"] = to_gemini_schema(&format.schema);
+        }
+        if generation_config.as_object().is_some_and(|c| !c.is_empty()) {
+            payload["generationConfig"] = generation_config;
+        }
+        merge_extra_params(&mut payload, &extra);
 
         debug!("Final payload: {}", serde_json::to_string_pretty(&payload)?);
         Ok(payload)
@@ -97,13 +166,67 @@ impl GeminiProvider {
         debug!("Parsing Gemini response...");
         debug!("Response body: {}", response_body);
 
-        let raw_response: Value = serde_json::from_str(response_body)?;
-        
-        let content = raw_response["candidates"][0]["content"]["parts"][0]["text"]
-            .as_str()
-            .ok_or_else(|| anyhow!("Missing text content in Gemini response"))?
-            .to_string();
+        let raw_response: Value =
+            serde_json::from_str(response_body).map_err(ProviderError::from)?;
+        if raw_response["error"].is_object() {
+            return Err(ProviderError::from_error(&raw_response["error"]))
+                .context("Gemini returned an error");
+        }
+        if let Some(reason) = raw_response["promptFeedback"]["blockReason"].as_str() {
+            return Err(ProviderError::ContentFiltered(format!(
+                "Gemini blocked the prompt: {}",
+                reason
+            ))
+            .into());
+        }
+
+        let candidate = raw_response["candidates"]
+            .get(0)
+            .ok_or_else(|| {
+                ProviderError::MalformedResponse("Missing candidates in Gemini response".to_string())
+            })?;
+        let parts = candidate["content"]["parts"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let mut content = String::new();
+        let mut thoughts = String::new();
+        let mut tool_calls = Vec::new();
+        for part in &parts {
+            // Thought summaries are text parts flagged with `thought: true`.
+            if let Some(text) = part["text"].as_str() {
+                if part["thought"].as_bool() == Some(true) {
+                    thoughts.push_str(text);
+                } else {
+                    content.push_str(text);
+                }
+            }
+            if let Some(call) = parse_function_call(part) {
+                tool_calls.push(call);
+            }
+        }
+        if parts.is_empty() {
+            match candidate["finishReason"].as_str() {
+                None => {
+                    return Err(ProviderError::MalformedResponse(
+                        "Missing content in Gemini response".to_string(),
+                    )
+                    .into());
+                }
+                Some(reason @ ("SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST" | "SPII")) => {
+                    return Err(ProviderError::ContentFiltered(format!(
+                        "Gemini stopped the response: {}",
+                        reason
+                    ))
+                    .into());
+                }
+                Some(_) => {}
+            }
+        }
+        let (reasoning, content) = split_reasoning(Some(&thoughts), &content, false);
         debug!("Extracted content: {}", content);
+        debug!("Extracted {} function call(s)", tool_calls.len());
 
         let finish_reason = raw_response["candidates"][0]["finishReason"]
             .as_str()
@@ -122,9 +245,9 @@ impl GeminiProvider {
             prompt_tokens, completion_tokens, total_tokens);
 
         let result = ApiResponse {
-            id: raw_response["promptFeedback"]["promptTokenCount"]
-                .as_u64()
-                .map(|n| n.to_string())
+            id: raw_response["responseId"]
+                .as_str()
+                .map(|s| s.to_string())
                 .unwrap_or_default(),
             content: content.clone(),
             finish_reason: finish_reason.clone(),
@@ -134,10 +257,16 @@ impl GeminiProvider {
             choices: vec![Choice {
                 index: 0,
                 message: ChatMessage {
-                    role: "assistant".to_string(),
+                    role: Role::Assistant,
                     content: Some(content),
-                    tool_calls: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
                     tool_call_id: None,
+                    reasoning,
+                    ..Default::default()
                 },
                 finish_reason,
             }],
@@ -147,25 +276,104 @@ impl GeminiProvider {
         Ok(result)
     }
 
-    async fn call_chat_completion_api(
+    /// Applies one SSE `data:` payload; each is a partial `GenerateContentResponse`.
+    fn parse_stream_chunk(
         &self,
-        messages: Vec<ChatMessage>,
-        tools: Option<&[ToolDefinition]>,
-    ) -> Result<ApiResponse> {
-        let endpoint = self.build_endpoint();
-        let payload = self.build_payload(messages, tools)?;
+        data: &str,
+        acc: &mut StreamAccumulator,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<()> {
+        let chunk: Value = serde_json::from_str(data)
+            .map_err(ProviderError::from)
+            .with_context(|| format!("Invalid JSON in Gemini stream chunk: {}", data))?;
+        let candidate = &chunk["candidates"][0];
+        if let Some(parts) = candidate["content"]["parts"].as_array() {
+            for part in parts {
+                if let Some(text) = part["text"].as_str() {
+                    let delta = if part["thought"].as_bool() == Some(true) {
+                        StreamDelta::Reasoning(text.to_string())
+                    } else {
+                        StreamDelta::Text(text.to_string())
+                    };
+                    acc.push(delta, on_delta);
+                }
+                // Function calls are never split across chunks.
+                if let Some(call) = parse_function_call(part) {
+                    acc.push(
+                        StreamDelta::ToolCall {
+                            index: acc.tool_call_count(),
+                            id: Some(call.id),
+                            name: Some(call.function.name),
+                            arguments: call.function.arguments,
+                        },
+                        on_delta,
+                    );
+                }
+            }
+        }
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            acc.finish_reason = Some(reason.to_string());
+        }
+        let usage = &chunk["usageMetadata"];
+        if usage.is_object() {
+            acc.prompt_tokens = usage["promptTokenCount"].as_u64().unwrap_or(0) as u32;
+            acc.completion_tokens = usage["candidatesTokenCount"].as_u64().unwrap_or(0) as u32;
+        }
+        Ok(())
+    }
 
+    async fn send_request(&self, endpoint: &str, payload: &Value) -> Result<reqwest::Response> {
         let response = self
             .http_client
-            .post(&endpoint)
+            .post(endpoint)
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await
+            .map_err(ProviderError::from)
             .context("Failed to send request to Gemini API")?;
+        Ok(check_response(response).await?)
+    }
+
+    async fn stream_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let payload = self.build_payload(messages, tools, options)?;
+        let endpoint = self.build_stream_endpoint();
 
-        let response_body = response
-            .text()
+        let mut acc = StreamAccumulator::default();
+        self.wire
+            .lines(
+                request,
+                &payload,
+                self.send_request(&endpoint, &payload),
+                |line| match sse_data(line) {
+                    Some(data) => self.parse_stream_chunk(data, &mut acc, on_delta),
+                    None => Ok(()),
+                },
+            )
+            .await
+            .context("Failed to read streamed response from Gemini API")?;
+        Ok(acc.finish(on_delta))
+    }
+
+    async fn call_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let endpoint = self.build_endpoint();
+        let request = normalize_request(&messages, tools, options);
+        let payload = self.build_payload(messages, tools, options)?;
+        let response_body = self
+            .wire
+            .body(request, &payload, self.send_request(&endpoint, &payload))
             .await
             .context("Failed to read response from Gemini API")?;
 
@@ -173,17 +381,634 @@ impl GeminiProvider {
     }
 }
 
+/// Schema keywords Gemini accepts in function declarations (an OpenAPI 3.0 subset).
+const SUPPORTED_SCHEMA_KEYS: &[&str] = &[
+    "type",
+    "format",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+];
+
+/// Splits chat history into Gemini's `systemInstruction` and `contents`.
+///
+/// Assistant messages use the `model` role with `functionCall` parts. Consecutive
+/// tool results are grouped into a single `user` turn of `functionResponse` parts,
+/// matching the function calls of the preceding model turn. Images become
+/// `inlineData` parts.
+fn convert_messages(messages: &[ChatMessage]) -> Result<(Option<Value>, Vec<Value>)> {
+    let messages = split_tool_results(messages);
+    let mut system_parts = Vec::new();
+    let mut contents: Vec<Value> = Vec::new();
+    let mut call_names: HashMap<&str, &str> = HashMap::new();
+    let mut previous_was_tool = false;
+
+    for msg in &messages {
+        let text = msg.text();
+        match msg.role {
+            Role::System => {
+                if msg.images().next().is_some() {
+                    return Err(ProviderError::Unsupported(
+                        "Gemini system instructions cannot contain images".to_string(),
+                    )
+                    .into());
+                }
+                system_parts.push(json!({ "text": text }));
+                previous_was_tool = false;
+            }
+            Role::Assistant => {
+                let mut parts = Vec::new();
+                if !text.is_empty() {
+                    parts.push(json!({ "text": text }));
+                }
+                parts.extend(image_parts(msg)?);
+                for call in msg.tool_calls.iter().flatten() {
+                    call_names.insert(&call.id, &call.function.name);
+                    let args: Value =
+                        serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                    parts.push(json!({
+                        "functionCall": { "name": call.function.name, "args": args }
+                    }));
+                }
+                if parts.is_empty() {
+                    parts.push(json!({ "text": "" }));
+                }
+                contents.push(json!({ "role": "model", "parts": parts }));
+                previous_was_tool = false;
+            }
+            Role::Tool => {
+                let name = msg
+                    .tool_call_id
+                    .as_deref()
+                    .and_then(|id| call_names.get(id))
+                    .copied()
+                    .unwrap_or_default();
+                let part = json!({
+                    "functionResponse": {
+                        "name": name,
+                        "response": { "content": text }
+                    }
+                });
+                match contents.last_mut() {
+                    Some(last) if previous_was_tool => {
+                        last["parts"].as_array_mut().unwrap().push(part)
+                    }
+                    _ => contents.push(json!({ "role": "user", "parts": [part] })),
+                }
+                previous_was_tool = true;
+            }
+            Role::User => {
+                let mut parts = vec![json!({ "text": text })];
+                parts.extend(image_parts(msg)?);
+                contents.push(json!({ "role": "user", "parts": parts }));
+                previous_was_tool = false;
+            }
+        }
+    }
+
+    let system_instruction = if system_parts.is_empty() {
+        None
+    } else {
+        Some(json!({ "parts": system_parts }))
+    };
+    Ok((system_instruction, contents))
+}
+
+/// Converts the images of a message into `inlineData` parts.
+fn image_parts(msg: &ChatMessage) -> Result<Vec<Value>> {
+    msg.images()
+        .map(|image| {
+            let (media_type, data) = image.to_base64()?;
+            Ok(json!({ "inlineData": { "mimeType": media_type, "data": data } }))
+        })
+        .collect()
+}
+
+/// Builds a `functionDeclarations` entry. Gemini rejects object schemas without
+/// properties, so parameters are omitted for tools that take no arguments.
+fn function_declaration(tool: &ToolDefinition) -> Value {
+    let mut declaration = json!({
+        "name": tool.name,
+        "description": tool.description,
+    });
+    if !tool.parameters.properties.is_empty() {
+        let schema = serde_json::to_value(&tool.parameters).unwrap_or(json!({}));
+        declaration["parameters"] = to_gemini_schema(&schema);
+    }
+    declaration
+}
+
+/// Recursively strips JSON schema keywords that Gemini does not support.
+fn to_gemini_schema(schema: &Value) -> Value {
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+    let mut converted = serde_json::Map::new();
+    for (key, value) in object {
+        if !SUPPORTED_SCHEMA_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match key.as_str() {
+            "properties" => match value.as_object() {
+                Some(props) if !props.is_empty() => Value::Object(
+                    props
+                        .iter()
+                        .map(|(name, prop)| (name.clone(), to_gemini_schema(prop)))
+                        .collect(),
+                ),
+                _ => continue,
+            },
+            "items" => to_gemini_schema(value),
+            "required" if value.as_array().is_some_and(|r| r.is_empty()) => continue,
+            _ => value.clone(),
+        };
+        converted.insert(key.clone(), value);
+    }
+    Value::Object(converted)
+}
+
+/// Parses a `functionCall` part into a [`ToolCall`], generating an ID when absent.
+fn parse_function_call(part: &Value) -> Option<ToolCall> {
+    let call = part.get("functionCall")?;
+    let name = call["name"].as_str()?;
+    let arguments = match &call["args"] {
+        Value::Null => "{}".to_string(),
+        args => args.to_string(),
+    };
+    Some(ToolCall {
+        id: call["id"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+        call_type: "function".to_string(),
+        function: ToolFunction {
+            name: name.to_string(),
+            arguments,
+        },
+    })
+}
+
 #[async_trait]
 impl Provider for GeminiProvider {
     fn name(&self) -> &str {
         &self.config.model_name
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::resolve(&self.config.model_name, &self.config.capabilities)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let endpoint = self.models_endpoint()?;
+        debug!("Listing Gemini models");
+        let response = self
+            .http_client
+            .get(endpoint)
+            .send()
+            .await
+            .map_err(ProviderError::from)
+            .context("Failed to list Gemini models")?;
+        let body: Value = serde_json::from_str(&check_response(response).await?.text().await?)
+            .map_err(ProviderError::from)?;
+        let listed = body["models"].as_array().ok_or_else(|| {
+            ProviderError::MalformedResponse("Gemini model list has no 'models' array".to_string())
+        })?;
+
+        // Embedding and other models that cannot chat are left out.
+        let mut models: Vec<ModelInfo> = listed
+            .iter()
+            .filter(|model| {
+                model["supportedGenerationMethods"]
+                    .as_array()
+                    .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"))
+            })
+            .filter_map(|model| {
+                let name = model["name"].as_str()?;
+                Some(ModelInfo {
+                    id: name.strip_prefix("models/").unwrap_or(name).to_string(),
+                    details: model["displayName"].as_str().map(String::from),
+                })
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
     async fn get_completion(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.call_chat_completion_api(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
     ) -> Result<ApiResponse> {
-        self.call_chat_completion_api(messages, tools).await
+        self.stream_chat_completion_api(messages, tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ContentPart, ImageSource};
+    use crate::models::request::ResponseFormat;
+    use crate::models::tools::{ToolParameter, ToolParameterType, ToolParametersDefinition};
+    use httpmock::prelude::*;
+    use std::sync::Mutex;
+
+    fn provider(endpoint: Option<String>) -> GeminiProvider {
+        GeminiProvider::new(
+            ModelConfig {
+                model_name: "gemini-test".to_string(),
+                endpoint,
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_stream_endpoint_from_configured_endpoint() {
+        let gemini = provider(Some(
+            "https://example.com/v1beta/models/gemini-test:generateContent?key=abc".to_string(),
+        ));
+        assert_eq!(
+            gemini.build_stream_endpoint(),
+            "https://example.com/v1beta/models/gemini-test:streamGenerateContent?key=abc&alt=sse"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_completion_reads_sse() {
+        let server = MockServer::start_async().await;
+        let body = [
+            r#"data: {"candidates":[{"content":{"role":"model","parts":[{"text":"Hello"}]}}]}"#,
+            r#"data: {"candidates":[{"content":{"role":"model","parts":[{"text":" world"}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":4,"candidatesTokenCount":2}}"#,
+        ]
+        .join("\r\n\r\n");
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/models/gemini-test:streamGenerateContent")
+                    .query_param("alt", "sse");
+                then.status(200)
+                    .header("content-type", "text/event-stream")
+                    .body(body);
+            })
+            .await;
+
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = provider(Some(server.url("/models/gemini-test:generateContent")))
+            .stream_completion(
+                vec![ChatMessage {
+                    role: Role::User,
+                    content: Some("hi".to_string()),
+                    ..Default::default()
+                }],
+                None, &RequestOptions::default(),
+                &sink,
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(deltas.lock().unwrap().len(), 2);
+        assert_eq!(response.content, "Hello world");
+        assert_eq!(response.finish_reason, "STOP");
+        assert_eq!(response.total_tokens, 6);
+    }
+
+    #[test]
+    fn test_payload_maps_roles_tools_and_results() {
+        let messages = vec![
+            ChatMessage {
+                role: Role::System,
+                content: Some("Be brief.".to_string()),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::User,
+                content: Some("Read two files".to_string()),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Assistant,
+                content: None,
+                tool_calls: Some(vec![
+                    ToolCall {
+                        id: "call_1".to_string(),
+                        call_type: "function".to_string(),
+                        function: ToolFunction {
+                            name: "read_file".to_string(),
+                            arguments: r#"{"path":"a.rs"}"#.to_string(),
+                        },
+                    },
+                    ToolCall {
+                        id: "call_2".to_string(),
+                        call_type: "function".to_string(),
+                        function: ToolFunction {
+                            name: "list_dir".to_string(),
+                            arguments: "{}".to_string(),
+                        },
+                    },
+                ]),
+                tool_call_id: None,
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("fn main() {}".to_string()),
+                tool_call_id: Some("call_1".to_string()),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("a.rs".to_string()),
+                tool_call_id: Some("call_2".to_string()),
+                ..Default::default()
+            },
+        ];
+        let tools = vec![
+            ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Read a file".to_string(),
+                parameters: ToolParametersDefinition {
+                    param_type: "object".to_string(),
+                    properties: HashMap::from([(
+                        "path".to_string(),
+                        ToolParameter {
+                            param_type: ToolParameterType::String,
+                            description: "Path".to_string(),
+                            enum_values: None,
+                            items: None,
+                        },
+                    )]),
+                    required: vec!["path".to_string()],
+                },
+            },
+            ToolDefinition {
+                name: "list_dir".to_string(),
+                description: "List the directory".to_string(),
+                parameters: ToolParametersDefinition {
+                    param_type: "object".to_string(),
+                    properties: HashMap::new(),
+                    required: vec![],
+                },
+            },
+        ];
+
+        let payload = provider(None)
+            .build_payload(messages, Some(&tools), &RequestOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            payload["systemInstruction"],
+            json!({ "parts": [{ "text": "Be brief." }] })
+        );
+        let contents = payload["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(
+            contents[1]["parts"][0]["functionCall"],
+            json!({ "name": "read_file", "args": { "path": "a.rs" } })
+        );
+        assert_eq!(contents[2]["role"], "user");
+        assert_eq!(
+            contents[2]["parts"],
+            json!([
+                { "functionResponse": { "name": "read_file", "response": { "content": "fn main() {}" } } },
+                { "functionResponse": { "name": "list_dir", "response": { "content": "a.rs" } } }
+            ])
+        );
+
+        let declarations = payload["tools"][0]["functionDeclarations"]
+            .as_array()
+            .unwrap();
+        assert_eq!(payload["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[0]["parameters"]["required"], json!(["path"]));
+        assert!(declarations[1].get("parameters").is_none());
+    }
+
+    #[test]
+    fn test_payload_sends_image_parts_as_inline_data() {
+        let messages = vec![ChatMessage {
+            role: Role::User,
+            content: Some("Describe this diagram.".to_string()),
+            parts: vec![ContentPart::Image {
+                source: ImageSource::Base64 {
+                    media_type: "image/webp".to_string(),
+                    data: "UklGRg==".to_string(),
+                },
+            }],
+            ..Default::default()
+        }];
+
+        let payload = provider(None)
+            .build_payload(messages, None, &RequestOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            payload["contents"],
+            json!([{
+                "role": "user",
+                "parts": [
+                    { "text": "Describe this diagram." },
+                    { "inlineData": { "mimeType": "image/webp", "data": "UklGRg==" } }
+                ]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_schema_conversion_drops_unsupported_keys() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string", "default": "x", "$schema": "y" }
+                }
+            },
+            "required": []
+        });
+        assert_eq!(
+            to_gemini_schema(&schema),
+            json!({
+                "type": "object",
+                "properties": { "tags": { "type": "array", "items": { "type": "string" } } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_response_separates_thought_parts() {
+        let body = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "text": "Compare the two layouts.", "thought": true },
+                        { "text": "The sidebar is wider." }
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+        let response = provider(None).parse_response(&body.to_string()).unwrap();
+        assert_eq!(response.content, "The sidebar is wider.");
+        assert_eq!(
+            response.choices[0].message.reasoning.as_deref(),
+            Some("Compare the two layouts.")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_with_function_calls() {
+        let body = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "text": "Checking." },
+                        { "functionCall": { "name": "read_file", "args": { "path": "a.rs" } } },
+                        { "functionCall": { "name": "list_dir" } }
+                    ]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": { "promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15 }
+        });
+
+        let response = provider(None).parse_response(&body.to_string()).unwrap();
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(response.content, "Checking.");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(calls[0].function.arguments, r#"{"path":"a.rs"}"#);
+        assert_eq!(calls[1].function.arguments, "{}");
+        assert_ne!(calls[0].id, calls[1].id);
+        assert_eq!(response.total_tokens, 15);
+    }
+
+    #[test]
+    fn test_payload_maps_generation_params() {
+        let provider = GeminiProvider::new(
+            ModelConfig {
+                model_name: "gemini-test".to_string(),
+                parameters: toml::from_str(
+                    r#"
+                    temperature = 0.4
+                    max_tokens = 2048
+                    stop = ["DONE"]
+                    seed = 3
+                    generation_config = { topK = 16 }
+                    safetySettings = [{ category = "HARM_CATEGORY_HARASSMENT", threshold = "BLOCK_NONE" }]
+                    "#,
+                )
+                .unwrap(),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        );
+        let payload = provider.build_payload(Vec::new(), None, &RequestOptions::default()).unwrap();
+
+        let config = &payload["generationConfig"];
+        assert_eq!(config["temperature"], json!(0.4));
+        assert_eq!(config["maxOutputTokens"], json!(2048));
+        assert_eq!(config["stopSequences"], json!(["DONE"]));
+        assert_eq!(config["seed"], json!(3));
+        assert_eq!(config["topK"], json!(16));
+        assert_eq!(payload["safetySettings"][0]["threshold"], "BLOCK_NONE");
+        assert!(payload.get("generation_config").is_none());
+    }
+
+    #[test]
+    fn test_payload_maps_request_options() {
+        let tools = vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: HashMap::new(),
+                required: vec![],
+            },
+        }];
+        let forced = RequestOptions::default().with_tool_choice(ToolChoice::Tool("read_file".to_string()));
+        let payload = provider(None)
+            .build_payload(Vec::new(), Some(&tools), &forced)
+            .unwrap();
+        assert_eq!(
+            payload["toolConfig"]["functionCallingConfig"],
+            json!({ "mode": "ANY", "allowedFunctionNames": ["read_file"] })
+        );
+
+        let structured = RequestOptions::default().with_response_format(ResponseFormat::json_schema(
+            "plan",
+            json!({ "type": "object", "additionalProperties": false, "properties": { "goal": { "type": "string" } } }),
+        ));
+        let payload = provider(None)
+            .build_payload(Vec::new(), None, &structured)
+            .unwrap();
+        let config = &payload["generationConfig"];
+        assert_eq!(config["responseMimeType"], "application/json");
+        assert_eq!(config["responseSchema"]["properties"]["goal"]["type"], "string");
+        assert!(config["responseSchema"].get("additionalProperties").is_none());
+
+        let error = provider(None)
+            .build_payload(Vec::new(), Some(&tools), &structured)
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_keeps_chat_models() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/v1beta/models").query_param("key", "abc");
+                then.status(200).json_body(json!({
+                    "models": [
+                        {"name": "models/gemini-test", "displayName": "Gemini Test",
+                         "supportedGenerationMethods": ["generateContent", "countTokens"]},
+                        {"name": "models/text-embedding-004", "displayName": "Embedding",
+                         "supportedGenerationMethods": ["embedContent"]}
+                    ]
+                }));
+            })
+            .await;
+
+        let endpoint = server.url("/v1beta/models/gemini-test:generateContent?key=abc");
+        let models = provider(Some(endpoint)).list_models().await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(
+            models,
+            vec![ModelInfo {
+                id: "gemini-test".to_string(),
+                details: Some("Gemini Test".to_string()),
+            }]
+        );
     }
 }
@@ -0,0 +1,292 @@
+// volition-agent-core/src/providers/http.rs
+
+//! Per-provider HTTP settings from the `http` table of a `model_config`.
+//!
+//! Connection settings (connect timeout, proxy, extra root certificates and
+//! static headers) need a dedicated client, built by [`build_client`]. The read
+//! timeout is enforced by [`ReadTimeoutProvider`], because it must allow long
+//! streams that keep sending data while failing those that go quiet.
+
+use super::capabilities::ModelCapabilities;
+use super::streaming::DeltaSink;
+use super::{ModelInfo, Provider};
+use crate::config::HttpConfig;
+use crate::errors::ProviderError;
+use crate::models::chat::{ApiResponse, ChatMessage, StreamDelta};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Proxy};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Returns a client with the connection settings of `config`, or `base` if it
+/// has none.
+pub fn build_client(config: &HttpConfig, base: &Client) -> Result<Client> {
+    if config.connect_timeout_secs.is_none()
+        && config.proxy.is_none()
+        && config.ca_bundle.is_none()
+        && config.headers.is_empty()
+    {
+        return Ok(base.clone());
+    }
+
+    let mut builder = Client::builder();
+    if let Some(secs) = config.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if let Some(proxy) = &config.proxy {
+        debug!(%proxy, "Sending provider requests through proxy");
+        builder = builder
+            .proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy URL: {}", proxy))?);
+    }
+    if let Some(path) = &config.ca_bundle {
+        let pem =
+            std::fs::read(path).with_context(|| format!("Failed to read CA bundle {}", path))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid PEM certificates in CA bundle {}", path))?;
+        debug!(path = %path, count = certificates.len(), "Adding root certificates");
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if !config.headers.is_empty() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("Invalid header name: {}", name))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("Invalid value for header {}", name))?,
+            );
+        }
+        builder = builder.default_headers(headers);
+    }
+    builder.build().context("Failed to build HTTP client")
+}
+
+/// Fails requests that receive no data for `timeout`: a complete response
+/// must arrive within it, and a stream must not pause for longer.
+pub struct ReadTimeoutProvider {
+    inner: Box<dyn Provider>,
+    timeout: Duration,
+}
+
+impl ReadTimeoutProvider {
+    pub fn new(inner: Box<dyn Provider>, timeout: Duration) -> Self {
+        debug!(
+            provider = inner.name(),
+            timeout_secs = timeout.as_secs_f64(),
+            "Applying read timeout"
+        );
+        Self { inner, timeout }
+    }
+
+    fn timeout_error(&self) -> anyhow::Error {
+        ProviderError::Transport(format!(
+            "No data from model {} for {}s (read_timeout_secs)",
+            self.inner.name(),
+            self.timeout.as_secs_f64()
+        ))
+        .into()
+    }
+}
+
+#[async_trait]
+impl Provider for ReadTimeoutProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        tokio::time::timeout(self.timeout, self.inner.list_models())
+            .await
+            .map_err(|_| self.timeout_error())?
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        tokio::time::timeout(
+            self.timeout,
+            self.inner.get_completion(messages, tools, options),
+        )
+        .await
+        .map_err(|_| self.timeout_error())?
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let last_data = Mutex::new(Instant::now());
+        let sink = |delta: StreamDelta| {
+            *last_data.lock().unwrap() = Instant::now();
+            on_delta(delta);
+        };
+        let completion = self
+            .inner
+            .stream_completion(messages, tools, options, &sink);
+        tokio::pin!(completion);
+        loop {
+            let deadline = *last_data.lock().unwrap() + self.timeout;
+            tokio::select! {
+                result = &mut completion => return result,
+                _ = tokio::time::sleep_until(deadline) => {
+                    if last_data.lock().unwrap().elapsed() >= self.timeout {
+                        return Err(self.timeout_error());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{Choice, Role};
+    use httpmock::prelude::*;
+    use std::collections::HashMap;
+
+    /// Emits `deltas` text deltas, `pause` apart, before responding.
+    struct SlowProvider {
+        pause: Duration,
+        deltas: usize,
+    }
+
+    #[async_trait]
+    impl Provider for SlowProvider {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        async fn get_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            tokio::time::sleep(self.pause * self.deltas as u32).await;
+            Ok(response())
+        }
+
+        async fn stream_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+            on_delta: DeltaSink<'_>,
+        ) -> Result<ApiResponse> {
+            for _ in 0..self.deltas {
+                tokio::time::sleep(self.pause).await;
+                on_delta(StreamDelta::Text("x".to_string()));
+            }
+            Ok(response())
+        }
+    }
+
+    fn response() -> ApiResponse {
+        ApiResponse {
+            id: "slow".to_string(),
+            content: "done".to_string(),
+            finish_reason: "stop".to_string(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some("done".to_string()),
+                    ..Default::default()
+                },
+                finish_reason: "stop".to_string(),
+            }],
+        }
+    }
+
+    fn with_timeout(pause_ms: u64, deltas: usize, timeout_ms: u64) -> ReadTimeoutProvider {
+        ReadTimeoutProvider::new(
+            Box::new(SlowProvider {
+                pause: Duration::from_millis(pause_ms),
+                deltas,
+            }),
+            Duration::from_millis(timeout_ms),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout_allows_active_streams() {
+        let options = RequestOptions::default();
+        // Ten deltas 20ms apart take longer than the timeout, but never pause that long.
+        let provider = with_timeout(20, 10, 100);
+        let response = provider
+            .stream_completion(Vec::new(), None, &options, &|_| {})
+            .await
+            .unwrap();
+        assert_eq!(response.content, "done");
+
+        let error = with_timeout(150, 2, 100)
+            .stream_completion(Vec::new(), None, &options, &|_| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::Transport(_))
+        ));
+
+        let error = provider
+            .get_completion(Vec::new(), None, &options)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("read_timeout_secs"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_client_sends_configured_headers() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/")
+                    .header("x-gateway-token", "secret");
+                then.status(200);
+            })
+            .await;
+
+        let config = HttpConfig {
+            connect_timeout_secs: Some(5),
+            headers: HashMap::from([("X-Gateway-Token".to_string(), "secret".to_string())]),
+            ..Default::default()
+        };
+        let client = build_client(&config, &Client::new()).unwrap();
+        client.get(server.url("/")).send().await.unwrap();
+        mock.assert_async().await;
+
+        let config = HttpConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        };
+        let error = build_client(&config, &Client::new()).unwrap_err();
+        assert!(
+            error.to_string().contains("Failed to read CA bundle"),
+            "{}",
+            error
+        );
+    }
+}
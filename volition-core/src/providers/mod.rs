@@ -1,24 +1,79 @@
 // volition-agent-core/src/providers/mod.rs
-use crate::models::chat::{ApiResponse, ChatMessage};
-use crate::models::tools::ToolDefinition; // Import ToolDefinition
+use crate::errors::ProviderError;
+use crate::models::chat::{ApiResponse, ChatMessage, Role};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{
+    ToolDefinition, ToolParameter, ToolParameterType, ToolParametersDefinition,
+};
+use capabilities::ModelCapabilities;
+use streaming::DeltaSink;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use reqwest::Response;
+use reqwest::header::RETRY_AFTER;
+use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
 
 #[async_trait]
 pub trait Provider: Send + Sync {
-    // Add tools argument
+    /// Requests a completion. Implementations must honor `options` or fail with
+    /// [`ProviderError::Unsupported`].
     async fn get_completion(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>, // Add tools argument
+        options: &RequestOptions,
     ) -> Result<ApiResponse>;
+
+    /// Streams a completion, passing text and tool-call deltas to `on_delta` as they
+    /// arrive, and returns the assembled response once the stream ends.
+    ///
+    /// Providers without streaming support fall back to [`Provider::get_completion`]
+    /// and emit no deltas.
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let _ = on_delta;
+        self.get_completion(messages, tools, options).await
+    }
+
     fn name(&self) -> &str;
+
+    /// What the model behind this provider supports.
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::default()
+    }
+
+    /// Lists the models the provider's server offers, sorted by ID. Providers
+    /// without a model listing fail with [`ProviderError::Unsupported`].
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Err(ProviderError::Unsupported(format!(
+            "Listing models is not supported for model {}",
+            self.name()
+        ))
+        .into())
+    }
+}
+
+/// A model offered by a provider's server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// The name to use as `model_name`.
+    pub id: String,
+    /// Size, owner or display name, as reported by the server.
+    pub details: Option<String>,
 }
 
 // Add ProviderRegistry back
 pub struct ProviderRegistry {
-    providers: HashMap<String, Box<dyn Provider>>,
+    providers: HashMap<String, Arc<dyn Provider>>,
     default_provider: String,
 }
 
@@ -31,13 +86,18 @@ impl ProviderRegistry {
     }
 
     pub fn register(&mut self, id: String, provider: Box<dyn Provider>) {
-        self.providers.insert(id, provider);
+        self.providers.insert(id, Arc::from(provider));
     }
 
     pub fn get(&self, id: &str) -> Result<&dyn Provider> {
+        self.get_shared(id).map(|p| p.as_ref())
+    }
+
+    /// Like [`ProviderRegistry::get`], for providers that hand requests on to
+    /// other registered providers.
+    pub fn get_shared(&self, id: &str) -> Result<&Arc<dyn Provider>> {
         self.providers
             .get(id)
-            .map(|p| p.as_ref())
             .ok_or_else(|| anyhow!("Provider not found: {}", id))
     }
 
@@ -50,6 +110,128 @@ impl ProviderRegistry {
     }
 }
 
+/// Name of the tool offered by [`probe_tool_calling`].
+pub const PROBE_TOOL: &str = "ping";
+
+/// Sends a minimal request that offers a single tool and asks the model to call
+/// it, returning whether the response contains that tool call. Probe a provider
+/// from [`ProviderFactoryRegistry::create_provider`]: the wrappers of a built
+/// registry may describe the tool in the prompt instead of offering it natively.
+pub async fn probe_tool_calling(provider: &dyn Provider) -> Result<bool> {
+    let tool = ToolDefinition {
+        name: PROBE_TOOL.to_string(),
+        description: "Confirms that tool calls work.".to_string(),
+        parameters: ToolParametersDefinition {
+            param_type: "object".to_string(),
+            properties: HashMap::from([(
+                "status".to_string(),
+                ToolParameter {
+                    param_type: ToolParameterType::String,
+                    description: "Always \"ok\".".to_string(),
+                    enum_values: None,
+                    items: None,
+                },
+            )]),
+            required: vec!["status".to_string()],
+        },
+    };
+    let messages = vec![ChatMessage {
+        role: Role::User,
+        content: Some(format!(
+            "Call the {} tool with status \"ok\". Do not answer with text.",
+            PROBE_TOOL
+        )),
+        ..Default::default()
+    }];
+    debug!(provider = provider.name(), "Probing tool calling");
+    let response = provider
+        .get_completion(messages, Some(&[tool]), &RequestOptions::default())
+        .await?;
+    Ok(response.choices.iter().any(|choice| {
+        choice
+            .message
+            .tool_calls
+            .iter()
+            .flatten()
+            .any(|call| call.function.name == PROBE_TOOL)
+    }))
+}
+
+/// Passes successful responses through and turns the rest into a classified
+/// [`ProviderError`], honoring a numeric `Retry-After` header.
+pub(crate) async fn check_response(response: Response) -> Result<Response, ProviderError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    Err(ProviderError::from_status(status.as_u16(), retry_after, &body))
+}
+
+/// Rejects a `tool_choice` that cannot be satisfied by the offered tools: a
+/// required or named tool call without tools, or a name that is not offered.
+pub(crate) fn check_tool_choice(
+    options: &RequestOptions,
+    tools: Option<&[ToolDefinition]>,
+) -> Result<(), ProviderError> {
+    let tools = tools.unwrap_or_default();
+    match &options.tool_choice {
+        Some(ToolChoice::Required) if tools.is_empty() => Err(ProviderError::Unsupported(
+            "tool_choice = required, but no tools were offered".to_string(),
+        )),
+        Some(ToolChoice::Tool(name)) if !tools.iter().any(|t| &t.name == name) => {
+            Err(ProviderError::Unsupported(format!(
+                "tool_choice names tool '{}', which was not offered",
+                name
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Merges the provider-specific `extra` generation parameters into a request
+/// body. Tables are merged key by key, so `options = { top_k = 40 }` extends an
+/// existing `options` object; any other value replaces what is there.
+pub(crate) fn merge_extra_params(payload: &mut Value, extra: &toml::value::Table) {
+    for (key, value) in extra {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        merge_value(&mut payload[key.as_str()], value);
+    }
+}
+
+fn merge_value(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => {
+            for (key, value) in value {
+                merge_value(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, value) => *target = value,
+    }
+}
+
+pub mod anthropic;
+pub mod azure_openai;
+pub mod cache;
+pub mod capabilities;
+pub mod cassette;
+pub mod continuation;
+pub mod factory;
 pub mod gemini;
+pub mod http;
 pub mod ollama;
 pub mod openai; // Added OpenAI module
+pub mod openai_compatible;
+pub mod reasoning;
+pub mod router;
+pub mod scripted;
+pub mod streaming;
+pub mod text_tools;
+
+pub use factory::{ProviderFactory, ProviderFactoryRegistry};
@@ -1,24 +1,37 @@
 // volition-agent-core/src/providers/ollama.rs
-use super::Provider;
+use super::{ModelInfo, Provider, check_response, check_tool_choice, merge_extra_params};
+use super::capabilities::ModelCapabilities;
+use super::reasoning::split_reasoning;
+use super::cassette::{Wire, normalize_request};
+use super::streaming::{DeltaSink, StreamAccumulator};
 use crate::config::ModelConfig;
-use crate::models::chat::{ApiResponse, ChatMessage, Choice};
-use crate::models::tools::ToolDefinition;
-use anyhow::{Result, anyhow, Context};
+use crate::errors::ProviderError;
+use crate::models::chat::{
+    ApiResponse, ChatMessage, Choice, Role, StreamDelta, split_tool_results,
+};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::{Result, Context};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use tracing::debug;
 
+const DEFAULT_OLLAMA_ENDPOINT: &str = "http://127.0.0.1:11434/api/chat";
+
 #[derive(Clone)]
 pub struct OllamaProvider {
     config: ModelConfig,
     http_client: Client,
+    wire: Wire,
 }
 
 impl OllamaProvider {
     pub fn new(config: ModelConfig, http_client: Client, _api_key: String) -> Self {
         debug!("Creating new Ollama provider with model: {}", config.model_name);
         Self {
+            wire: Wire::from_config(&config),
             config,
             http_client,
         }
@@ -28,6 +41,7 @@ impl OllamaProvider {
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
     ) -> Result<Value> {
         debug!("Building Ollama payload...");
         debug!("Model name: {}", self.config.model_name);
@@ -36,46 +50,82 @@ impl OllamaProvider {
 
         let mut payload = json!({
             "model": self.config.model_name,
-            "messages": messages.iter().map(|msg| {
-                json!({
-                    "role": msg.role,
-                    "content": msg.content.as_deref().unwrap_or_default()
-                })
-            }).collect::<Vec<_>>()
+            "messages": convert_messages(&messages)?,
         });
 
         // Add tools if present
-        if let Some(tools) = tools {
-            if !tools.is_empty() {
-                debug!("Adding tools to payload for model {}", self.config.model_name);
-                let tools_with_type: Vec<Value> = tools
-                    .iter()
-                    .map(|t| {
-                        json!({
-                            "type": "function",
-                            "function": {
-                                "name": t.name,
-                                "description": t.description,
-                                "parameters": t.parameters
-                            }
-                        })
+        if let Some(tools) = tools
+            && !tools.is_empty()
+        {
+            debug!("Adding tools to payload for model {}", self.config.model_name);
+            let tools_with_type: Vec<Value> = tools
+                .iter()
+                .map(|t| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": t.name,
+                            "description": t.description,
+                            "parameters": t.parameters
+                        }
                     })
-                    .collect();
-                payload["tools"] = json!(tools_with_type);
-            }
+                })
+                .collect();
+            payload["tools"] = json!(tools_with_type);
         }
 
-        // Add parameters if present
-        if let Some(params) = &self.config.parameters {
-            debug!("Adding parameters to payload");
-            if let Some(temperature) = params.get("temperature").and_then(|t| t.as_float()) {
-                payload["temperature"] = json!(temperature);
-                debug!("Added temperature: {}", temperature);
+        // Add request options. Ollama has no tool_choice, so only "auto" and "none" can be honored.
+        check_tool_choice(options, tools)?;
+        match &options.tool_choice {
+            Some(ToolChoice::None) => {
+                if let Some(payload) = payload.as_object_mut() {
+                    payload.remove("tools");
+                }
+            }
+            Some(choice @ (ToolChoice::Required | ToolChoice::Tool(_))) => {
+                return Err(ProviderError::Unsupported(format!(
+                    "Ollama cannot force tool_choice = {}",
+                    choice
+                ))
+                .into());
             }
-            // Add other Ollama-specific parameters here if needed
+            Some(ToolChoice::Auto) | None => {}
+        }
+        if let Some(format) = &options.response_format {
+            payload["format"] = format.schema.clone();
         }
 
-        // Always set stream to false to disable streaming
+        // Add generation parameters. Ollama takes sampling settings under `options`.
+        let params = &self.config.parameters;
+        let mut options = json!({});
+        if let Some(temperature) = params.temperature {
+            options["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            options["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            options["num_predict"] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            options["stop"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            options["seed"] = json!(seed);
+        }
+        if let Some(num_ctx) = params.num_ctx {
+            options["num_ctx"] = json!(num_ctx);
+        }
+        if options.as_object().is_some_and(|o| !o.is_empty()) {
+            debug!("Adding options: {}", options);
+            payload["options"] = options;
+        }
+        if let Some(keep_alive) = &params.keep_alive {
+            payload["keep_alive"] = json!(keep_alive);
+        }
+        merge_extra_params(&mut payload, &params.extra);
+
+        // Streaming callers switch this on after building the payload
         payload["stream"] = json!(false);
 
         debug!("Final payload: {}", serde_json::to_string_pretty(&payload)?);
@@ -86,33 +136,59 @@ impl OllamaProvider {
         debug!("Parsing Ollama response...");
         debug!("Response body: {}", response_body);
 
-        let raw_response: Value = serde_json::from_str(response_body)?;
-        
-        let content = raw_response["message"]["content"]
-            .as_str()
-            .ok_or_else(|| anyhow!("Missing content in Ollama response"))?
-            .to_string();
+        let raw_response: Value =
+            serde_json::from_str(response_body).map_err(ProviderError::from)?;
+        if raw_response["error"].is_string() {
+            return Err(ProviderError::from_error(&raw_response["error"]))
+                .context("Ollama returned an error");
+        }
+
+        let message = &raw_response["message"];
+        let content = message["content"].as_str().ok_or_else(|| {
+            ProviderError::MalformedResponse("Missing content in Ollama response".to_string())
+        })?;
+        // Thinking models return reasoning in `thinking` when `think` is enabled,
+        // and inline in a leading `<think>` block otherwise.
+        let (reasoning, content) = split_reasoning(message["thinking"].as_str(), content, true);
         debug!("Extracted content: {}", content);
 
+        let tool_calls: Vec<ToolCall> = message["tool_calls"]
+            .as_array()
+            .map(|calls| calls.iter().filter_map(parse_tool_call).collect())
+            .unwrap_or_default();
+        debug!("Extracted {} tool call(s)", tool_calls.len());
+
+        let finish_reason =
+            map_done_reason(raw_response["done_reason"].as_str(), !tool_calls.is_empty());
+        let prompt_tokens = raw_response["prompt_eval_count"].as_u64().unwrap_or(0) as u32;
+        let completion_tokens = raw_response["eval_count"].as_u64().unwrap_or(0) as u32;
+        debug!("Token usage - prompt: {}, completion: {}", prompt_tokens, completion_tokens);
+
         let result = ApiResponse {
             id: raw_response["model"]
                 .as_str()
                 .map(|s| s.to_string())
                 .unwrap_or_default(),
             content: content.clone(),
-            finish_reason: "stop".to_string(), // Ollama doesn't provide this
-            prompt_tokens: 0, // Ollama doesn't provide token counts
-            completion_tokens: 0,
-            total_tokens: 0,
+            finish_reason: finish_reason.clone(),
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
             choices: vec![Choice {
                 index: 0,
                 message: ChatMessage {
-                    role: "assistant".to_string(),
+                    role: Role::Assistant,
                     content: Some(content),
-                    tool_calls: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
                     tool_call_id: None,
+                    reasoning,
+                    ..Default::default()
                 },
-                finish_reason: "stop".to_string(),
+                finish_reason,
             }],
         };
         
@@ -120,47 +196,623 @@ impl OllamaProvider {
         Ok(result)
     }
 
-    async fn call_chat_completion_api(
+    /// Applies one NDJSON line from a streamed /api/chat response.
+    fn parse_stream_line(
         &self,
-        messages: Vec<ChatMessage>,
-        tools: Option<&[ToolDefinition]>,
-    ) -> Result<ApiResponse> {
-        let endpoint = self.config.endpoint.as_deref().unwrap_or("http://127.0.0.1:11434/api/chat");
-        debug!("Using Ollama endpoint: {}", endpoint);
+        line: &str,
+        acc: &mut StreamAccumulator,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<()> {
+        let chunk: Value = serde_json::from_str(line)
+            .map_err(ProviderError::from)
+            .with_context(|| format!("Invalid JSON in Ollama stream line: {}", line))?;
+        if chunk["error"].is_string() {
+            return Err(ProviderError::from_error(&chunk["error"]))
+                .context("Ollama returned an error");
+        }
+        if let Some(model) = chunk["model"].as_str() {
+            acc.id = model.to_string();
+        }
+        if let Some(thinking) = chunk["message"]["thinking"].as_str() {
+            acc.push(StreamDelta::Reasoning(thinking.to_string()), on_delta);
+        }
+        if let Some(text) = chunk["message"]["content"].as_str() {
+            acc.push(StreamDelta::Text(text.to_string()), on_delta);
+        }
+        // Ollama sends each tool call whole rather than in fragments.
+        if let Some(calls) = chunk["message"]["tool_calls"].as_array() {
+            for call in calls.iter().filter_map(parse_tool_call) {
+                acc.push(
+                    StreamDelta::ToolCall {
+                        index: acc.tool_call_count(),
+                        id: Some(call.id),
+                        name: Some(call.function.name),
+                        arguments: call.function.arguments,
+                    },
+                    on_delta,
+                );
+            }
+        }
+        if chunk["done"].as_bool().unwrap_or(false) {
+            acc.finish_reason = Some(map_done_reason(
+                chunk["done_reason"].as_str(),
+                acc.tool_call_count() > 0,
+            ));
+            acc.prompt_tokens = chunk["prompt_eval_count"].as_u64().unwrap_or(0) as u32;
+            acc.completion_tokens = chunk["eval_count"].as_u64().unwrap_or(0) as u32;
+        }
+        Ok(())
+    }
 
-        let payload = self.build_payload(messages, tools)?;
+    async fn send_request(&self, payload: &Value) -> Result<reqwest::Response> {
+        let endpoint = self.config.endpoint.as_deref().unwrap_or(DEFAULT_OLLAMA_ENDPOINT);
+        debug!("Using Ollama endpoint: {}", endpoint);
 
         debug!("Sending request to Ollama API...");
         let response = self
             .http_client
             .post(endpoint)
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await
+            .map_err(ProviderError::from)
             .context("Failed to send request to Ollama API")?;
+        Ok(check_response(response).await?)
+    }
+
+    async fn stream_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let mut payload = self.build_payload(messages, tools, options)?;
+        payload["stream"] = json!(true);
+
+        let mut acc = StreamAccumulator::with_think_tags();
+        self.wire
+            .lines(request, &payload, self.send_request(&payload), |line| {
+                self.parse_stream_line(line, &mut acc, on_delta)
+            })
+            .await
+            .context("Failed to read streamed response from Ollama API")?;
+        Ok(acc.finish(on_delta))
+    }
 
-        debug!("Received response from Ollama API, status: {}", response.status());
-        let response_body = response
-            .text()
+    async fn call_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let payload = self.build_payload(messages, tools, options)?;
+        let response_body = self
+            .wire
+            .body(request, &payload, self.send_request(&payload))
             .await
             .context("Failed to read response from Ollama API")?;
+        debug!("Received response from Ollama API");
 
         self.parse_response(&response_body)
     }
 }
 
+/// Converts chat history into /api/chat messages.
+///
+/// Ollama expects tool call arguments as JSON objects and identifies tool results
+/// by `tool_name`, so the name is looked up from the assistant message that made the call.
+/// Images are sent as a list of base64 strings in `images`.
+fn convert_messages(messages: &[ChatMessage]) -> Result<Vec<Value>> {
+    let messages = split_tool_results(messages);
+    let mut call_names: HashMap<&str, &str> = HashMap::new();
+    messages
+        .iter()
+        .map(|msg| {
+            let mut converted = json!({
+                "role": msg.role,
+                "content": msg.text()
+            });
+            let images = msg
+                .images()
+                .map(|image| image.to_base64().map(|(_, data)| data))
+                .collect::<Result<Vec<_>>>()?;
+            if !images.is_empty() {
+                converted["images"] = json!(images);
+            }
+            if let Some(tool_calls) = &msg.tool_calls {
+                converted["tool_calls"] = tool_calls
+                    .iter()
+                    .map(|call| {
+                        call_names.insert(&call.id, &call.function.name);
+                        let arguments: Value =
+                            serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": {
+                                "name": call.function.name,
+                                "arguments": arguments
+                            }
+                        })
+                    })
+                    .collect();
+            }
+            if let Some(tool_call_id) = &msg.tool_call_id {
+                converted["tool_call_id"] = json!(tool_call_id);
+                if let Some(name) = call_names.get(tool_call_id.as_str()) {
+                    converted["tool_name"] = json!(name);
+                }
+            }
+            Ok(converted)
+        })
+        .collect()
+}
+
+/// Parses one entry of `message.tool_calls`, generating an ID when Ollama omits one.
+fn parse_tool_call(call: &Value) -> Option<ToolCall> {
+    let function = &call["function"];
+    let name = function["name"].as_str()?;
+    let arguments = match &function["arguments"] {
+        Value::String(s) => s.clone(),
+        Value::Null => "{}".to_string(),
+        other => other.to_string(),
+    };
+    Some(ToolCall {
+        id: call["id"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+        call_type: "function".to_string(),
+        function: ToolFunction {
+            name: name.to_string(),
+            arguments,
+        },
+    })
+}
+
+/// Maps Ollama's `done_reason` onto the OpenAI-style finish reasons used elsewhere.
+fn map_done_reason(done_reason: Option<&str>, has_tool_calls: bool) -> String {
+    if has_tool_calls {
+        return "tool_calls".to_string();
+    }
+    match done_reason {
+        Some("length") => "length",
+        Some(other) if !other.is_empty() && other != "stop" => other,
+        _ => "stop",
+    }
+    .to_string()
+}
+
+/// The `/api/tags` URL next to an `.../api/chat` endpoint, keeping any path prefix.
+fn tags_endpoint(endpoint: &str) -> Result<url::Url> {
+    let mut url = url::Url::parse(endpoint)
+        .with_context(|| format!("Invalid Ollama endpoint URL: {}", endpoint))?;
+    let path = url.path().trim_end_matches('/');
+    let base = path.strip_suffix("/api/chat").unwrap_or(path);
+    url.set_path(&format!("{}/api/tags", base));
+    Ok(url)
+}
+
 #[async_trait]
 impl Provider for OllamaProvider {
     fn name(&self) -> &str {
         &self.config.model_name
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        // Ollama truncates prompts to `num_ctx`, whatever the model could take.
+        let mut capabilities = ModelCapabilities::for_model(&self.config.model_name);
+        if let Some(num_ctx) = self.config.parameters.num_ctx {
+            capabilities.context_window = Some(num_ctx);
+        }
+        capabilities.with_overrides(&self.config.capabilities)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let endpoint = self.config.endpoint.as_deref().unwrap_or(DEFAULT_OLLAMA_ENDPOINT);
+        let url = tags_endpoint(endpoint)?;
+        debug!("Listing Ollama models from {}", url);
+        let response = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .map_err(ProviderError::from)
+            .context("Failed to list Ollama models")?;
+        let body: Value = serde_json::from_str(&check_response(response).await?.text().await?)
+            .map_err(ProviderError::from)?;
+        let tags = body["models"].as_array().ok_or_else(|| {
+            ProviderError::MalformedResponse("Ollama tag list has no 'models' array".to_string())
+        })?;
+
+        let mut models: Vec<ModelInfo> = tags
+            .iter()
+            .filter_map(|model| {
+                let details = [
+                    &model["details"]["parameter_size"],
+                    &model["details"]["quantization_level"],
+                ]
+                .iter()
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+                Some(ModelInfo {
+                    id: model["name"].as_str()?.to_string(),
+                    details: (!details.is_empty()).then_some(details),
+                })
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
     async fn get_completion(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.call_chat_completion_api(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
     ) -> Result<ApiResponse> {
-        self.call_chat_completion_api(messages, tools).await
+        self.stream_chat_completion_api(messages, tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ContentPart, ImageSource};
+    use crate::models::request::ResponseFormat;
+    use httpmock::prelude::*;
+    use std::sync::Mutex;
+
+    fn provider(endpoint: String) -> OllamaProvider {
+        OllamaProvider::new(
+            ModelConfig {
+                model_name: "qwen2.5-coder:7b".to_string(),
+                endpoint: Some(endpoint),
+                ..Default::default()
+            },
+            Client::new(),
+            String::new(),
+        )
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_stream_completion_reads_ndjson() {
+        let server = MockServer::start_async().await;
+        let body = concat!(
+            r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":"Hi"},"done":false}"#,
+            "\n",
+            r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":" there"},"done":false}"#,
+            "\n",
+            r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop"}"#,
+            "\n",
+        );
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/chat")
+                    .json_body_partial(r#"{"stream": true}"#);
+                then.status(200)
+                    .header("content-type", "application/x-ndjson")
+                    .body(body);
+            })
+            .await;
+
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = provider(server.url("/api/chat"))
+            .stream_completion(user("hello"), None, &RequestOptions::default(), &sink)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(
+            *deltas.lock().unwrap(),
+            vec![
+                StreamDelta::Text("Hi".to_string()),
+                StreamDelta::Text(" there".to_string())
+            ]
+        );
+        assert_eq!(response.content, "Hi there");
+        assert_eq!(response.finish_reason, "stop");
+    }
+
+    #[tokio::test]
+    async fn test_stream_completion_separates_think_blocks() {
+        let server = MockServer::start_async().await;
+        let body = concat!(
+            r#"{"model":"deepseek-r1:8b","message":{"role":"assistant","content":"<think>"},"done":false}"#,
+            "\n",
+            r#"{"model":"deepseek-r1:8b","message":{"role":"assistant","content":"Say hi.</th"},"done":false}"#,
+            "\n",
+            r#"{"model":"deepseek-r1:8b","message":{"role":"assistant","content":"ink>\n\nHi!"},"done":false}"#,
+            "\n",
+            r#"{"model":"deepseek-r1:8b","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop"}"#,
+            "\n",
+        );
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/api/chat");
+                then.status(200)
+                    .header("content-type", "application/x-ndjson")
+                    .body(body);
+            })
+            .await;
+
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = provider(server.url("/api/chat"))
+            .stream_completion(user("hello"), None, &RequestOptions::default(), &sink)
+            .await
+            .unwrap();
+
+        assert_eq!(deltas.lock().unwrap()[0], StreamDelta::Reasoning("Say hi.".to_string()));
+        assert_eq!(response.content, "Hi!");
+        assert_eq!(
+            response.choices[0].message.reasoning.as_deref(),
+            Some("Say hi.")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_reads_thinking_field() {
+        let response = provider(String::new())
+            .parse_response(
+                r#"{"model":"qwen3:8b","message":{"role":"assistant","content":"Hi!","thinking":"Greet them."},"done":true,"done_reason":"stop"}"#,
+            )
+            .unwrap();
+        assert_eq!(response.content, "Hi!");
+        assert_eq!(
+            response.choices[0].message.reasoning.as_deref(),
+            Some("Greet them.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_completion_parses_tool_calls_and_usage() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/api/chat");
+                then.status(200).json_body(json!({
+                    "model": "qwen2.5-coder:7b",
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [
+                            {"function": {"name": "read_file", "arguments": {"path": "a.rs"}}},
+                            {"function": {"name": "read_file", "arguments": {"path": "b.rs"}}}
+                        ]
+                    },
+                    "done": true,
+                    "done_reason": "stop",
+                    "prompt_eval_count": 42,
+                    "eval_count": 8
+                }));
+            })
+            .await;
+
+        let response = provider(server.url("/api/chat"))
+            .get_completion(user("read both"), None, &RequestOptions::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.prompt_tokens, 42);
+        assert_eq!(response.completion_tokens, 8);
+        assert_eq!(response.total_tokens, 50);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_ne!(calls[0].id, calls[1].id);
+        assert_eq!(calls[1].function.arguments, r#"{"path":"b.rs"}"#);
+    }
+
+    #[test]
+    fn test_payload_includes_tool_round_trip() {
+        let messages = vec![
+            ChatMessage {
+                role: Role::Assistant,
+                content: None,
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    call_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: "read_file".to_string(),
+                        arguments: r#"{"path":"a.rs"}"#.to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("fn main() {}".to_string()),
+                tool_call_id: Some("call_1".to_string()),
+                ..Default::default()
+            },
+        ];
+        let payload = provider("http://localhost/api/chat".to_string())
+            .build_payload(messages, None, &RequestOptions::default())
+            .unwrap();
+
+        let sent = &payload["messages"];
+        assert_eq!(sent[0]["tool_calls"][0]["function"]["arguments"]["path"], "a.rs");
+        assert_eq!(sent[1]["role"], "tool");
+        assert_eq!(sent[1]["tool_name"], "read_file");
+        assert_eq!(sent[1]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn test_payload_sends_images_as_base64_list() {
+        let messages = vec![ChatMessage {
+            role: Role::User,
+            content: Some("What does this chart show?".to_string()),
+            parts: vec![ContentPart::Image {
+                source: ImageSource::Base64 {
+                    media_type: "image/png".to_string(),
+                    data: "iVBORw0KGgo=".to_string(),
+                },
+            }],
+            ..Default::default()
+        }];
+
+        let payload = provider(String::new())
+            .build_payload(messages, None, &RequestOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            payload["messages"],
+            json!([{
+                "role": "user",
+                "content": "What does this chart show?",
+                "images": ["iVBORw0KGgo="]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_map_done_reason() {
+        assert_eq!(map_done_reason(Some("length"), false), "length");
+        assert_eq!(map_done_reason(Some("stop"), true), "tool_calls");
+        assert_eq!(map_done_reason(None, false), "stop");
+    }
+
+    #[test]
+    fn test_payload_maps_generation_params() {
+        let provider = OllamaProvider::new(
+            ModelConfig {
+                model_name: "qwen2.5-coder:7b".to_string(),
+                parameters: toml::from_str(
+                    r#"
+                    temperature = 0.1
+                    max_tokens = 512
+                    num_ctx = 32768
+                    keep_alive = "10m"
+                    options = { top_k = 40 }
+                    "#,
+                )
+                .unwrap(),
+                ..Default::default()
+            },
+            Client::new(),
+            String::new(),
+        );
+        let payload = provider.build_payload(user("hi"), None, &RequestOptions::default()).unwrap();
+
+        let options = &payload["options"];
+        assert_eq!(options["temperature"], json!(0.1));
+        assert_eq!(options["num_predict"], json!(512));
+        assert_eq!(options["num_ctx"], json!(32768));
+        assert_eq!(options["top_k"], json!(40));
+        assert_eq!(payload["keep_alive"], "10m");
+        assert!(payload.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_payload_maps_request_options() {
+        let tools = vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: crate::models::tools::ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: Default::default(),
+                required: vec![],
+            },
+        }];
+        let options = RequestOptions::default()
+            .with_tool_choice(ToolChoice::None)
+            .with_response_format(ResponseFormat::json_schema("answer", json!({ "type": "object" })));
+        let payload = provider(String::new())
+            .build_payload(user("hi"), Some(&tools), &options)
+            .unwrap();
+        assert_eq!(payload["format"], json!({ "type": "object" }));
+        assert!(payload.get("tools").is_none());
+
+        let error = provider(String::new())
+            .build_payload(
+                user("hi"),
+                Some(&tools),
+                &RequestOptions::default().with_tool_choice(ToolChoice::Required),
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("cannot force tool_choice = required"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_reads_tags() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/tags");
+                then.status(200).json_body(json!({
+                    "models": [
+                        {"name": "qwen2.5-coder:7b", "details": {"parameter_size": "7.6B", "quantization_level": "Q4_K_M"}},
+                        {"name": "llama3:latest", "details": {}}
+                    ]
+                }));
+            })
+            .await;
+
+        let models = provider(server.url("/api/chat")).list_models().await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(
+            models,
+            vec![
+                ModelInfo {
+                    id: "llama3:latest".to_string(),
+                    details: None,
+                },
+                ModelInfo {
+                    id: "qwen2.5-coder:7b".to_string(),
+                    details: Some("7.6B Q4_K_M".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_models_keeps_endpoint_path_prefix() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/ollama/api/tags");
+                then.status(200).json_body(json!({"models": [{"name": "llama3:latest"}]}));
+            })
+            .await;
+
+        let models = provider(server.url("/ollama/api/chat"))
+            .list_models()
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert_eq!(models[0].id, "llama3:latest");
+        assert_eq!(
+            tags_endpoint("https://host/ollama/api/chat").unwrap().as_str(),
+            "https://host/ollama/api/tags"
+        );
     }
 }
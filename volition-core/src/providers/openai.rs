@@ -1,9 +1,17 @@
 // volition-agent-core/src/providers/openai.rs
-use super::Provider;
+use super::{ModelInfo, Provider, check_response, check_tool_choice, merge_extra_params};
+use super::capabilities::ModelCapabilities;
+use super::cassette::{Wire, normalize_request};
+use super::reasoning::split_reasoning;
+use super::streaming::{DeltaSink, StreamAccumulator, sse_data};
 use crate::config::ModelConfig;
-use crate::models::chat::{ApiResponse, ChatMessage, Choice};
-use crate::models::tools::ToolDefinition;
-use anyhow::{Result, anyhow, Context};
+use crate::errors::ProviderError;
+use crate::models::chat::{
+    ApiResponse, ChatMessage, Choice, Role, StreamDelta, split_tool_results,
+};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::{Result, Context};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
@@ -11,19 +19,67 @@ use tracing::{debug, warn};
 
 const DEFAULT_OPENAI_ENDPOINT: &str = "https://api.openai.com/v1/chat/completions";
 
+/// Differences between servers that speak the Chat Completions dialect.
+#[derive(Clone, Debug)]
+pub(crate) struct Dialect {
+    /// Name used in log and error messages.
+    pub label: &'static str,
+    pub endpoint: String,
+    /// Header carrying the API key, if the server needs one.
+    pub auth_header: Option<(String, String)>,
+    /// Extra headers sent with every request.
+    pub headers: Vec<(String, String)>,
+    /// Whether the `tools` array may be sent.
+    pub supports_tools: bool,
+    /// Whether `stream_options.include_usage` may be sent when streaming.
+    pub supports_stream_usage: bool,
+    /// Response field holding the token counts.
+    pub usage_field: String,
+    /// Whether reasoning may arrive inline as a leading `<think>` block, as
+    /// from local servers, rather than only in `reasoning_content`.
+    pub think_tags: bool,
+}
+
 #[derive(Clone)]
 pub struct OpenAIProvider {
     config: ModelConfig,
     http_client: Client,
-    api_key: String,
+    dialect: Dialect,
+    wire: Wire,
 }
 
 impl OpenAIProvider {
     pub fn new(config: ModelConfig, http_client: Client, api_key: String) -> Self {
+        if api_key.is_empty() {
+            warn!(
+                "API key is empty for OpenAI provider model {}. API calls will likely fail.",
+                config.model_name
+            );
+        }
+        let endpoint = config.endpoint.clone().unwrap_or_else(|| {
+            warn!("No endpoint specified for OpenAI provider model {}, using default: {}", config.model_name, DEFAULT_OPENAI_ENDPOINT);
+            DEFAULT_OPENAI_ENDPOINT.to_string()
+        });
+        let dialect = Dialect {
+            label: "OpenAI",
+            endpoint,
+            auth_header: Some(("Authorization".to_string(), format!("Bearer {}", api_key))),
+            headers: Vec::new(),
+            supports_tools: true,
+            supports_stream_usage: true,
+            usage_field: "usage".to_string(),
+            think_tags: false,
+        };
+        Self::with_dialect(config, http_client, dialect)
+    }
+
+    /// Creates a provider for another server speaking the Chat Completions dialect.
+    pub(crate) fn with_dialect(config: ModelConfig, http_client: Client, dialect: Dialect) -> Self {
         Self {
+            wire: Wire::from_config(&config),
             config,
             http_client,
-            api_key,
+            dialect,
         }
     }
 
@@ -31,96 +87,138 @@ impl OpenAIProvider {
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
     ) -> Result<Value> {
-        debug!("Building OpenAI payload...");
+        debug!("Building {} payload...", self.dialect.label);
         debug!("Model name: {}", self.config.model_name);
         debug!("Message count: {}", messages.len());
 
         let mut payload = json!({
             "model": self.config.model_name,
-            "messages": messages.iter().map(|msg| {
-                json!({
-                    "role": msg.role,
-                    "content": msg.content.as_deref().unwrap_or_default()
-                })
-            }).collect::<Vec<_>>()
+            "messages": convert_messages(&messages)?,
         });
 
         // Add tools if present
-        if let Some(tools) = tools {
-            if !tools.is_empty() {
-                let functions: Vec<Value> = tools
-                    .iter()
-                    .map(|t| {
-                        json!({
-                            "name": t.name,
-                            "description": t.description,
-                            "parameters": t.parameters
-                        })
-                    })
-                    .collect();
-                payload["functions"] = json!(functions);
-                payload["function_call"] = json!("auto");
+        if let Some(tools) = tools
+            && !tools.is_empty()
+        {
+            if self.dialect.supports_tools {
+                payload["tools"] = json!(convert_tools(tools));
+                payload["tool_choice"] = json!("auto");
+            } else {
+                warn!(
+                    "{} server for model {} does not support tools; {} tool definition(s) not sent. Consider `tool_calling = \"json\"`.",
+                    self.dialect.label,
+                    self.config.model_name,
+                    tools.len()
+                );
             }
         }
 
-        // Add parameters if present
-        if let Some(params) = &self.config.parameters {
-            if let Some(temperature) = params.get("temperature").and_then(|t| t.as_float()) {
-                payload["temperature"] = json!(temperature);
+        // Add request options
+        check_tool_choice(options, tools)?;
+        if let Some(choice) = &options.tool_choice {
+            if payload.get("tools").is_some() {
+                payload["tool_choice"] = convert_tool_choice(choice);
+            } else if matches!(choice, ToolChoice::Required | ToolChoice::Tool(_)) {
+                return Err(ProviderError::Unsupported(format!(
+                    "{} server for model {} does not support tools, so tool_choice = {} cannot be honored",
+                    self.dialect.label, self.config.model_name, choice
+                ))
+                .into());
             }
-            // Add other OpenAI-specific parameters here if needed
         }
+        if let Some(format) = &options.response_format {
+            payload["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": format.name, "schema": format.schema },
+            });
+        }
+
+        // Add generation parameters
+        let params = &self.config.parameters;
+        if let Some(temperature) = params.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            payload["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            payload["max_tokens"] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            payload["stop"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            payload["seed"] = json!(seed);
+        }
+        merge_extra_params(&mut payload, &params.extra);
 
         debug!("Final payload: {}", serde_json::to_string_pretty(&payload)?);
         Ok(payload)
     }
 
     fn parse_response(&self, response_body: &str) -> Result<ApiResponse> {
-        debug!("Parsing OpenAI response...");
+        debug!("Parsing {} response...", self.dialect.label);
         debug!("Response body: {}", response_body);
 
-        let raw_response: Value = serde_json::from_str(response_body)?;
-        
-        let choice = &raw_response["choices"][0];
+        let raw_response: Value =
+            serde_json::from_str(response_body).map_err(ProviderError::from)?;
+        // Some compatible servers send the error as a plain string.
+        if !raw_response["error"].is_null() {
+            return Err(ProviderError::from_error(&raw_response["error"]))
+                .with_context(|| format!("{} returned an error", self.dialect.label));
+        }
+
+        let choice = raw_response["choices"]
+            .get(0)
+            .ok_or_else(|| {
+                ProviderError::MalformedResponse(format!(
+                    "Missing choices in {} response",
+                    self.dialect.label
+                ))
+            })?;
         let message = &choice["message"];
 
-        let content = message["content"]
+        // `content` is null when the model only returns tool calls. Reasoning
+        // servers put the chain of thought in `reasoning_content` (or `reasoning`)
+        // or, for dialects that allow it, inline in a leading `<think>` block.
+        let native_reasoning = message["reasoning_content"]
             .as_str()
-            .ok_or_else(|| anyhow!("Missing content in OpenAI response"))?
-            .to_string();
+            .or_else(|| message["reasoning"].as_str());
+        let (reasoning, content) = split_reasoning(
+            native_reasoning,
+            message["content"].as_str().unwrap_or_default(),
+            self.dialect.think_tags,
+        );
         debug!("Extracted content: {}", content);
 
+        let tool_calls = parse_tool_calls(message)?;
+        debug!("Extracted {} tool call(s)", tool_calls.len());
+
         let finish_reason = choice["finish_reason"]
             .as_str()
             .unwrap_or("stop")
             .to_string();
         debug!("Finish reason: {}", finish_reason);
+        if finish_reason == "content_filter" && content.is_empty() && tool_calls.is_empty() {
+            return Err(ProviderError::ContentFiltered(format!(
+                "{} filtered the response",
+                self.dialect.label
+            ))
+            .into());
+        }
 
-        let usage = &raw_response["usage"];
+        let usage = &raw_response[self.dialect.usage_field.as_str()];
         let prompt_tokens = usage["prompt_tokens"].as_u64().unwrap_or(0) as u32;
         let completion_tokens = usage["completion_tokens"].as_u64().unwrap_or(0) as u32;
-        let total_tokens = usage["total_tokens"].as_u64().unwrap_or(0) as u32;
+        let total_tokens = usage["total_tokens"]
+            .as_u64()
+            .map(|t| t as u32)
+            .unwrap_or(prompt_tokens + completion_tokens);
         debug!("Token usage - prompt: {}, completion: {}, total: {}", 
             prompt_tokens, completion_tokens, total_tokens);
 
-        let mut tool_calls = None;
-        if let Some(function_call) = message.get("function_call") {
-            if let (Some(name), Some(arguments)) = (
-                function_call["name"].as_str(),
-                function_call["arguments"].as_str(),
-            ) {
-                tool_calls = Some(vec![crate::models::tools::ToolCall {
-                    id: format!("call_{}", name),
-                    call_type: "function".to_string(),
-                    function: crate::models::tools::ToolFunction {
-                        name: name.to_string(),
-                        arguments: arguments.to_string(),
-                    },
-                }]);
-            }
-        }
-
         let result = ApiResponse {
             id: raw_response["id"]
                 .as_str()
@@ -134,10 +232,16 @@ impl OpenAIProvider {
             choices: vec![Choice {
                 index: 0,
                 message: ChatMessage {
-                    role: "assistant".to_string(),
+                    role: Role::Assistant,
                     content: Some(content),
-                    tool_calls,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
                     tool_call_id: None,
+                    reasoning,
+                    ..Default::default()
                 },
                 finish_reason,
             }],
@@ -147,42 +251,292 @@ impl OpenAIProvider {
         Ok(result)
     }
 
-    async fn call_chat_completion_api(
+    /// Applies one SSE `data:` payload from a streamed chat completion.
+    fn parse_stream_chunk(
         &self,
-        messages: Vec<ChatMessage>,
-        tools: Option<&[ToolDefinition]>,
-    ) -> Result<ApiResponse> {
-        let endpoint = self.config.endpoint.as_deref().unwrap_or_else(|| {
-            warn!("No endpoint specified for OpenAI provider model {}, using default: {}", self.config.model_name, DEFAULT_OPENAI_ENDPOINT);
-            DEFAULT_OPENAI_ENDPOINT
-        });
+        data: &str,
+        acc: &mut StreamAccumulator,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<()> {
+        if data == "[DONE]" {
+            return Ok(());
+        }
+        let chunk: Value = serde_json::from_str(data)
+            .map_err(ProviderError::from)
+            .with_context(|| {
+                format!("Invalid JSON in {} stream chunk: {}", self.dialect.label, data)
+            })?;
 
-        if self.api_key.is_empty() {
-            warn!(
-                "API key is empty for OpenAI provider model {}. The API call will likely fail.",
-                self.config.model_name
-            );
+        if let Some(id) = chunk["id"].as_str() {
+            acc.id = id.to_string();
+        }
+        let usage = &chunk[self.dialect.usage_field.as_str()];
+        if usage.is_object() {
+            acc.prompt_tokens = usage["prompt_tokens"].as_u64().unwrap_or(0) as u32;
+            acc.completion_tokens = usage["completion_tokens"].as_u64().unwrap_or(0) as u32;
+            acc.total_tokens = usage["total_tokens"].as_u64().map(|t| t as u32);
         }
 
-        let payload = self.build_payload(messages, tools)?;
+        let choice = &chunk["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(reasoning) = delta["reasoning_content"]
+            .as_str()
+            .or_else(|| delta["reasoning"].as_str())
+        {
+            acc.push(StreamDelta::Reasoning(reasoning.to_string()), on_delta);
+        }
+        if let Some(text) = delta["content"].as_str() {
+            acc.push(StreamDelta::Text(text.to_string()), on_delta);
+        }
+        if let Some(calls) = delta["tool_calls"].as_array() {
+            for call in calls {
+                acc.push(
+                    StreamDelta::ToolCall {
+                        index: call["index"].as_u64().unwrap_or(0) as usize,
+                        id: call["id"].as_str().map(String::from),
+                        name: call["function"]["name"].as_str().map(String::from),
+                        arguments: call["function"]["arguments"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    },
+                    on_delta,
+                );
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            acc.finish_reason = Some(reason.to_string());
+        }
+        Ok(())
+    }
 
-        let response = self
+    async fn send_request(&self, payload: &Value) -> Result<reqwest::Response> {
+        debug!("Using {} endpoint: {}", self.dialect.label, self.dialect.endpoint);
+        let mut request = self
             .http_client
-            .post(endpoint)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&payload)
+            .post(&self.dialect.endpoint)
+            .header("Content-Type", "application/json");
+        if let Some((name, value)) = &self.dialect.auth_header {
+            request = request.header(name, value);
+        }
+        for (name, value) in &self.dialect.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .json(payload)
             .send()
             .await
-            .context("Failed to send request to OpenAI API")?;
+            .map_err(ProviderError::from)
+            .with_context(|| format!("Failed to send request to {} API", self.dialect.label))?;
+        Ok(check_response(response).await?)
+    }
 
-        let response_body = response
-            .text()
+    async fn call_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let payload = self.build_payload(messages, tools, options)?;
+        let response_body = self
+            .wire
+            .body(request, &payload, self.send_request(&payload))
             .await
-            .context("Failed to read response from OpenAI API")?;
+            .with_context(|| format!("Failed to read response from {} API", self.dialect.label))?;
 
         self.parse_response(&response_body)
     }
+
+    async fn stream_chat_completion_api(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let request = normalize_request(&messages, tools, options);
+        let mut payload = self.build_payload(messages, tools, options)?;
+        payload["stream"] = json!(true);
+        if self.dialect.supports_stream_usage {
+            payload["stream_options"] = json!({ "include_usage": true });
+        }
+
+        let mut acc = if self.dialect.think_tags {
+            StreamAccumulator::with_think_tags()
+        } else {
+            StreamAccumulator::default()
+        };
+        self.wire
+            .lines(
+                request,
+                &payload,
+                self.send_request(&payload),
+                |line| match sse_data(line) {
+                    Some(data) => self.parse_stream_chunk(data, &mut acc, on_delta),
+                    None => Ok(()),
+                },
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to read streamed response from {} API",
+                    self.dialect.label
+                )
+            })?;
+        Ok(acc.finish(on_delta))
+    }
+}
+
+/// Converts chat history into Chat Completions messages. Assistant tool calls are
+/// sent as `tool_calls` and tool results as `tool` messages with their `tool_call_id`.
+/// User messages with images send `content` as an array of text and `image_url`
+/// parts; the API only accepts images from the user.
+fn convert_messages(messages: &[ChatMessage]) -> Result<Vec<Value>> {
+    split_tool_results(messages)
+        .iter()
+        .map(|msg| {
+            let text = msg.text();
+            let mut converted = json!({
+                "role": msg.role,
+                "content": text,
+            });
+            if msg.images().next().is_some() {
+                if msg.role != Role::User {
+                    return Err(ProviderError::Unsupported(format!(
+                        "Chat Completions only accepts images in user messages, not {} messages",
+                        msg.role
+                    ))
+                    .into());
+                }
+                let mut content = Vec::new();
+                if !text.is_empty() {
+                    content.push(json!({ "type": "text", "text": text }));
+                }
+                for image in msg.images() {
+                    let (media_type, data) = image.to_base64()?;
+                    content.push(json!({
+                        "type": "image_url",
+                        "image_url": { "url": format!("data:{};base64,{}", media_type, data) },
+                    }));
+                }
+                converted["content"] = json!(content);
+            }
+            if let Some(calls) = &msg.tool_calls
+                && !calls.is_empty()
+            {
+                converted["tool_calls"] = json!(calls
+                    .iter()
+                    .map(|call| json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {
+                            "name": call.function.name,
+                            "arguments": call.function.arguments,
+                        }
+                    }))
+                    .collect::<Vec<_>>());
+                if text.is_empty() {
+                    converted["content"] = Value::Null;
+                }
+            }
+            if let Some(tool_call_id) = &msg.tool_call_id {
+                converted["tool_call_id"] = json!(tool_call_id);
+            }
+            Ok(converted)
+        })
+        .collect()
+}
+
+/// Converts tool definitions into the `tools` array of function tools.
+fn convert_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|t| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": t.name,
+                    "description": t.description,
+                    "parameters": t.parameters
+                }
+            })
+        })
+        .collect()
+}
+
+/// Converts a [`ToolChoice`] into the `tool_choice` request field.
+fn convert_tool_choice(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({ "type": "function", "function": { "name": name } }),
+    }
+}
+
+/// Reads the `tool_calls` array of a response message, keeping the IDs assigned by the API.
+fn parse_tool_calls(message: &Value) -> Result<Vec<ToolCall>> {
+    let Some(calls) = message["tool_calls"].as_array() else {
+        return Ok(Vec::new());
+    };
+    calls
+        .iter()
+        .map(|call| {
+            let name = call["function"]["name"]
+                .as_str()
+                .ok_or_else(|| {
+                    ProviderError::MalformedResponse(format!(
+                        "Missing function name in OpenAI tool call: {}",
+                        call
+                    ))
+                })?;
+            Ok(ToolCall {
+                id: call["id"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+                call_type: call["type"].as_str().unwrap_or("function").to_string(),
+                function: ToolFunction {
+                    name: name.to_string(),
+                    arguments: call["function"]["arguments"]
+                        .as_str()
+                        .unwrap_or("{}")
+                        .to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// The `/models` URL next to a `.../chat/completions` endpoint.
+fn models_endpoint(endpoint: &str) -> Result<String> {
+    let mut url = url::Url::parse(endpoint)
+        .with_context(|| format!("Invalid endpoint URL: {}", endpoint))?;
+    let path = url.path().trim_end_matches('/');
+    let base = path.strip_suffix("/chat/completions").unwrap_or(path);
+    url.set_path(&format!("{}/models", base));
+    Ok(url.to_string())
+}
+
+/// Parses the `data` list of a `/models` response.
+fn parse_model_list(body: &str) -> Result<Vec<ModelInfo>> {
+    let body: Value = serde_json::from_str(body).map_err(ProviderError::from)?;
+    let data = body["data"].as_array().ok_or_else(|| {
+        ProviderError::MalformedResponse("Model list has no 'data' array".to_string())
+    })?;
+    let mut models: Vec<ModelInfo> = data
+        .iter()
+        .filter_map(|model| {
+            Some(ModelInfo {
+                id: model["id"].as_str()?.to_string(),
+                details: model["owned_by"].as_str().map(String::from),
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
 }
 
 #[async_trait]
@@ -191,11 +545,486 @@ impl Provider for OpenAIProvider {
         &self.config.model_name
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        let capabilities =
+            ModelCapabilities::resolve(&self.config.model_name, &self.config.capabilities);
+        ModelCapabilities {
+            tools: capabilities.tools && self.dialect.supports_tools,
+            ..capabilities
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let endpoint = models_endpoint(&self.dialect.endpoint)?;
+        debug!("Listing {} models from {}", self.dialect.label, endpoint);
+        let mut request = self.http_client.get(&endpoint);
+        if let Some((name, value)) = &self.dialect.auth_header {
+            request = request.header(name, value);
+        }
+        for (name, value) in &self.dialect.headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(ProviderError::from)
+            .with_context(|| format!("Failed to list {} models", self.dialect.label))?;
+        let body = check_response(response).await?.text().await?;
+        parse_model_list(&body)
+    }
+
     async fn get_completion(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.call_chat_completion_api(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
     ) -> Result<ApiResponse> {
-        self.call_chat_completion_api(messages, tools).await
+        self.stream_chat_completion_api(messages, tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{ContentPart, ImageSource};
+    use crate::models::request::ResponseFormat;
+    use httpmock::prelude::*;
+    use std::sync::Mutex;
+
+    fn provider(endpoint: String) -> OpenAIProvider {
+        OpenAIProvider::new(
+            ModelConfig {
+                model_name: "gpt-test".to_string(),
+                endpoint: Some(endpoint),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_stream_completion_assembles_sse_deltas() {
+        let server = MockServer::start_async().await;
+        let body = [
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"lo"}}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_x","type":"function","function":{"name":"read_file","arguments":"{\"path\":"}}]}}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[],"usage":{"prompt_tokens":5,"completion_tokens":7,"total_tokens":12}}"#,
+            "data: [DONE]",
+        ]
+        .join("\n\n");
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/chat/completions")
+                    .json_body_partial(r#"{"stream": true}"#);
+                then.status(200)
+                    .header("content-type", "text/event-stream")
+                    .body(body);
+            })
+            .await;
+
+        let deltas = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| deltas.lock().unwrap().push(delta);
+        let response = provider(server.url("/v1/chat/completions"))
+            .stream_completion(
+                vec![ChatMessage {
+                    role: Role::User,
+                    content: Some("hi".to_string()),
+                    ..Default::default()
+                }],
+                None, &RequestOptions::default(),
+                &sink,
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        let deltas = deltas.lock().unwrap();
+        assert_eq!(deltas[0], StreamDelta::Text("Hel".to_string()));
+        assert_eq!(deltas.len(), 4);
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.total_tokens, 12);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "call_x");
+        assert_eq!(calls[0].function.arguments, "{\"path\":\"a\"}");
+    }
+
+    #[tokio::test]
+    async fn test_parses_parallel_tool_calls_with_null_content() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/v1/chat/completions");
+                then.status(200).json_body(json!({
+                    "id": "chatcmpl-2",
+                    "choices": [{
+                        "index": 0,
+                        "message": {
+                            "role": "assistant",
+                            "content": null,
+                            "tool_calls": [
+                                {
+                                    "id": "call_abc",
+                                    "type": "function",
+                                    "function": { "name": "read_file", "arguments": "{\"path\":\"a.rs\"}" }
+                                },
+                                {
+                                    "id": "call_def",
+                                    "type": "function",
+                                    "function": { "name": "read_file", "arguments": "{\"path\":\"b.rs\"}" }
+                                }
+                            ]
+                        },
+                        "finish_reason": "tool_calls"
+                    }],
+                    "usage": { "prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12 }
+                }));
+            })
+            .await;
+
+        let response = provider(server.url("/v1/chat/completions"))
+            .get_completion(
+                vec![ChatMessage {
+                    role: Role::User,
+                    content: Some("Read both".to_string()),
+                    ..Default::default()
+                }],
+                None,
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.content, "");
+        assert_eq!(response.finish_reason, "tool_calls");
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_abc");
+        assert_eq!(calls[1].id, "call_def");
+        assert_eq!(calls[1].function.arguments, "{\"path\":\"b.rs\"}");
+    }
+
+    #[test]
+    fn test_payload_sends_tool_calls_and_tool_messages() {
+        let messages = vec![
+            ChatMessage {
+                role: Role::Assistant,
+                content: None,
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_abc".to_string(),
+                    call_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: "read_file".to_string(),
+                        arguments: "{\"path\":\"a.rs\"}".to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("fn main() {}".to_string()),
+                tool_call_id: Some("call_abc".to_string()),
+                ..Default::default()
+            },
+        ];
+        let tools = vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: crate::models::tools::ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: Default::default(),
+                required: vec![],
+            },
+        }];
+
+        let payload = provider(String::new())
+            .build_payload(messages, Some(&tools), &RequestOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            payload["messages"],
+            json!([
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": { "name": "read_file", "arguments": "{\"path\":\"a.rs\"}" }
+                    }]
+                },
+                { "role": "tool", "content": "fn main() {}", "tool_call_id": "call_abc" }
+            ])
+        );
+        assert_eq!(payload["tools"][0]["type"], "function");
+        assert_eq!(payload["tools"][0]["function"]["name"], "read_file");
+        assert!(payload.get("functions").is_none());
+    }
+
+    #[test]
+    fn test_payload_sends_image_and_tool_result_parts() {
+        let messages = vec![
+            ChatMessage {
+                role: Role::User,
+                content: Some("What is wrong with this layout?".to_string()),
+                parts: vec![ContentPart::Image {
+                    source: ImageSource::Base64 {
+                        media_type: "image/png".to_string(),
+                        data: "iVBORw0KGgo=".to_string(),
+                    },
+                }],
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::User,
+                parts: vec![ContentPart::ToolResult {
+                    tool_call_id: "call_abc".to_string(),
+                    content: "ok".to_string(),
+                }],
+                ..Default::default()
+            },
+        ];
+
+        let payload = provider(String::new())
+            .build_payload(messages, None, &RequestOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            payload["messages"],
+            json!([
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "What is wrong with this layout?" },
+                        { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } }
+                    ]
+                },
+                { "role": "tool", "content": "ok", "tool_call_id": "call_abc" }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reasoning_is_parsed_and_not_sent_back() {
+        let body = json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "message": { "role": "assistant", "content": "4", "reasoning_content": "2 + 2 = 4." },
+                "finish_reason": "stop"
+            }]
+        });
+        let provider = provider(String::new());
+        let response = provider.parse_response(&body.to_string()).unwrap();
+        assert_eq!(response.content, "4");
+        let message = response.choices[0].message.clone();
+        assert_eq!(message.reasoning.as_deref(), Some("2 + 2 = 4."));
+
+        let payload = provider
+            .build_payload(vec![message], None, &RequestOptions::default())
+            .unwrap();
+        assert_eq!(payload["messages"], json!([{ "role": "assistant", "content": "4" }]));
+    }
+
+    #[test]
+    fn test_think_tags_are_left_in_the_answer() {
+        let body = json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "message": { "role": "assistant", "content": "<think>Not reasoning.</think>Hi" },
+                "finish_reason": "stop"
+            }]
+        });
+        let response = provider(String::new())
+            .parse_response(&body.to_string())
+            .unwrap();
+        assert_eq!(response.content, "<think>Not reasoning.</think>Hi");
+        assert!(response.choices[0].message.reasoning.is_none());
+    }
+
+    #[test]
+    fn test_invalid_stream_chunk_is_malformed_response() {
+        let mut acc = StreamAccumulator::default();
+        let error = provider(String::new())
+            .parse_stream_chunk("{\"id\":", &mut acc, &|_| {})
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_image_in_assistant_message_is_unsupported() {
+        let messages = vec![ChatMessage {
+            role: Role::Assistant,
+            parts: vec![ContentPart::Image {
+                source: ImageSource::Base64 {
+                    media_type: "image/png".to_string(),
+                    data: "iVBORw0KGgo=".to_string(),
+                },
+            }],
+            ..Default::default()
+        }];
+
+        let error = provider(String::new())
+            .build_payload(messages, None, &RequestOptions::default())
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_status_becomes_provider_error() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/v1/chat/completions");
+                then.status(429)
+                    .header("retry-after", "7")
+                    .json_body(json!({ "error": { "message": "Rate limit reached", "type": "requests" } }));
+            })
+            .await;
+
+        let error = provider(server.url("/v1/chat/completions"))
+            .get_completion(
+                vec![ChatMessage {
+                    role: Role::User,
+                    content: Some("hi".to_string()),
+                    ..Default::default()
+                }],
+                None,
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            ProviderError::find(error.as_ref()),
+            Some(&ProviderError::RateLimited {
+                retry_after: Some(std::time::Duration::from_secs(7)),
+                message: "Rate limit reached".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_payload_maps_generation_params() {
+        let provider = OpenAIProvider::new(
+            ModelConfig {
+                model_name: "gpt-test".to_string(),
+                parameters: toml::from_str(
+                    r#"
+                    temperature = 0.2
+                    top_p = 0.9
+                    max_tokens = 256
+                    stop = ["END"]
+                    seed = 7
+                    num_ctx = 8192
+                    frequency_penalty = 0.5
+                    "#,
+                )
+                .unwrap(),
+                ..Default::default()
+            },
+            Client::new(),
+            "test-key".to_string(),
+        );
+        let payload = provider.build_payload(Vec::new(), None, &RequestOptions::default()).unwrap();
+
+        assert_eq!(payload["temperature"], json!(0.2));
+        assert_eq!(payload["top_p"], json!(0.9));
+        assert_eq!(payload["max_tokens"], json!(256));
+        assert_eq!(payload["stop"], json!(["END"]));
+        assert_eq!(payload["seed"], json!(7));
+        assert_eq!(payload["frequency_penalty"], json!(0.5));
+        assert!(payload.get("num_ctx").is_none());
+    }
+
+    #[test]
+    fn test_payload_maps_request_options() {
+        let tools = vec![ToolDefinition {
+            name: "submit_plan".to_string(),
+            description: "Submit the plan".to_string(),
+            parameters: crate::models::tools::ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: Default::default(),
+                required: vec![],
+            },
+        }];
+        let options = RequestOptions::default()
+            .with_tool_choice(ToolChoice::Tool("submit_plan".to_string()))
+            .with_response_format(ResponseFormat::json_schema(
+                "plan",
+                json!({ "type": "object", "properties": { "steps": { "type": "array" } } }),
+            ));
+
+        let payload = provider(String::new())
+            .build_payload(Vec::new(), Some(&tools), &options)
+            .unwrap();
+        assert_eq!(
+            payload["tool_choice"],
+            json!({ "type": "function", "function": { "name": "submit_plan" } })
+        );
+        assert_eq!(payload["response_format"]["type"], "json_schema");
+        assert_eq!(payload["response_format"]["json_schema"]["name"], "plan");
+
+        let error = provider(String::new())
+            .build_payload(
+                Vec::new(),
+                None,
+                &RequestOptions::default().with_tool_choice(ToolChoice::Required),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            ProviderError::find(error.as_ref()),
+            Some(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_uses_models_endpoint() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/v1/models")
+                    .header("authorization", "Bearer test-key");
+                then.status(200).json_body(json!({
+                    "object": "list",
+                    "data": [
+                        {"id": "gpt-test", "object": "model", "owned_by": "openai"},
+                        {"id": "gpt-a", "object": "model"}
+                    ]
+                }));
+            })
+            .await;
+
+        let models = provider(server.url("/v1/chat/completions"))
+            .list_models()
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["gpt-a", "gpt-test"]);
+        assert_eq!(models[1].details.as_deref(), Some("openai"));
     }
 }
@@ -0,0 +1,265 @@
+// volition-agent-core/src/providers/openai_compatible.rs
+
+//! Provider for self-hosted servers that speak the OpenAI chat-completions
+//! dialect, such as llama.cpp, vLLM and LM Studio.
+
+use super::{ModelInfo, Provider};
+use super::capabilities::ModelCapabilities;
+use super::openai::{Dialect, OpenAIProvider};
+use super::streaming::DeltaSink;
+use crate::config::ModelConfig;
+use crate::models::chat::{ApiResponse, ChatMessage};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::debug;
+
+/// Sends requests to a configured chat-completions endpoint. Authentication is
+/// only sent when an API key is available, and server quirks come from the
+/// model's `compatibility` table.
+#[derive(Clone)]
+pub struct OpenAICompatibleProvider {
+    inner: OpenAIProvider,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(config: ModelConfig, http_client: Client, api_key: String) -> Result<Self> {
+        let endpoint = config.endpoint.clone().ok_or_else(|| {
+            anyhow!(
+                "The openai_compatible provider for model {} requires an endpoint.",
+                config.model_name
+            )
+        })?;
+        let compatibility = config.compatibility.clone().unwrap_or_default();
+        debug!(
+            "Creating OpenAI-compatible provider for model {} at {}",
+            config.model_name, endpoint
+        );
+
+        let dialect = Dialect {
+            label: "OpenAI-compatible",
+            endpoint,
+            auth_header: if api_key.is_empty() {
+                None
+            } else {
+                Some(("Authorization".to_string(), format!("Bearer {}", api_key)))
+            },
+            headers: compatibility.headers.into_iter().collect(),
+            supports_tools: compatibility.supports_tools,
+            supports_stream_usage: compatibility.supports_stream_usage,
+            usage_field: compatibility.usage_field,
+            think_tags: true,
+        };
+        Ok(Self {
+            inner: OpenAIProvider::with_dialect(config, http_client, dialect),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAICompatibleProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.inner.get_completion(messages, tools, options).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.inner.stream_completion(messages, tools, options, on_delta).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::Role;
+    use crate::config::CompatibilityConfig;
+    use crate::models::tools::ToolParametersDefinition;
+    use httpmock::prelude::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn config(endpoint: Option<String>, compatibility: CompatibilityConfig) -> ModelConfig {
+        ModelConfig {
+            model_name: "local-model".to_string(),
+            endpoint,
+            compatibility: Some(compatibility),
+            ..Default::default()
+        }
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_requires_endpoint() {
+        let result = OpenAICompatibleProvider::new(
+            config(None, CompatibilityConfig::default()),
+            Client::new(),
+            String::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sends_custom_headers_without_auth_and_skips_tools() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/chat/completions")
+                    .header("x-gateway", "dev")
+                    .json_body_partial(r#"{"model": "local-model"}"#)
+                    .matches(|req| {
+                        let headers = req.headers.clone().unwrap_or_default();
+                        !headers
+                            .iter()
+                            .any(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+                            && !String::from_utf8_lossy(req.body.as_deref().unwrap_or_default())
+                                .contains("\"tools\"")
+                    });
+                then.status(200).json_body(json!({
+                    "id": "cmpl-local",
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "Hello" },
+                        "finish_reason": "stop"
+                    }],
+                    "stats": { "prompt_tokens": 3, "completion_tokens": 2 }
+                }));
+            })
+            .await;
+
+        let provider = OpenAICompatibleProvider::new(
+            config(
+                Some(server.url("/v1/chat/completions")),
+                CompatibilityConfig {
+                    headers: HashMap::from([("X-Gateway".to_string(), "dev".to_string())]),
+                    supports_tools: false,
+                    usage_field: "stats".to_string(),
+                    ..Default::default()
+                },
+            ),
+            Client::new(),
+            String::new(),
+        )
+        .unwrap();
+        let tools = vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: HashMap::new(),
+                required: vec![],
+            },
+        }];
+
+        let response = provider
+            .get_completion(user("hi"), Some(&tools), &RequestOptions::default())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.prompt_tokens, 3);
+        assert_eq!(response.total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_sends_bearer_auth_when_key_is_set() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/chat/completions")
+                    .header("authorization", "Bearer secret");
+                then.status(200).json_body(json!({
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "ok" },
+                        "finish_reason": "stop"
+                    }]
+                }));
+            })
+            .await;
+
+        let provider = OpenAICompatibleProvider::new(
+            config(
+                Some(server.url("/v1/chat/completions")),
+                CompatibilityConfig::default(),
+            ),
+            Client::new(),
+            "secret".to_string(),
+        )
+        .unwrap();
+
+        let response = provider.get_completion(user("hi"), None, &RequestOptions::default()).await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(response.content, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_leading_think_block_is_reasoning() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/v1/chat/completions");
+                then.status(200).json_body(json!({
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "<think>Greet them.</think>Hi!" },
+                        "finish_reason": "stop"
+                    }]
+                }));
+            })
+            .await;
+
+        let provider = OpenAICompatibleProvider::new(
+            config(
+                Some(server.url("/v1/chat/completions")),
+                CompatibilityConfig::default(),
+            ),
+            Client::new(),
+            String::new(),
+        )
+        .unwrap();
+
+        let response = provider
+            .get_completion(user("hi"), None, &RequestOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Hi!");
+        assert_eq!(
+            response.choices[0].message.reasoning.as_deref(),
+            Some("Greet them.")
+        );
+    }
+}
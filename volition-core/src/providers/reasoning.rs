@@ -0,0 +1,343 @@
+// volition-agent-core/src/providers/reasoning.rs
+
+//! Separation of a model's reasoning from its answer.
+//!
+//! Reasoning models served locally emit their chain of thought inline as a
+//! `<think>...</think>` block at the start of the response. Providers for such
+//! servers move that block, and any reasoning the API returns in a dedicated
+//! field, into [`ChatMessage::reasoning`] so it is not shown as the answer.
+//! Providers whose APIs return reasoning in its own field (OpenAI, Anthropic,
+//! Gemini) leave tags in the answer alone. Providers never send `reasoning` back
+//! to the model; [`KeepReasoningProvider`] inlines it again for models
+//! configured with `keep_reasoning = true`.
+
+use super::{ModelInfo, Provider};
+use super::capabilities::ModelCapabilities;
+use super::streaming::DeltaSink;
+use crate::models::chat::{ApiResponse, ChatMessage, StreamDelta};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::debug;
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// Splits a leading `<think>` block out of a complete response text, returning
+/// the reasoning (if any) and the remaining answer.
+///
+/// Only a block that opens the response, after optional whitespace, counts as
+/// reasoning; tags anywhere else are part of the answer. An unclosed block, as
+/// left by a response cut off while thinking, counts as reasoning up to the end
+/// of the text.
+pub fn split_think_tags(text: &str) -> (Option<String>, String) {
+    let mut splitter = ThinkTagSplitter::default();
+    let mut reasoning = String::new();
+    let mut answer = String::new();
+    for delta in splitter.push(text).into_iter().chain(splitter.finish()) {
+        match delta {
+            StreamDelta::Reasoning(text) => reasoning.push_str(&text),
+            StreamDelta::Text(text) => answer.push_str(&text),
+            StreamDelta::ToolCall { .. } => {}
+        }
+    }
+    let reasoning = reasoning.trim();
+    if reasoning.is_empty() {
+        return (None, answer);
+    }
+    (Some(reasoning.to_string()), answer.trim_start().to_string())
+}
+
+/// Separates the reasoning of a complete response: `native` is reasoning the API
+/// returned in its own field, followed by a leading `<think>` block in `content`
+/// when `think_tags` is set. APIs that always return reasoning in its own field
+/// pass `false`, so their answers are never searched for tags.
+pub fn split_reasoning(
+    native: Option<&str>,
+    content: &str,
+    think_tags: bool,
+) -> (Option<String>, String) {
+    let (inline, answer) = if think_tags {
+        split_think_tags(content)
+    } else {
+        (None, content.to_string())
+    };
+    let reasoning = native
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(String::from)
+        .into_iter()
+        .chain(inline)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    ((!reasoning.is_empty()).then_some(reasoning), answer)
+}
+
+/// Where a [`ThinkTagSplitter`] is in the response.
+#[derive(Debug, Default, PartialEq)]
+enum ThinkState {
+    /// Only whitespace so far, so a `<think>` block may still open.
+    #[default]
+    Leading,
+    /// Inside the leading block.
+    Thinking,
+    /// Past the leading block, or the response did not open with one.
+    Answer,
+}
+
+/// Incrementally separates a leading `<think>` block from streamed text. Text
+/// that might be the start of a tag is held back until the next fragment
+/// decides it.
+#[derive(Debug, Default)]
+pub struct ThinkTagSplitter {
+    state: ThinkState,
+    pending: String,
+}
+
+impl ThinkTagSplitter {
+    /// Consumes a text fragment, returning `Text` and `Reasoning` deltas in order.
+    pub fn push(&mut self, fragment: &str) -> Vec<StreamDelta> {
+        self.pending.push_str(fragment);
+        let mut deltas = Vec::new();
+        loop {
+            match self.state {
+                ThinkState::Leading => {
+                    let start = self.pending.trim_start();
+                    if start.starts_with(OPEN_TAG) {
+                        let tag_end = self.pending.len() - start.len() + OPEN_TAG.len();
+                        self.pending.drain(..tag_end);
+                        self.state = ThinkState::Thinking;
+                    } else if OPEN_TAG.starts_with(start) {
+                        // Whitespace or a partial tag: wait for more text.
+                        return deltas;
+                    } else {
+                        self.state = ThinkState::Answer;
+                    }
+                }
+                ThinkState::Thinking => match self.pending.find(CLOSE_TAG) {
+                    Some(pos) => {
+                        let reasoning: String = self.pending.drain(..pos).collect();
+                        self.pending.drain(..CLOSE_TAG.len());
+                        self.emit(reasoning, &mut deltas);
+                        self.state = ThinkState::Answer;
+                    }
+                    None => {
+                        // Keep any suffix that could still grow into the tag.
+                        let keep = (1..CLOSE_TAG.len())
+                            .rev()
+                            .find(|&n| self.pending.ends_with(&CLOSE_TAG[..n]))
+                            .unwrap_or(0);
+                        let split = self.pending.len() - keep;
+                        let ready: String = self.pending.drain(..split).collect();
+                        self.emit(ready, &mut deltas);
+                        return deltas;
+                    }
+                },
+                ThinkState::Answer => {
+                    let text = std::mem::take(&mut self.pending);
+                    self.emit(text, &mut deltas);
+                    return deltas;
+                }
+            }
+        }
+    }
+
+    /// Returns any held-back text once the stream has ended.
+    pub fn finish(&mut self) -> Vec<StreamDelta> {
+        if self.state == ThinkState::Leading {
+            self.state = ThinkState::Answer;
+        }
+        let rest = std::mem::take(&mut self.pending);
+        let mut deltas = Vec::new();
+        self.emit(rest, &mut deltas);
+        deltas
+    }
+
+    fn emit(&self, text: String, deltas: &mut Vec<StreamDelta>) {
+        if text.is_empty() {
+            return;
+        }
+        deltas.push(if self.state == ThinkState::Thinking {
+            StreamDelta::Reasoning(text)
+        } else {
+            StreamDelta::Text(text)
+        });
+    }
+}
+
+/// Wraps a provider so earlier reasoning is sent back to the model, inlined as a
+/// `<think>` block before the content of each assistant message.
+pub struct KeepReasoningProvider {
+    inner: Box<dyn Provider>,
+}
+
+impl KeepReasoningProvider {
+    pub fn new(inner: Box<dyn Provider>) -> Self {
+        debug!(provider = inner.name(), "Keeping reasoning in context");
+        Self { inner }
+    }
+}
+
+/// Moves the `reasoning` of each message back into its content.
+fn inline_reasoning(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    messages
+        .into_iter()
+        .map(ChatMessage::with_text_in_content)
+        .map(|message| match message.reasoning {
+            Some(reasoning) => ChatMessage {
+                content: Some(format!(
+                    "{}\n{}\n{}\n\n{}",
+                    OPEN_TAG,
+                    reasoning,
+                    CLOSE_TAG,
+                    message.content.unwrap_or_default()
+                )),
+                reasoning: None,
+                ..message
+            },
+            None => message,
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Provider for KeepReasoningProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.inner
+            .get_completion(inline_reasoning(messages), tools, options)
+            .await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.inner
+            .stream_completion(inline_reasoning(messages), tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::Role;
+
+    #[test]
+    fn test_split_think_tags() {
+        let (reasoning, answer) =
+            split_think_tags("<think>\nThe user wants a greeting.\n</think>\n\nHello!");
+        assert_eq!(reasoning.as_deref(), Some("The user wants a greeting."));
+        assert_eq!(answer, "Hello!");
+
+        let (reasoning, answer) = split_think_tags("No thinking here.");
+        assert_eq!(reasoning, None);
+        assert_eq!(answer, "No thinking here.");
+
+        let (reasoning, answer) = split_think_tags("<think>Cut off while thin");
+        assert_eq!(reasoning.as_deref(), Some("Cut off while thin"));
+        assert_eq!(answer, "");
+
+        let (reasoning, answer) =
+            split_reasoning(Some("From the API."), "<think>Inline.</think>Hi", true);
+        assert_eq!(reasoning.as_deref(), Some("From the API.\n\nInline."));
+        assert_eq!(answer, "Hi");
+
+        let (reasoning, answer) =
+            split_reasoning(Some("From the API."), "<think>Inline.</think>Hi", false);
+        assert_eq!(reasoning.as_deref(), Some("From the API."));
+        assert_eq!(answer, "<think>Inline.</think>Hi");
+    }
+
+    #[test]
+    fn test_only_a_leading_think_block_is_reasoning() {
+        let (reasoning, answer) = split_think_tags("\n  <think>Plan.</think>Done.");
+        assert_eq!(reasoning.as_deref(), Some("Plan."));
+        assert_eq!(answer, "Done.");
+
+        let text = "Wrap it in <think>...</think> tags.";
+        assert_eq!(split_think_tags(text), (None, text.to_string()));
+
+        let text = "<think>Plan.</think>Then write <think>x</think> literally.";
+        let (reasoning, answer) = split_think_tags(text);
+        assert_eq!(reasoning.as_deref(), Some("Plan."));
+        assert_eq!(answer, "Then write <think>x</think> literally.");
+
+        // Streamed, a tag after other text stays text even when split up.
+        let mut splitter = ThinkTagSplitter::default();
+        let mut deltas = Vec::new();
+        for fragment in [" ", "Use <th", "ink>", " here."] {
+            deltas.extend(splitter.push(fragment));
+        }
+        deltas.extend(splitter.finish());
+        assert!(
+            deltas.iter().all(|d| matches!(d, StreamDelta::Text(_))),
+            "{:?}",
+            deltas
+        );
+    }
+
+    #[test]
+    fn test_splitter_handles_tags_across_fragments() {
+        let mut splitter = ThinkTagSplitter::default();
+        let mut deltas = Vec::new();
+        for fragment in ["<th", "ink>Plan", " it.</th", "ink>Done <", "b>"] {
+            deltas.extend(splitter.push(fragment));
+        }
+        deltas.extend(splitter.finish());
+
+        let reasoning: String = deltas
+            .iter()
+            .filter_map(|d| match d {
+                StreamDelta::Reasoning(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        let text: String = deltas
+            .iter()
+            .filter_map(|d| match d {
+                StreamDelta::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasoning, "Plan it.");
+        assert_eq!(text, "Done <b>");
+    }
+
+    #[test]
+    fn test_inline_reasoning() {
+        let messages = vec![ChatMessage {
+            role: Role::Assistant,
+            content: Some("Hello!".to_string()),
+            reasoning: Some("Greet them.".to_string()),
+            ..Default::default()
+        }];
+        let inlined = inline_reasoning(messages);
+        assert_eq!(
+            inlined[0].content.as_deref(),
+            Some("<think>\nGreet them.\n</think>\n\nHello!")
+        );
+        assert!(inlined[0].reasoning.is_none());
+    }
+}
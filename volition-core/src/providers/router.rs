@@ -0,0 +1,435 @@
+// volition-agent-core/src/providers/router.rs
+
+//! Rule-based routing of each request to one of the other configured providers,
+//! so that cheap requests go to a local model and hard ones escalate to a
+//! hosted model without switching providers by hand.
+//!
+//! A provider with `type = "router"` lists `routes` in its `model_config`. Each
+//! [`RouteRule`] names a target provider and conditions on the estimated prompt
+//! size, whether tools are offered, the strategy phase, keywords in the latest
+//! user message and the number of tool calls that failed in a row. The first
+//! matching rule wins; requests matching none go to the `fallback` provider.
+
+use super::capabilities::ModelCapabilities;
+use super::streaming::DeltaSink;
+use super::{ModelInfo, Provider, ProviderRegistry};
+use crate::config::{ModelConfig, RouteRule};
+use crate::models::chat::{ApiResponse, ChatMessage, ContentPart, Role, estimate_tokens};
+use crate::models::request::RequestOptions;
+use crate::models::tools::ToolDefinition;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, info};
+
+/// The TOML `type` of router providers.
+pub const ROUTER_TYPE: &str = "router";
+
+/// What the rules of a router look at, computed once per request.
+struct RouteRequest {
+    prompt_tokens: usize,
+    has_tools: bool,
+    phase: Option<String>,
+    /// The latest user message, lowercased.
+    latest_prompt: String,
+    tool_failures: u32,
+}
+
+impl RouteRequest {
+    fn new(
+        messages: &[ChatMessage],
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Self {
+        // User messages carrying tool results are not prompts.
+        let latest_prompt = messages
+            .iter()
+            .rev()
+            .find(|m| {
+                m.role == Role::User
+                    && !m
+                        .parts
+                        .iter()
+                        .any(|p| matches!(p, ContentPart::ToolResult { .. }))
+            })
+            .map(|m| m.text().to_lowercase())
+            .unwrap_or_default();
+        Self {
+            prompt_tokens: estimate_tokens(messages),
+            has_tools: tools.is_some_and(|tools| !tools.is_empty()),
+            phase: options.phase.clone(),
+            latest_prompt,
+            tool_failures: options.tool_failures,
+        }
+    }
+
+    fn matches(&self, rule: &RouteRule) -> bool {
+        rule.min_prompt_tokens
+            .is_none_or(|min| self.prompt_tokens >= min)
+            && rule
+                .max_prompt_tokens
+                .is_none_or(|max| self.prompt_tokens <= max)
+            && rule.tools.is_none_or(|tools| self.has_tools == tools)
+            && rule
+                .phase
+                .as_ref()
+                .is_none_or(|phase| self.phase.as_ref() == Some(phase))
+            && (rule.keywords.is_empty()
+                || rule
+                    .keywords
+                    .iter()
+                    .any(|keyword| contains_word(&self.latest_prompt, &keyword.to_lowercase())))
+            && rule
+                .min_tool_failures
+                .is_none_or(|min| self.tool_failures >= min)
+    }
+}
+
+/// Whether `word` occurs in `text` as a whole word, i.e. not directly next to
+/// another letter, digit or underscore, so "prove" does not match "improve".
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(start, _)| {
+        let end = start + word.len();
+        !text[..start].chars().next_back().is_some_and(is_word_char)
+            && !text[end..].chars().next().is_some_and(is_word_char)
+    })
+}
+
+/// A provider to which a router hands requests.
+struct Target {
+    provider_id: String,
+    provider: Arc<dyn Provider>,
+}
+
+/// Sends each request to the provider of the first matching rule.
+pub struct RouterProvider {
+    id: String,
+    rules: Vec<RouteRule>,
+    /// One target per rule, followed by the fallback.
+    targets: Vec<Target>,
+    /// Index into `targets` of the provider that served the latest request.
+    last_target: AtomicUsize,
+}
+
+impl RouterProvider {
+    /// Creates router `id` from its `routes` and `fallback`, whose providers
+    /// must already be in `registry`.
+    pub fn new(id: &str, config: &ModelConfig, registry: &ProviderRegistry) -> Result<Self> {
+        let fallback = config.fallback.as_deref().ok_or_else(|| {
+            anyhow!(
+                "Router provider '{}' is missing 'model_config.fallback'.",
+                id
+            )
+        })?;
+        let targets = config
+            .routes
+            .iter()
+            .map(|rule| rule.provider.as_str())
+            .chain(std::iter::once(fallback))
+            .map(|provider_id| {
+                Ok(Target {
+                    provider_id: provider_id.to_string(),
+                    provider: registry.get_shared(provider_id)?.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        debug!(router = %id, rules = config.routes.len(), %fallback, "Created router provider");
+        Ok(Self {
+            id: id.to_string(),
+            rules: config.routes.clone(),
+            last_target: AtomicUsize::new(targets.len() - 1),
+            targets,
+        })
+    }
+
+    /// Picks the target for a request and remembers it as the latest.
+    fn route(
+        &self,
+        messages: &[ChatMessage],
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> &Arc<dyn Provider> {
+        let request = RouteRequest::new(messages, tools, options);
+        let index = self
+            .rules
+            .iter()
+            .position(|rule| request.matches(rule))
+            .unwrap_or(self.rules.len());
+        let target = &self.targets[index];
+        info!(
+            router = %self.id,
+            provider = %target.provider_id,
+            rule = if index < self.rules.len() { Some(index + 1) } else { None },
+            prompt_tokens = request.prompt_tokens,
+            has_tools = request.has_tools,
+            phase = ?request.phase,
+            tool_failures = request.tool_failures,
+            "Routing request"
+        );
+        self.last_target.store(index, Ordering::Relaxed);
+        &target.provider
+    }
+}
+
+#[async_trait]
+impl Provider for RouterProvider {
+    /// The model of the provider that served the latest request, or of the
+    /// fallback before the first request, so usage is attributed to it.
+    fn name(&self) -> &str {
+        self.targets[self.last_target.load(Ordering::Relaxed)]
+            .provider
+            .name()
+    }
+
+    /// What every target supports, since the target is only known per request.
+    /// Streaming is left on if any target streams; the others are asked for a
+    /// complete response.
+    fn capabilities(&self) -> ModelCapabilities {
+        let mut targets = self.targets.iter().map(|t| t.provider.capabilities());
+        let mut capabilities = targets.next().unwrap_or_default();
+        for other in targets {
+            capabilities.tools &= other.tools;
+            capabilities.images &= other.images;
+            capabilities.json_mode &= other.json_mode;
+            capabilities.streaming |= other.streaming;
+            capabilities.context_window = match (capabilities.context_window, other.context_window)
+            {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        capabilities
+    }
+
+    /// The models of the targets, each described by its provider ID.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let mut models: Vec<ModelInfo> = Vec::new();
+        for target in &self.targets {
+            let id = target.provider.name().to_string();
+            if !models.iter().any(|m| m.id == id) {
+                models.push(ModelInfo {
+                    id,
+                    details: Some(format!("via {}", target.provider_id)),
+                });
+            }
+        }
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.route(&messages, tools, options)
+            .get_completion(messages, tools, options)
+            .await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        let provider = self.route(&messages, tools, options);
+        if !provider.capabilities().streaming {
+            return provider.get_completion(messages, tools, options).await;
+        }
+        provider
+            .stream_completion(messages, tools, options, on_delta)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::Choice;
+    use crate::models::tools::ToolParametersDefinition;
+
+    /// Answers with its own name.
+    struct NamedProvider(&'static str);
+
+    #[async_trait]
+    impl Provider for NamedProvider {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn get_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            Ok(ApiResponse {
+                id: self.0.to_string(),
+                content: self.0.to_string(),
+                finish_reason: "stop".to_string(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                choices: vec![Choice {
+                    index: 0,
+                    message: ChatMessage {
+                        role: Role::Assistant,
+                        content: Some(self.0.to_string()),
+                        ..Default::default()
+                    },
+                    finish_reason: "stop".to_string(),
+                }],
+            })
+        }
+    }
+
+    fn router() -> RouterProvider {
+        let mut registry = ProviderRegistry::new("local".to_string());
+        registry.register("local".to_string(), Box::new(NamedProvider("llama3")));
+        registry.register("hosted".to_string(), Box::new(NamedProvider("gpt-4o")));
+        let config: ModelConfig = toml::from_str(
+            r#"
+            fallback = "local"
+            [[routes]]
+            provider = "hosted"
+            min_tool_failures = 2
+            [[routes]]
+            provider = "hosted"
+            phase = "planning"
+            [[routes]]
+            provider = "hosted"
+            keywords = ["Refactor", "prove"]
+            [[routes]]
+            provider = "hosted"
+            min_prompt_tokens = 1000
+            tools = true
+            "#,
+        )
+        .unwrap();
+        RouterProvider::new("auto", &config, &registry).unwrap()
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }]
+    }
+
+    async fn routed(
+        router: &RouterProvider,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: RequestOptions,
+    ) -> String {
+        router
+            .get_completion(messages, tools, &options)
+            .await
+            .unwrap()
+            .content
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_to_first_matching_route() {
+        let router = router();
+        let tools = [ToolDefinition {
+            name: "shell".to_string(),
+            description: "Runs a command.".to_string(),
+            parameters: ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties: Default::default(),
+                required: Vec::new(),
+            },
+        }];
+        assert_eq!(router.name(), "llama3");
+
+        let simple = || user("List the files.");
+        assert_eq!(
+            routed(&router, simple(), Some(&tools), RequestOptions::default()).await,
+            "llama3"
+        );
+        assert_eq!(
+            routed(
+                &router,
+                user("Please REFACTOR this."),
+                None,
+                RequestOptions::default()
+            )
+            .await,
+            "gpt-4o"
+        );
+        assert_eq!(router.name(), "gpt-4o");
+        // Keywords match whole words only.
+        assert_eq!(
+            routed(
+                &router,
+                user("Improve the refactoring notes."),
+                None,
+                RequestOptions::default()
+            )
+            .await,
+            "llama3"
+        );
+        assert_eq!(
+            routed(
+                &router,
+                user("Prove it: 1+1=2"),
+                None,
+                RequestOptions::default()
+            )
+            .await,
+            "gpt-4o"
+        );
+        assert_eq!(
+            routed(
+                &router,
+                simple(),
+                None,
+                RequestOptions::default().with_phase("planning")
+            )
+            .await,
+            "gpt-4o"
+        );
+        let failing = RequestOptions {
+            tool_failures: 2,
+            ..Default::default()
+        };
+        assert_eq!(routed(&router, simple(), None, failing).await, "gpt-4o");
+
+        // Large prompts only escalate when tools are offered.
+        let large = || user(&"x".repeat(8000));
+        assert_eq!(
+            routed(&router, large(), None, RequestOptions::default()).await,
+            "llama3"
+        );
+        assert_eq!(router.name(), "llama3");
+        assert_eq!(
+            routed(&router, large(), Some(&tools), RequestOptions::default()).await,
+            "gpt-4o"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_lists_target_models() {
+        let models = router().list_models().await.unwrap();
+        assert_eq!(
+            models,
+            vec![
+                ModelInfo {
+                    id: "gpt-4o".to_string(),
+                    details: Some("via hosted".to_string()),
+                },
+                ModelInfo {
+                    id: "llama3".to_string(),
+                    details: Some("via local".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,323 @@
+// volition-agent-core/src/providers/scripted.rs
+
+//! A provider that returns canned assistant turns from a script file, for
+//! end-to-end tests that should not depend on a model server.
+//!
+//! Scripts are TOML (or JSON, for files ending in `.json`) with a list of turns:
+//!
+//! ```toml
+//! [[turns]]
+//! match = "list the files"   # optional
+//! content = "Let me look."
+//! reasoning = "I should list the directory first."   # optional
+//!   [[turns.tool_calls]]
+//!   name = "list_directory"
+//!   arguments = { path = "." }
+//!
+//! [[turns]]
+//! content = "The directory contains main.rs."
+//! ```
+//!
+//! A turn with `match` is returned when its text occurs in the latest user or
+//! tool message. Otherwise the next unused turn without `match` is returned, so
+//! scripts without any `match` keys play back in order. Each turn is used once.
+
+use super::{ModelInfo, Provider};
+use super::capabilities::ModelCapabilities;
+use crate::config::ModelConfig;
+use crate::models::chat::{ApiResponse, ChatMessage, Choice, Role, split_tool_results};
+use crate::models::request::RequestOptions;
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::debug;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Script {
+    #[serde(default)]
+    pub turns: Vec<ScriptedTurn>,
+}
+
+/// One canned assistant response.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScriptedTurn {
+    /// Text that must occur in the latest user or tool message.
+    #[serde(default, rename = "match")]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    /// Reasoning returned apart from `content`, as a reasoning model would.
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ScriptedToolCall>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScriptedToolCall {
+    pub name: String,
+    #[serde(default = "empty_arguments")]
+    pub arguments: Value,
+}
+
+fn empty_arguments() -> Value {
+    Value::Object(Default::default())
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script file {}", path.display()))?;
+        let script = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(anyhow::Error::from)
+        } else {
+            toml::from_str(&content).map_err(anyhow::Error::from)
+        };
+        script.with_context(|| format!("Failed to parse script file {}", path.display()))
+    }
+}
+
+pub struct ScriptedProvider {
+    name: String,
+    path: PathBuf,
+    turns: Vec<ScriptedTurn>,
+    used: Mutex<Vec<bool>>,
+    capabilities: ModelCapabilities,
+}
+
+impl ScriptedProvider {
+    /// Creates a provider for `type = "scripted"`, reading `model_config.script`.
+    pub fn from_config(config: &ModelConfig) -> Result<Self> {
+        let path = config.script.as_deref().ok_or_else(|| {
+            anyhow!(
+                "The scripted provider for model {} requires 'model_config.script'.",
+                config.model_name
+            )
+        })?;
+        let mut provider = Self::load(config.model_name.clone(), path)?;
+        provider.capabilities = provider.capabilities.with_overrides(&config.capabilities);
+        Ok(provider)
+    }
+
+    pub fn load(name: String, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let script = Script::load(&path)?;
+        Ok(Self::new(name, path, script))
+    }
+
+    pub fn new(name: String, path: PathBuf, script: Script) -> Self {
+        let used = vec![false; script.turns.len()];
+        Self {
+            name,
+            path,
+            turns: script.turns,
+            used: Mutex::new(used),
+            capabilities: ModelCapabilities::unrestricted(),
+        }
+    }
+
+    fn to_response(&self, index: usize, turn: &ScriptedTurn) -> ApiResponse {
+        let tool_calls: Vec<ToolCall> = turn
+            .tool_calls
+            .iter()
+            .map(|call| ToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call_type: "function".to_string(),
+                function: ToolFunction {
+                    name: call.name.clone(),
+                    arguments: call.arguments.to_string(),
+                },
+            })
+            .collect();
+        let finish_reason = if tool_calls.is_empty() { "stop" } else { "tool_calls" }.to_string();
+
+        ApiResponse {
+            id: format!("scripted-{}", index + 1),
+            content: turn.content.clone(),
+            finish_reason: finish_reason.clone(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(turn.content.clone()),
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                    reasoning: turn.reasoning.clone(),
+                    ..Default::default()
+                },
+                finish_reason,
+            }],
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for ScriptedProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.capabilities
+    }
+
+    /// Lists the scripted model itself, so `volition models` works offline.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(vec![ModelInfo {
+            id: self.name.clone(),
+            details: Some(format!("script {}", self.path.display())),
+        }])
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        _tools: Option<&[ToolDefinition]>,
+        _options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        let latest = split_tool_results(&messages)
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, Role::User | Role::Tool))
+            .map(ChatMessage::text)
+            .unwrap_or_default();
+        let mut used = self.used.lock().unwrap();
+
+        let unused = || self.turns.iter().enumerate().filter(|(i, _)| !used[*i]);
+        let matched = unused()
+            .find(|(_, turn)| {
+                turn.pattern
+                    .as_deref()
+                    .is_some_and(|pattern| latest.contains(pattern))
+            })
+            .or_else(|| unused().find(|(_, turn)| turn.pattern.is_none()))
+            .map(|(i, _)| i);
+
+        let Some(index) = matched else {
+            return Err(anyhow!(
+                "Scripted provider {} has no remaining turn for the latest message: {:?}",
+                self.path.display(),
+                latest
+            ));
+        };
+        used[index] = true;
+        debug!("Returning scripted turn {} from {}", index + 1, self.path.display());
+        Ok(self.to_response(index, &self.turns[index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(content.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn provider(script: &str) -> ScriptedProvider {
+        ScriptedProvider::new(
+            "scripted".to_string(),
+            PathBuf::from("script.toml"),
+            toml::from_str(script).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_turns_play_in_order() {
+        let provider = provider(
+            r#"
+            [[turns]]
+            content = "Looking."
+              [[turns.tool_calls]]
+              name = "list_directory"
+              arguments = { path = "." }
+
+            [[turns]]
+            content = "Done."
+            "#,
+        );
+
+        let first = provider
+            .get_completion(vec![message(Role::User, "list files")], None, &RequestOptions::default())
+            .await
+            .unwrap();
+        let calls = first.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(first.finish_reason, "tool_calls");
+        assert_eq!(calls[0].function.name, "list_directory");
+        assert_eq!(calls[0].function.arguments, r#"{"path":"."}"#);
+
+        let second = provider
+            .get_completion(vec![message(Role::Tool, "main.rs")], None, &RequestOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(second.content, "Done.");
+        assert!(second.choices[0].message.tool_calls.is_none());
+
+        let error = provider
+            .get_completion(vec![message(Role::User, "more")], None, &RequestOptions::default())
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("no remaining turn"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_match_on_latest_user_or_tool_message() {
+        let provider = provider(
+            r#"
+            [[turns]]
+            match = "weather"
+            content = "Sunny."
+
+            [[turns]]
+            content = "Fallback."
+            "#,
+        );
+
+        let response = provider
+            .get_completion(
+                vec![message(Role::User, "what's the weather"), message(Role::Assistant, "hmm")],
+                None,
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Sunny.");
+
+        let response = provider
+            .get_completion(vec![message(Role::User, "hello")], None, &RequestOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Fallback.");
+    }
+
+    #[test]
+    fn test_load_json_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.json");
+        std::fs::write(
+            &path,
+            r#"{"turns": [{"content": "Hi", "tool_calls": [{"name": "git_status"}]}]}"#,
+        )
+        .unwrap();
+
+        let script = Script::load(&path).unwrap();
+        assert_eq!(script.turns[0].tool_calls[0].name, "git_status");
+        assert_eq!(script.turns[0].tool_calls[0].arguments, empty_arguments());
+    }
+}
@@ -0,0 +1,278 @@
+// volition-agent-core/src/providers/streaming.rs
+
+//! Shared plumbing for streamed completions: splitting a response body into
+//! lines (for SSE and NDJSON) and assembling deltas into an [`ApiResponse`].
+
+use crate::models::chat::{ApiResponse, ChatMessage, Choice, Role, StreamDelta};
+use crate::models::tools::{ToolCall, ToolFunction};
+use super::check_response;
+use super::reasoning::ThinkTagSplitter;
+use crate::errors::ProviderError;
+use anyhow::{Context, Result};
+use reqwest::Response;
+
+/// Callback invoked with each delta as it is received.
+pub type DeltaSink<'a> = &'a (dyn Fn(StreamDelta) + Send + Sync);
+
+/// Reads a streaming HTTP response line by line, calling `handle_line` for each
+/// non-empty line. Fails with the response body if the status is not a success.
+pub async fn for_each_line(
+    response: Response,
+    mut handle_line: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut response = check_response(response).await?;
+
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(ProviderError::from)
+        .context("Failed to read chunk from streaming response")?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                handle_line(line)?;
+            }
+        }
+    }
+
+    let rest = String::from_utf8_lossy(&buffer);
+    let rest = rest.trim();
+    if !rest.is_empty() {
+        handle_line(rest)?;
+    }
+    Ok(())
+}
+
+/// Returns the payload of an SSE `data:` line, or `None` for other SSE fields.
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+/// Collects streamed deltas into the final [`ApiResponse`], forwarding each delta
+/// to a [`DeltaSink`] on the way. Text is passed through as is unless the
+/// accumulator is made [`with_think_tags`](Self::with_think_tags).
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    pub id: String,
+    pub finish_reason: Option<String>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: Option<u32>,
+    content: String,
+    reasoning: String,
+    think_tags: Option<ThinkTagSplitter>,
+    tool_calls: Vec<PartialToolCall>,
+}
+
+impl StreamAccumulator {
+    /// An accumulator that turns a leading `<think>` block in the text into
+    /// reasoning deltas, for servers that return reasoning inline.
+    pub fn with_think_tags() -> Self {
+        Self {
+            think_tags: Some(ThinkTagSplitter::default()),
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, delta: StreamDelta, sink: DeltaSink<'_>) {
+        match (&delta, &mut self.think_tags) {
+            (StreamDelta::Text(text), Some(think_tags)) => {
+                for delta in think_tags.push(text) {
+                    self.record(delta, sink);
+                }
+            }
+            _ => self.record(delta, sink),
+        }
+    }
+
+    fn record(&mut self, delta: StreamDelta, sink: DeltaSink<'_>) {
+        match &delta {
+            StreamDelta::Text(text) => {
+                if text.is_empty() {
+                    return;
+                }
+                self.content.push_str(text);
+            }
+            StreamDelta::Reasoning(text) => {
+                if text.is_empty() {
+                    return;
+                }
+                self.reasoning.push_str(text);
+            }
+            StreamDelta::ToolCall {
+                index,
+                id,
+                name,
+                arguments,
+            } => {
+                if self.tool_calls.len() <= *index {
+                    self.tool_calls.resize_with(index + 1, PartialToolCall::default);
+                }
+                let call = &mut self.tool_calls[*index];
+                if let Some(id) = id {
+                    call.id = Some(id.clone());
+                }
+                if let Some(name) = name {
+                    call.name.push_str(name);
+                }
+                call.arguments.push_str(arguments);
+            }
+        }
+        sink(delta);
+    }
+
+    /// Number of tool calls seen so far, used to index calls that arrive whole.
+    pub fn tool_call_count(&self) -> usize {
+        self.tool_calls.len()
+    }
+
+    /// Assembles the response, passing any text still held back to `sink`.
+    pub fn finish(mut self, sink: DeltaSink<'_>) -> ApiResponse {
+        if let Some(mut think_tags) = self.think_tags.take() {
+            for delta in think_tags.finish() {
+                self.record(delta, sink);
+            }
+        }
+        let reasoning = self.reasoning.trim();
+        let reasoning = (!reasoning.is_empty()).then(|| reasoning.to_string());
+        if reasoning.is_some() {
+            self.content = self.content.trim_start().to_string();
+        }
+        let tool_calls: Vec<ToolCall> = self
+            .tool_calls
+            .into_iter()
+            .filter(|call| !call.name.is_empty())
+            .map(|call| ToolCall {
+                id: call
+                    .id
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+                call_type: "function".to_string(),
+                function: ToolFunction {
+                    name: call.name,
+                    arguments: if call.arguments.is_empty() {
+                        "{}".to_string()
+                    } else {
+                        call.arguments
+                    },
+                },
+            })
+            .collect();
+
+        let finish_reason = self.finish_reason.unwrap_or_else(|| {
+            if tool_calls.is_empty() { "stop" } else { "tool_calls" }.to_string()
+        });
+        let total_tokens = self
+            .total_tokens
+            .unwrap_or(self.prompt_tokens + self.completion_tokens);
+
+        ApiResponse {
+            id: self.id,
+            content: self.content.clone(),
+            finish_reason: finish_reason.clone(),
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(self.content),
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                    reasoning,
+                    ..Default::default()
+                },
+                finish_reason,
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_accumulator_assembles_text_and_tool_calls() {
+        let seen = Mutex::new(Vec::new());
+        let sink = |delta: StreamDelta| seen.lock().unwrap().push(delta);
+        let mut acc = StreamAccumulator::default();
+
+        acc.push(StreamDelta::Text("Hel".to_string()), &sink);
+        acc.push(StreamDelta::Text("lo".to_string()), &sink);
+        acc.push(
+            StreamDelta::ToolCall {
+                index: 0,
+                id: Some("call_a".to_string()),
+                name: Some("read_file".to_string()),
+                arguments: "{\"pa".to_string(),
+            },
+            &sink,
+        );
+        acc.push(
+            StreamDelta::ToolCall {
+                index: 0,
+                id: None,
+                name: None,
+                arguments: "th\":\"x\"}".to_string(),
+            },
+            &sink,
+        );
+        acc.prompt_tokens = 3;
+        acc.completion_tokens = 4;
+
+        let response = acc.finish(&sink);
+        assert_eq!(seen.lock().unwrap().len(), 4);
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.total_tokens, 7);
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "call_a");
+        assert_eq!(calls[0].function.arguments, "{\"path\":\"x\"}");
+    }
+
+    #[test]
+    fn test_think_tags_are_split_only_when_enabled() {
+        let text = |acc: StreamAccumulator| {
+            let mut acc = acc;
+            for fragment in ["<think>Plan", ".</think>", "Done."] {
+                acc.push(StreamDelta::Text(fragment.to_string()), &|_| {});
+            }
+            let message = acc.finish(&|_| {}).choices[0].message.clone();
+            (message.reasoning, message.content.unwrap_or_default())
+        };
+
+        assert_eq!(
+            text(StreamAccumulator::default()),
+            (None, "<think>Plan.</think>Done.".to_string())
+        );
+        assert_eq!(
+            text(StreamAccumulator::with_think_tags()),
+            (Some("Plan.".to_string()), "Done.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(sse_data("data:[DONE]"), Some("[DONE]"));
+        assert_eq!(sse_data("event: ping"), None);
+    }
+}
@@ -0,0 +1,840 @@
+// volition-agent-core/src/providers/text_tools.rs
+
+//! Prompt-based tool calling for models without native function calling.
+//!
+//! [`TextToolProvider`] wraps another [`Provider`], describes the available tools
+//! in the system prompt, and parses tool invocations out of the model's text.
+//! Invocations that cannot be parsed are reported back to the model so it can
+//! correct them. Streamed responses are passed on as they arrive, with the
+//! invocation blocks held out of the text deltas.
+
+use super::{ModelInfo, Provider, check_tool_choice};
+use super::capabilities::ModelCapabilities;
+use super::streaming::DeltaSink;
+use crate::config::ToolCallingMode;
+use crate::errors::ProviderError;
+use crate::models::chat::{ApiResponse, ChatMessage, Role, StreamDelta, split_tool_results};
+use crate::models::request::{RequestOptions, ToolChoice};
+use crate::models::tools::{ToolCall, ToolDefinition, ToolFunction};
+use anyhow::Result;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+/// How many times a malformed invocation is sent back to the model before giving up.
+const MAX_CORRECTION_ATTEMPTS: usize = 2;
+
+lazy_static! {
+    static ref FENCED_CALL: Regex = Regex::new(r"(?s)```tool_call[ \t]*\r?\n(.*?)```").unwrap();
+    static ref XML_CALL: Regex = Regex::new(r"(?s)<tool_call>(.*?)</tool_call>").unwrap();
+}
+
+/// Result of scanning a model response for tool invocations.
+#[derive(Debug, Default)]
+pub struct ParsedToolText {
+    /// The response text with all invocation blocks removed.
+    pub content: String,
+    /// Invocations that parsed successfully.
+    pub tool_calls: Vec<ToolCall>,
+    /// One message per invocation that could not be parsed.
+    pub errors: Vec<String>,
+}
+
+pub struct TextToolProvider {
+    inner: Box<dyn Provider>,
+    mode: ToolCallingMode,
+}
+
+impl TextToolProvider {
+    pub fn new(inner: Box<dyn Provider>, mode: ToolCallingMode) -> Self {
+        debug!(
+            provider = inner.name(),
+            ?mode,
+            "Wrapping provider with text tool calling"
+        );
+        Self { inner, mode }
+    }
+
+    /// Calls the inner provider, streaming its text to `on_delta` without the
+    /// invocation blocks when a sink is given.
+    async fn call(
+        &self,
+        messages: Vec<ChatMessage>,
+        options: &RequestOptions,
+        on_delta: Option<DeltaSink<'_>>,
+    ) -> Result<ApiResponse> {
+        let Some(on_delta) = on_delta else {
+            return self.inner.get_completion(messages, None, options).await;
+        };
+        let filter = Mutex::new(InvocationFilter::default());
+        let response = self
+            .inner
+            .stream_completion(messages, None, options, &|delta: StreamDelta| match delta {
+                StreamDelta::Text(text) => {
+                    let text = filter.lock().unwrap().push(&text);
+                    if !text.is_empty() {
+                        on_delta(StreamDelta::Text(text));
+                    }
+                }
+                other => on_delta(other),
+            })
+            .await?;
+        let rest = filter.into_inner().unwrap().finish();
+        if !rest.is_empty() {
+            on_delta(StreamDelta::Text(rest));
+        }
+        Ok(response)
+    }
+
+    async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: Option<DeltaSink<'_>>,
+    ) -> Result<ApiResponse> {
+        // Tool choice is handled here; the inner provider never sees native tools.
+        check_tool_choice(options, tools)?;
+        let inner_options = RequestOptions {
+            tool_choice: None,
+            ..options.clone()
+        };
+        let tools = match tools {
+            Some(tools) if !tools.is_empty() && options.tool_choice != Some(ToolChoice::None) => {
+                tools
+            }
+            _ => {
+                return match on_delta {
+                    Some(on_delta) => {
+                        self.inner
+                            .stream_completion(messages, None, &inner_options, on_delta)
+                            .await
+                    }
+                    None => {
+                        self.inner
+                            .get_completion(messages, None, &inner_options)
+                            .await
+                    }
+                };
+            }
+        };
+        if let Some(choice @ (ToolChoice::Required | ToolChoice::Tool(_))) = &options.tool_choice {
+            return Err(ProviderError::Unsupported(format!(
+                "Text tool calling ({:?}) cannot force tool_choice = {}",
+                self.mode, choice
+            ))
+            .into());
+        }
+        if options.response_format.is_some() {
+            return Err(ProviderError::Unsupported(format!(
+                "Text tool calling ({:?}) cannot be combined with a JSON schema response format; pass no tools or tool_choice = none",
+                self.mode
+            ))
+            .into());
+        }
+
+        let mut request = to_text_messages(messages, tools, self.mode);
+        let mut attempt = 0;
+        loop {
+            let mut response = self.call(request.clone(), &inner_options, on_delta).await?;
+            let text = response.content.clone();
+            let parsed = parse_tool_calls(&text, tools);
+
+            if !parsed.errors.is_empty() && attempt < MAX_CORRECTION_ATTEMPTS {
+                attempt += 1;
+                warn!(attempt, errors = ?parsed.errors, "Malformed tool invocation, asking model to correct it");
+                request.push(ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(text),
+                    ..Default::default()
+                });
+                request.push(ChatMessage {
+                    role: Role::User,
+                    content: Some(correction_prompt(&parsed.errors, self.mode)),
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            if !parsed.errors.is_empty() {
+                warn!(errors = ?parsed.errors, "Giving up on malformed tool invocations");
+            }
+
+            let tool_calls = if parsed.tool_calls.is_empty() {
+                None
+            } else {
+                Some(parsed.tool_calls)
+            };
+            let content = if tool_calls.is_some() {
+                parsed.content
+            } else {
+                text
+            };
+            if tool_calls.is_some() && !response.is_truncated() {
+                response.finish_reason = "tool_calls".to_string();
+            }
+            response.content = content.clone();
+            if let Some(choice) = response.choices.first_mut() {
+                choice.message.content = Some(content);
+                choice.message.tool_calls = tool_calls;
+                choice.finish_reason = response.finish_reason.clone();
+            }
+            return Ok(response);
+        }
+    }
+}
+
+/// Holds invocation blocks back from streamed text. Text that might be the
+/// start of a block marker is held until the next fragment decides it.
+#[derive(Debug, Default)]
+struct InvocationFilter {
+    pending: String,
+    /// The marker closing the block being held back, if inside one.
+    closing: Option<&'static str>,
+}
+
+/// Opening markers of invocation blocks and the markers that close them.
+const INVOCATION_MARKERS: &[(&str, &str)] =
+    &[("```tool_call", "```"), ("<tool_call>", "</tool_call>")];
+
+impl InvocationFilter {
+    /// Consumes a text fragment, returning the text to show.
+    fn push(&mut self, fragment: &str) -> String {
+        self.pending.push_str(fragment);
+        let mut shown = String::new();
+        loop {
+            if let Some(closing) = self.closing {
+                match self.pending.find(closing) {
+                    Some(pos) => {
+                        self.pending.drain(..pos + closing.len());
+                        self.closing = None;
+                    }
+                    None => {
+                        let keep = partial_marker_len(&self.pending, &[closing]);
+                        self.pending.drain(..self.pending.len() - keep);
+                        return shown;
+                    }
+                }
+                continue;
+            }
+            let next = INVOCATION_MARKERS
+                .iter()
+                .filter_map(|&(opening, closing)| {
+                    self.pending
+                        .find(opening)
+                        .map(|pos| (pos, opening, closing))
+                })
+                .min_by_key(|&(pos, ..)| pos);
+            match next {
+                Some((pos, opening, closing)) => {
+                    shown.extend(self.pending.drain(..pos));
+                    self.pending.drain(..opening.len());
+                    self.closing = Some(closing);
+                }
+                None => {
+                    let openings: Vec<&str> = INVOCATION_MARKERS.iter().map(|m| m.0).collect();
+                    let keep = partial_marker_len(&self.pending, &openings);
+                    shown.extend(self.pending.drain(..self.pending.len() - keep));
+                    return shown;
+                }
+            }
+        }
+    }
+
+    /// Returns the text still held back once the stream has ended, unless it
+    /// belongs to an unclosed block.
+    fn finish(self) -> String {
+        if self.closing.is_some() {
+            String::new()
+        } else {
+            self.pending
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that could still grow into one of
+/// `markers`.
+fn partial_marker_len(text: &str, markers: &[&str]) -> usize {
+    markers
+        .iter()
+        .flat_map(|marker| (1..marker.len()).filter(|&n| text.ends_with(&marker[..n])))
+        .max()
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl Provider for TextToolProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn get_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse> {
+        self.complete(messages, tools, options, None).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<&[ToolDefinition]>,
+        options: &RequestOptions,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ApiResponse> {
+        self.complete(messages, tools, options, Some(on_delta))
+            .await
+    }
+}
+
+/// Builds the system prompt section that describes the tools and the invocation format.
+pub fn tool_prompt(tools: &[ToolDefinition], mode: ToolCallingMode) -> String {
+    let mut prompt = String::from(
+        "# Tools\nYou can call the following tools. Each tool is described by its name, \
+         a description, and a JSON schema for its arguments.\n\n",
+    );
+    for tool in tools {
+        let schema = serde_json::to_string(&tool.parameters).unwrap_or_else(|_| "{}".to_string());
+        prompt.push_str(&format!(
+            "## {}\n{}\nArguments schema: {}\n\n",
+            tool.name, tool.description, schema
+        ));
+    }
+    prompt.push_str(
+        "To call a tool, reply with one block per call in exactly this format, \
+         with `arguments` as a JSON object:\n\n",
+    );
+    prompt.push_str(&render_invocation(
+        "tool_name",
+        &json!({"arg": "value"}),
+        mode,
+    ));
+    prompt.push_str(
+        "\n\nYou may call several tools in one reply. The results will be sent back to you. \
+         When you do not need a tool, answer normally without any tool blocks.",
+    );
+    prompt
+}
+
+/// Parses every tool invocation (fenced ```tool_call blocks or `<tool_call>` tags) in `text`.
+///
+/// Both formats are accepted regardless of the configured mode, since models
+/// frequently drift between them.
+pub fn parse_tool_calls(text: &str, tools: &[ToolDefinition]) -> ParsedToolText {
+    let mut parsed = ParsedToolText::default();
+    let mut content = text.to_string();
+
+    for pattern in [&*FENCED_CALL, &*XML_CALL] {
+        for captures in pattern.captures_iter(text) {
+            let body = captures[1].trim();
+            match parse_invocation(body, tools) {
+                Ok(calls) => parsed.tool_calls.extend(calls),
+                Err(error) => parsed.errors.push(error),
+            }
+        }
+        content = pattern.replace_all(&content, "").to_string();
+    }
+
+    parsed.content = content.trim().to_string();
+    parsed
+}
+
+fn parse_invocation(body: &str, tools: &[ToolDefinition]) -> Result<Vec<ToolCall>, String> {
+    let value: Value = serde_json::from_str(body)
+        .map_err(|e| format!("Invalid JSON in tool invocation `{}`: {}", body, e))?;
+    let invocations = match value {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    invocations
+        .into_iter()
+        .map(|invocation| {
+            let name = invocation
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| {
+                    format!(
+                        "Tool invocation `{}` is missing a string `name`",
+                        invocation
+                    )
+                })?;
+            if !tools.iter().any(|t| t.name == name) {
+                return Err(format!("Unknown tool `{}`", name));
+            }
+            let arguments = match invocation.get("arguments") {
+                None | Some(Value::Null) => json!({}),
+                Some(Value::Object(map)) => Value::Object(map.clone()),
+                // Some models double-encode the arguments as a JSON string.
+                Some(Value::String(s)) => match serde_json::from_str::<Value>(s) {
+                    Ok(obj @ Value::Object(_)) => obj,
+                    _ => {
+                        return Err(format!(
+                            "Arguments for tool `{}` must be a JSON object",
+                            name
+                        ));
+                    }
+                },
+                Some(_) => {
+                    return Err(format!(
+                        "Arguments for tool `{}` must be a JSON object",
+                        name
+                    ));
+                }
+            };
+            Ok(ToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call_type: "function".to_string(),
+                function: ToolFunction {
+                    name: name.to_string(),
+                    arguments: arguments.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+fn render_invocation(name: &str, arguments: &Value, mode: ToolCallingMode) -> String {
+    let body = json!({ "name": name, "arguments": arguments });
+    match mode {
+        ToolCallingMode::Xml => format!("<tool_call>{}</tool_call>", body),
+        _ => format!("```tool_call\n{}\n```", body),
+    }
+}
+
+fn correction_prompt(errors: &[String], mode: ToolCallingMode) -> String {
+    format!(
+        "Your tool invocation could not be processed:\n- {}\n\nPlease resend the tool call(s) using exactly this format:\n{}",
+        errors.join("\n- "),
+        render_invocation("tool_name", &json!({"arg": "value"}), mode)
+    )
+}
+
+/// Rewrites a conversation so it can be sent to a model without native tool support.
+///
+/// The tool prompt is appended to the system message, assistant tool calls are
+/// rendered as invocation blocks, and tool results, including tool-result parts,
+/// become user messages. Other parts such as images are passed through.
+fn to_text_messages(
+    messages: Vec<ChatMessage>,
+    tools: &[ToolDefinition],
+    mode: ToolCallingMode,
+) -> Vec<ChatMessage> {
+    let prompt = tool_prompt(tools, mode);
+    let mut call_names: HashMap<String, String> = HashMap::new();
+    let mut converted = Vec::with_capacity(messages.len() + 1);
+
+    if !messages.iter().any(|m| m.role == Role::System) {
+        converted.push(ChatMessage {
+            role: Role::System,
+            content: Some(prompt.clone()),
+            ..Default::default()
+        });
+    }
+
+    for message in split_tool_results(&messages) {
+        let message = message.with_text_in_content();
+        match message.role {
+            Role::System => {
+                let content = match message.content.as_deref() {
+                    Some(existing) if !existing.is_empty() => format!("{}\n\n{}", existing, prompt),
+                    _ => prompt.clone(),
+                };
+                converted.push(ChatMessage {
+                    content: Some(content),
+                    ..message
+                });
+            }
+            Role::Assistant if message.tool_calls.is_some() => {
+                let mut blocks: Vec<String> = message
+                    .content
+                    .iter()
+                    .filter(|c| !c.is_empty())
+                    .cloned()
+                    .collect();
+                for call in message.tool_calls.unwrap_or_default() {
+                    let arguments =
+                        serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                    blocks.push(render_invocation(&call.function.name, &arguments, mode));
+                    call_names.insert(call.id, call.function.name);
+                }
+                converted.push(ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(blocks.join("\n\n")),
+                    parts: message.parts,
+                    ..Default::default()
+                });
+            }
+            Role::Tool => {
+                let name = message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| call_names.get(id))
+                    .map(String::as_str)
+                    .unwrap_or("tool");
+                converted.push(ChatMessage {
+                    role: Role::User,
+                    content: Some(format!(
+                        "Result of `{}`:\n{}",
+                        name,
+                        message.content.unwrap_or_default()
+                    )),
+                    parts: message.parts,
+                    ..Default::default()
+                });
+            }
+            _ => converted.push(message),
+        }
+    }
+
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat::{Choice, ContentPart, ImageSource};
+    use crate::models::tools::{ToolParameter, ToolParameterType, ToolParametersDefinition};
+    use std::sync::{Arc, Mutex};
+
+    fn read_file_tool() -> Vec<ToolDefinition> {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "path".to_string(),
+            ToolParameter {
+                param_type: ToolParameterType::String,
+                description: "Path to read".to_string(),
+                enum_values: None,
+                items: None,
+            },
+        );
+        vec![ToolDefinition {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            parameters: ToolParametersDefinition {
+                param_type: "object".to_string(),
+                properties,
+                required: vec!["path".to_string()],
+            },
+        }]
+    }
+
+    #[test]
+    fn test_parse_fenced_json_call() {
+        let text = "Let me look.\n```tool_call\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"src/lib.rs\"}}\n```";
+        let parsed = parse_tool_calls(text, &read_file_tool());
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.content, "Let me look.");
+        assert_eq!(parsed.tool_calls.len(), 1);
+        assert_eq!(parsed.tool_calls[0].function.name, "read_file");
+        let args: Value = serde_json::from_str(&parsed.tool_calls[0].function.arguments).unwrap();
+        assert_eq!(args["path"], "src/lib.rs");
+    }
+
+    #[test]
+    fn test_parse_xml_calls_with_unique_ids() {
+        let text = "<tool_call>{\"name\": \"read_file\", \"arguments\": {\"path\": \"a\"}}</tool_call>\n<tool_call>{\"name\": \"read_file\", \"arguments\": \"{\\\"path\\\": \\\"b\\\"}\"}</tool_call>";
+        let parsed = parse_tool_calls(text, &read_file_tool());
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.tool_calls.len(), 2);
+        assert_ne!(parsed.tool_calls[0].id, parsed.tool_calls[1].id);
+        assert!(parsed.content.is_empty());
+    }
+
+    #[test]
+    fn test_parse_reports_malformed_invocations() {
+        let text = "```tool_call\n{\"name\": \"read_file\", \"arguments\": {\"path\": \n```\n<tool_call>{\"name\": \"delete_everything\"}</tool_call>";
+        let parsed = parse_tool_calls(text, &read_file_tool());
+        assert!(parsed.tool_calls.is_empty());
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(parsed.errors[0].contains("Invalid JSON"));
+        assert!(parsed.errors[1].contains("Unknown tool `delete_everything`"));
+    }
+
+    #[test]
+    fn test_plain_json_code_is_not_a_call() {
+        let text = "Here is an example:\n```json\n{\"name\": \"read_file\"}\n```";
+        let parsed = parse_tool_calls(text, &read_file_tool());
+        assert!(parsed.tool_calls.is_empty());
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn test_to_text_messages_renders_tool_round_trip() {
+        let messages = vec![
+            ChatMessage {
+                role: Role::User,
+                content: Some("Read it".to_string()),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Assistant,
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    call_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: "read_file".to_string(),
+                        arguments: "{\"path\":\"a\"}".to_string(),
+                    },
+                }]),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: Role::Tool,
+                content: Some("contents".to_string()),
+                tool_call_id: Some("call_1".to_string()),
+                ..Default::default()
+            },
+        ];
+        let converted = to_text_messages(messages, &read_file_tool(), ToolCallingMode::Xml);
+        assert_eq!(converted.len(), 4);
+        assert_eq!(converted[0].role, Role::System);
+        assert!(
+            converted[0]
+                .content
+                .as_deref()
+                .unwrap()
+                .contains("## read_file")
+        );
+        assert!(converted[2].tool_calls.is_none());
+        assert!(
+            converted[2]
+                .content
+                .as_deref()
+                .unwrap()
+                .starts_with("<tool_call>")
+        );
+        assert_eq!(converted[3].role, Role::User);
+        assert_eq!(
+            converted[3].content.as_deref(),
+            Some("Result of `read_file`:\ncontents")
+        );
+    }
+
+    #[test]
+    fn test_to_text_messages_renders_tool_result_parts_and_keeps_images() {
+        let image = ContentPart::Image {
+            source: ImageSource::Base64 {
+                media_type: "image/png".to_string(),
+                data: "iVBORw0KGgo=".to_string(),
+            },
+        };
+        let messages = vec![ChatMessage {
+            role: Role::User,
+            content: Some("Compare with this.".to_string()),
+            parts: vec![
+                ContentPart::ToolResult {
+                    tool_call_id: "call_1".to_string(),
+                    content: "contents".to_string(),
+                },
+                image.clone(),
+            ],
+            ..Default::default()
+        }];
+        let converted = to_text_messages(messages, &read_file_tool(), ToolCallingMode::Json);
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[1].role, Role::User);
+        assert_eq!(
+            converted[1].content.as_deref(),
+            Some("Result of `tool`:\ncontents")
+        );
+        assert_eq!(converted[2].content.as_deref(), Some("Compare with this."));
+        assert_eq!(converted[2].parts, vec![image]);
+    }
+
+    #[derive(Clone)]
+    struct ScriptedProvider {
+        replies: Arc<Mutex<Vec<&'static str>>>,
+        requests: Arc<Mutex<Vec<Vec<ChatMessage>>>>,
+        finish_reason: &'static str,
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        async fn get_completion(
+            &self,
+            messages: Vec<ChatMessage>,
+            tools: Option<&[ToolDefinition]>,
+            _options: &RequestOptions,
+        ) -> Result<ApiResponse> {
+            assert!(
+                tools.is_none(),
+                "inner provider must not receive native tools"
+            );
+            self.requests.lock().unwrap().push(messages);
+            let text = self.replies.lock().unwrap().remove(0).to_string();
+            Ok(ApiResponse {
+                id: "resp".to_string(),
+                content: text.clone(),
+                finish_reason: self.finish_reason.to_string(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                choices: vec![Choice {
+                    index: 0,
+                    message: ChatMessage {
+                        role: Role::Assistant,
+                        content: Some(text),
+                        ..Default::default()
+                    },
+                    finish_reason: self.finish_reason.to_string(),
+                }],
+            })
+        }
+
+        /// Sends the reply in three-character deltas.
+        async fn stream_completion(
+            &self,
+            messages: Vec<ChatMessage>,
+            tools: Option<&[ToolDefinition]>,
+            options: &RequestOptions,
+            on_delta: DeltaSink<'_>,
+        ) -> Result<ApiResponse> {
+            let response = self.get_completion(messages, tools, options).await?;
+            let chars: Vec<char> = response.content.chars().collect();
+            for chunk in chars.chunks(3) {
+                on_delta(StreamDelta::Text(chunk.iter().collect()));
+            }
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_text_reaches_the_sink_without_invocations() {
+        let inner = ScriptedProvider {
+            replies: Arc::new(Mutex::new(vec![
+                "Let me look.\n```tool_call\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"a\"}}\n```\nDone <b>",
+            ])),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            finish_reason: "stop",
+        };
+        let provider = TextToolProvider::new(Box::new(inner), ToolCallingMode::Json);
+        let user = ChatMessage {
+            role: Role::User,
+            content: Some("Read a".to_string()),
+            ..Default::default()
+        };
+        let shown = Mutex::new(String::new());
+        let response = provider
+            .stream_completion(
+                vec![user],
+                Some(&read_file_tool()),
+                &RequestOptions::default(),
+                &|delta| {
+                    if let StreamDelta::Text(text) = delta {
+                        shown.lock().unwrap().push_str(&text);
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(*shown.lock().unwrap(), "Let me look.\n\nDone <b>");
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(response.finish_reason, "tool_calls");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_response_keeps_its_finish_reason() {
+        let inner = ScriptedProvider {
+            replies: Arc::new(Mutex::new(vec![
+                "```tool_call\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"a\"}}\n```\nThen I will",
+            ])),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            finish_reason: "length",
+        };
+        let provider = TextToolProvider::new(Box::new(inner), ToolCallingMode::Json);
+        let user = ChatMessage {
+            role: Role::User,
+            content: Some("Read a".to_string()),
+            ..Default::default()
+        };
+        let response = provider
+            .get_completion(
+                vec![user],
+                Some(&read_file_tool()),
+                &RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert!(response.choices[0].message.tool_calls.is_some());
+        assert!(response.is_truncated());
+        assert_eq!(response.choices[0].finish_reason, "length");
+    }
+
+    #[test]
+    fn test_invocation_filter_holds_back_xml_blocks_across_fragments() {
+        let mut filter = InvocationFilter::default();
+        let mut shown = String::new();
+        for fragment in [
+            "Hi <",
+            "tool_",
+            "call>{\"name\"",
+            ": \"x\"}</tool",
+            "_call> <",
+            "b>",
+        ] {
+            shown.push_str(&filter.push(fragment));
+        }
+        shown.push_str(&filter.finish());
+        assert_eq!(shown, "Hi  <b>");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_invocation_is_sent_back_for_correction() {
+        let inner = ScriptedProvider {
+            replies: Arc::new(Mutex::new(vec![
+                "```tool_call\n{\"name\": \"read_file\", \"arguments\": [1]}\n```",
+                "```tool_call\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"a\"}}\n```",
+            ])),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            finish_reason: "stop",
+        };
+        let provider = TextToolProvider::new(Box::new(inner.clone()), ToolCallingMode::Json);
+        let user = ChatMessage {
+            role: Role::User,
+            content: Some("Read a".to_string()),
+            ..Default::default()
+        };
+        let response = provider
+            .get_completion(vec![user], Some(&read_file_tool()), &RequestOptions::default())
+            .await
+            .unwrap();
+
+        let requests = inner.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let correction = requests[1].last().unwrap();
+        assert_eq!(correction.role, Role::User);
+        assert!(
+            correction
+                .content
+                .as_deref()
+                .unwrap()
+                .contains("must be a JSON object")
+        );
+
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(response.finish_reason, "tool_calls");
+    }
+}
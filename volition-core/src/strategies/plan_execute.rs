@@ -2,7 +2,7 @@
 use super::{DelegationResult, NextStep, Strategy, StrategyConfig};
 use crate::UserInteraction;
 use crate::errors::AgentError;
-use crate::models::chat::{ApiResponse, ChatMessage};
+use crate::models::chat::{ApiResponse, ChatMessage, Role};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use tracing::{debug, info, instrument};
@@ -52,8 +52,8 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
             .messages
             .iter()
             .rev()
-            .find(|m| m.role == "user")
-            .and_then(|m| m.content.as_ref())
+            .find(|m| m.role == Role::User)
+            .map(|m| m.text())
             .ok_or_else(|| {
                 AgentError::Strategy(
                     "Could not find the current user task message in the provided state history"
@@ -63,12 +63,12 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
 
         let planning_messages = vec![
             ChatMessage {
-                role: "system".to_string(),
+                role: Role::System,
                 content: Some("You are a planning assistant. Create a concise, step-by-step plan to accomplish the user's task. Output ONLY the plan steps.".to_string()),
                 ..Default::default()
             },
             ChatMessage {
-                role: "user".to_string(),
+                role: Role::User,
                 content: Some(format!("Create a plan for this task: {}", current_task)), // Rephrase slightly
                 ..Default::default()
             },
@@ -77,6 +77,7 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
         // Append planning context instead of overwriting
         agent_state.messages.extend(planning_messages);
         agent_state.pending_tool_calls.clear();
+        agent_state.request_options.phase = Some("planning".to_string());
         Ok(NextStep::CallApi(agent_state.clone()))
     }
 
@@ -117,12 +118,12 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
 
                 let execution_messages = vec![
                     ChatMessage {
-                        role: "system".to_string(),
+                        role: Role::System,
                         content: Some("You are an execution assistant. Execute the given plan step-by-step using the available tools (MCP servers). Request tool calls as needed.".to_string()),
                         ..Default::default()
                     },
                     ChatMessage {
-                        role: "user".to_string(),
+                        role: Role::User,
                         content: Some(format!("Execute this plan:\n---\n{}\n---", plan_content)),
                         ..Default::default()
                     },
@@ -131,6 +132,7 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
                 // Append execution context instead of overwriting
                 agent_state.messages.extend(execution_messages);
                 agent_state.pending_tool_calls.clear();
+                agent_state.request_options.phase = Some("execution".to_string());
                 Ok(NextStep::CallApi(agent_state.clone()))
             }
             PlanExecutePhase::Execution => {
@@ -169,6 +171,7 @@ impl<UI: UserInteraction + 'static> Strategy<UI> for PlanExecuteStrategy {
             ));
         }
         agent_state.add_tool_results(tool_results);
+        agent_state.request_options.phase = Some("execution".to_string());
         Ok(NextStep::CallApi(agent_state.clone()))
     }
 
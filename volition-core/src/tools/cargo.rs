@@ -52,10 +52,7 @@ mod tests {
 
     fn test_working_dir() -> PathBuf {
         tempdir()
-            .map(|d| {
-                let path = d.into_path();
-                path
-            })
+            .map(|d| d.into_path())
             .unwrap_or_else(|_| PathBuf::from("."))
     }
 
@@ -57,11 +57,11 @@ mod tests {
         Command::new("git").current_dir(&dir).arg("init").output()?;
         Command::new("git")
             .current_dir(&dir)
-            .args(&["config", "user.email", "test@example.com"])
+            .args(["config", "user.email", "test@example.com"])
             .output()?;
         Command::new("git")
             .current_dir(&dir)
-            .args(&["config", "user.name", "Test User"])
+            .args(["config", "user.name", "Test User"])
             .output()?;
         fs::write(dir.join("README.md"), "Initial commit")?;
         Command::new("git")